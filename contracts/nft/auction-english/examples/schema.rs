@@ -12,10 +12,12 @@ fn main() {
     export_schema(&schema_for!(msg::InstantiateMsg), &out_dir);
     export_schema(&schema_for!(msg::ExecuteMsg), &out_dir);
     export_schema(&schema_for!(msg::QueryMsg), &out_dir);
+    export_schema(&schema_for!(msg::MigrateMsg), &out_dir);
 
     export_schema(&schema_for!(msg::QueryOptions<msg::TokenTimestampOffset>), &out_dir);
     export_schema(&schema_for!(msg::QueryOptions<msg::TokenPriceOffset>), &out_dir);
     export_schema(&schema_for!(msg::ConfigResponse), &out_dir);
     export_schema(&schema_for!(msg::AuctionResponse), &out_dir);
     export_schema(&schema_for!(msg::AuctionsResponse), &out_dir);
+    export_schema(&schema_for!(msg::SettlementPreviewResponse), &out_dir);
 }
\ No newline at end of file
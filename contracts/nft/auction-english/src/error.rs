@@ -13,12 +13,21 @@ pub enum ContractError {
     #[error("Invalid price")]
     InvalidPrice {},
 
+    #[error("Collection not approved: {0}")]
+    UnapprovedCollection(String),
+
+    #[error("Denom not approved: {0}")]
+    UnapprovedDenom(String),
+
     #[error("{0}")]
     BidPaymentError(#[from] PaymentError),
 
     #[error("Incorrect bid payment: expected {0}, actual {1}")]
     IncorrectBidPayment(Uint128, Uint128),
 
+    #[error("Invalid max price: max_price {0} < price {1}")]
+    InvalidMaxPrice(Uint128, Uint128),
+
     #[error("Invalid reserve price: reserve_price {0} < starting_price {1}")]
     InvalidReservePrice(Uint128, Uint128),
 
@@ -37,9 +46,15 @@ pub enum ContractError {
     #[error("Auction bid too low")]
     BidTooLow {},
 
+    #[error("Bid gate restriction: {0}")]
+    BidGateRestriction(String),
+
     #[error("Reserve price restriction: {0}")]
     ReservePriceRestriction(String),
 
     #[error("Invalid config: {0}")]
     InvalidConfig(String),
+
+    #[error("Invalid migration: {0}")]
+    InvalidMigration(String),
 }
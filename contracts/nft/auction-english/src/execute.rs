@@ -1,25 +1,33 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{Decimal, DepsMut, Env, Event, MessageInfo, Uint128, Response};
-use cw2::set_contract_version;
+use cosmwasm_std::{Addr, Coin, Decimal, Deps, DepsMut, Env, Event, MessageInfo, Order, StdResult, Uint128, Response};
+use cw2::{get_contract_version, set_contract_version};
 use cw_utils::{maybe_addr, must_pay, nonpayable};
+use semver::Version;
 
 use crate::error::ContractError;
 use crate::helpers::{
     map_validate, finalize_sale, price_validate, only_seller, only_owner,
     only_operator, transfer_nft, transfer_token, validate_auction_times,
-    validate_config
+    validate_config, load_max_bid, refund_bid_escrow, only_gated_bidder,
+    validate_collection_denom
 };
-use crate::msg::{InstantiateMsg, ExecuteMsg};
+use crate::legacy::migrate_v0_1_storage;
+use crate::msg::{InstantiateMsg, ExecuteMsg, MigrateMsg, BidGateMsg};
 use crate::state::{
-    Config, CONFIG, TokenId,
-    Auction, AuctionStatus, auctions, AuctionBid,
+    Config, CONFIG, DenomConfig, TokenId,
+    Auction, AuctionStatus, auctions, AuctionBid, BidGate, MAX_BIDS,
+    auction_key,
 };
 
 // Version info for migration info
 const CONTRACT_NAME: &str = "crates.io:marketplace-v2";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// Settle limits
+const DEFAULT_SETTLE_LIMIT: u32 = 10;
+const MAX_SETTLE_LIMIT: u32 = 30;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -31,17 +39,16 @@ pub fn instantiate(
 
     let api = deps.api;
     let config = Config {
-        cw721_address: api.addr_validate(&msg.cw721_address)?,
-        denom: msg.denom,
+        collections: map_validate(deps.api, &msg.collections)?,
+        denoms: msg.denoms,
         collector_address: api.addr_validate(&msg.collector_address)?,
         trading_fee_percent: Decimal::percent(msg.trading_fee_bps),
         operators: map_validate(deps.api, &msg.operators)?,
-        min_price: msg.min_price,
-        min_bid_increment: msg.min_bid_increment,
         min_duration: msg.min_duration,
         max_duration: msg.max_duration,
         closed_duration: msg.closed_duration,
         buffer_duration: msg.buffer_duration,
+        keeper_fee_percent: Decimal::percent(msg.keeper_fee_bps),
     };
     validate_config(&config)?;
     CONFIG.save(deps.storage, &config)?;
@@ -62,41 +69,46 @@ pub fn execute(
 
     match msg {
         ExecuteMsg::UpdateConfig {
+            collections,
+            denoms,
             collector_address,
             trading_fee_bps,
             operators,
-            min_price,
-            min_bid_increment,
             min_duration,
             max_duration,
             closed_duration,
             buffer_duration,
+            keeper_fee_bps,
         } => execute_update_config(
             deps,
             env,
             info,
+            collections,
+            denoms,
             collector_address,
             trading_fee_bps,
             operators,
-            min_price,
-            min_bid_increment,
             min_duration,
             max_duration,
             closed_duration,
             buffer_duration,
+            keeper_fee_bps,
         ),
         ExecuteMsg::SetAuction {
+            collection,
             token_id,
             start_time,
             end_time,
             starting_price,
             reserve_price,
             funds_recipient,
+            bid_gate,
         } => execute_set_auction(
             deps,
             env,
             info,
             Auction {
+                collection: api.addr_validate(&collection)?,
                 token_id,
                 seller: message_info.sender,
                 start_time,
@@ -104,49 +116,128 @@ pub fn execute(
                 starting_price,
                 reserve_price,
                 funds_recipient: maybe_addr(api, funds_recipient)?,
-                highest_bid: None
+                highest_bid: None,
+                bid_gate: match bid_gate {
+                    Some(BidGateMsg::Whitelist(whitelist)) => Some(BidGate::Whitelist(api.addr_validate(&whitelist)?)),
+                    Some(BidGateMsg::Collection(collection)) => Some(BidGate::Collection(api.addr_validate(&collection)?)),
+                    None => None,
+                },
             },
         ),
         ExecuteMsg::SetAuctionBid {
+            collection,
             token_id,
             price,
+            max_price,
+            proof,
         } => execute_set_auction_bid(
             deps,
             env,
             info,
+            api.addr_validate(&collection)?,
             token_id,
             AuctionBid {
                 bidder: message_info.sender,
                 price,
             },
+            max_price,
+            proof,
         ),
         ExecuteMsg::CloseAuction {
+            collection,
             token_id,
             accept_highest_bid,
         } => execute_close_auction(
             deps,
             env,
             info,
+            api.addr_validate(&collection)?,
             token_id,
             accept_highest_bid,
         ),
         ExecuteMsg::FinalizeAuction {
+            collection,
             token_id,
         } => execute_finalize_auction(
             deps,
             env,
             info,
+            api.addr_validate(&collection)?,
             token_id,
         ),
         ExecuteMsg::VoidAuction {
+            collection,
             token_id,
         } => execute_void_auction(
             deps,
             env,
             info,
+            api.addr_validate(&collection)?,
             token_id,
         ),
+        ExecuteMsg::SettleExpired {
+            limit,
+        } => execute_settle_expired(
+            deps,
+            env,
+            info,
+            limit,
+        ),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(
+    deps: DepsMut,
+    _env: Env,
+    msg: MigrateMsg,
+) -> Result<Response, ContractError> {
+    let prev_contract_version = get_contract_version(deps.storage)?;
+    if prev_contract_version.contract != CONTRACT_NAME {
+        return Err(ContractError::InvalidMigration(format!("cannot migrate from {}", prev_contract_version.contract)));
+    }
+    let prev_version = parse_version(&prev_contract_version.version)?;
+    let next_version = parse_version(CONTRACT_VERSION)?;
+    if prev_version > next_version {
+        return Err(ContractError::InvalidMigration(format!("cannot migrate to older version {}", next_version)));
     }
+
+    let mut response = Response::new();
+
+    // Versions prior to 0.2.0 support a single collection and denom, and key Auctions by token_id
+    if prev_version < Version::new(0, 2, 0) {
+        let num_auctions = migrate_v0_1_storage(deps.storage)?;
+        let event = Event::new("migrate-storage")
+            .add_attribute("num_auctions", num_auctions.to_string());
+        response.events.push(event);
+    }
+
+    let mut config = CONFIG.load(deps.storage)?;
+    if let Some(_collections) = msg.collections {
+        config.collections = map_validate(deps.api, &_collections)?;
+    }
+    if let Some(_denoms) = msg.denoms {
+        config.denoms = _denoms;
+    }
+    if let Some(_keeper_fee_bps) = msg.keeper_fee_bps {
+        config.keeper_fee_percent = Decimal::percent(_keeper_fee_bps);
+    }
+    validate_config(&config)?;
+    CONFIG.save(deps.storage, &config)?;
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    let event = Event::new("contract-migrated")
+        .add_attribute("prev-version", prev_contract_version.version)
+        .add_attribute("next-version", CONTRACT_VERSION);
+    response.events.push(event);
+    Ok(response)
+}
+
+fn parse_version(version: &str) -> Result<Version, ContractError> {
+    version
+        .parse()
+        .map_err(|_| ContractError::InvalidMigration(format!("invalid version {}", version)))
 }
 
 /// An operator may update the marketplace config
@@ -154,20 +245,27 @@ pub fn execute_update_config(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
+    collections: Option<Vec<String>>,
+    denoms: Option<Vec<DenomConfig>>,
     collector_address: Option<String>,
     trading_fee_bps: Option<u64>,
     operators: Option<Vec<String>>,
-    min_price: Option<Uint128>,
-    min_bid_increment: Option<Uint128>,
     min_duration: Option<u64>,
     max_duration: Option<u64>,
     closed_duration: Option<u64>,
     buffer_duration: Option<u64>,
+    keeper_fee_bps: Option<u64>,
 ) -> Result<Response, ContractError> {
     let mut config = CONFIG.load(deps.storage)?;
     
     only_operator(&info, &config)?;
     
+    if let Some(_collections) = collections {
+        config.collections = map_validate(deps.api, &_collections)?;
+    }
+    if let Some(_denoms) = denoms {
+        config.denoms = _denoms;
+    }
     if let Some(_collector_address) = collector_address {
         config.collector_address = deps.api.addr_validate(&_collector_address)?;
     }
@@ -177,12 +275,6 @@ pub fn execute_update_config(
     if let Some(_operators) = operators {
         config.operators = map_validate(deps.api, &_operators)?;
     }
-    if let Some(_min_price) = min_price {
-        config.min_price = _min_price;
-    }
-    if let Some(_min_bid_increment) = min_bid_increment {
-        config.min_bid_increment = _min_bid_increment;
-    }
     if let Some(_min_duration) = min_duration {
         config.min_duration = _min_duration;
     }
@@ -195,6 +287,9 @@ pub fn execute_update_config(
     if let Some(_buffer_duration) = buffer_duration {
         config.buffer_duration = _buffer_duration;
     }
+    if let Some(_keeper_fee_bps) = keeper_fee_bps {
+        config.keeper_fee_percent = Decimal::percent(_keeper_fee_bps);
+    }
     validate_config(&config)?;
     CONFIG.save(deps.storage, &config)?;
     Ok(Response::new())
@@ -210,31 +305,33 @@ pub fn execute_set_auction(
     nonpayable(&info)?;
     
     let config = CONFIG.load(deps.storage)?;
+    let denom_config = validate_collection_denom(&auction, &config)?;
     validate_auction_times(&auction, &config, &env.block.time)?;
     
-    price_validate(&auction.starting_price, &config)?;
+    price_validate(&auction.starting_price, denom_config)?;
     if let Some(_reserve_price) = &auction.reserve_price {
-        price_validate(&_reserve_price, &config)?;
+        price_validate(&_reserve_price, denom_config)?;
         if _reserve_price.amount < auction.starting_price.amount {
             return Err(ContractError::InvalidReservePrice(_reserve_price.amount, auction.starting_price.amount));
         }
     }
 
-    only_owner(deps.as_ref(), &info, &config.cw721_address, &auction.token_id)?;
+    only_owner(deps.as_ref(), &info, &auction.collection, &auction.token_id)?;
 
-    let existing_auction = auctions().may_load(deps.storage, auction.token_id.clone())?;
+    let key = auction_key(&auction.collection, &auction.token_id);
+    let existing_auction = auctions().may_load(deps.storage, key.clone())?;
     if let Some(_existing_auction) = existing_auction {
         return Err(ContractError::AlreadyExists(auction.token_id.clone()));
     }
 
-    auctions().save(deps.storage, auction.token_id.clone(), &auction)?;
+    auctions().save(deps.storage, key, &auction)?;
 
     let mut response = Response::new();
 
-    transfer_nft(&auction.token_id, &env.contract.address, &config.cw721_address, &mut response)?;
+    transfer_nft(&auction.token_id, &env.contract.address, &auction.collection, &mut response)?;
 
     let event = Event::new("set-auction")
-        .add_attribute("collection", config.cw721_address.to_string())
+        .add_attribute("collection", auction.collection.to_string())
         .add_attribute("token_id", auction.token_id.to_string())
         .add_attribute("seller", auction.seller)
         .add_attribute("start_time", auction.start_time.to_string())
@@ -249,43 +346,141 @@ pub fn execute_set_auction_bid(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    collection: Addr,
     token_id: TokenId,
     auction_bid: AuctionBid,
+    max_price: Option<Coin>,
+    proof: Option<Vec<String>>,
 ) -> Result<Response, ContractError> {
     let mut response = Response::new();
 
     let config = CONFIG.load(deps.storage)?; 
 
     // Validate auction exists, and is open
-    let mut auction = auctions().load(deps.storage, token_id.clone())?;
+    let key = auction_key(&collection, &token_id);
+    let mut auction = auctions().load(deps.storage, key.clone())?;
     let auction_status = auction.get_auction_status(&env.block.time, config.closed_duration);
     match &auction_status {
         AuctionStatus::Open => {},
         _ => return Err(ContractError::InvalidStatus(auction_status.to_string())),
     }
 
+    // Validate bidder is allowed by the bid gate
+    only_gated_bidder(deps.as_ref(), &auction.bid_gate, &auction_bid.bidder, proof)?;
+
+    let denom = &auction.starting_price.denom;
+    let denom_config = config
+        .denom_config(denom)
+        .ok_or_else(|| ContractError::UnapprovedDenom(denom.clone()))?;
+
+    // The highest bidder can raise their max bid by topping up their escrow, which leaves
+    // their visible bid unchanged. A highest bidder that is proxy bidding can only raise their max bid
+    let prev_max_bid = load_max_bid(deps.storage, &auction)?;
+    let is_highest_bidder = auction.highest_bid.as_ref().map_or(false, |bid| bid.bidder == auction_bid.bidder);
+    if is_highest_bidder && (max_price.is_some() || MAX_BIDS.has(deps.storage, key.clone())) {
+        let _prev_max_bid = prev_max_bid.unwrap();
+        let max_price = match max_price {
+            Some(_max_price) if _max_price.amount > _prev_max_bid.price.amount => _max_price,
+            _ => return Err(ContractError::BidTooLow {}),
+        };
+        price_validate(&max_price, denom_config)?;
+
+        let top_up_amount = max_price.amount - _prev_max_bid.price.amount;
+        let payment_amount = must_pay(&info, denom)?;
+        if top_up_amount != payment_amount {
+            return Err(ContractError::IncorrectBidPayment(top_up_amount, payment_amount));
+        }
+        MAX_BIDS.save(deps.storage, key, &AuctionBid {
+            bidder: auction_bid.bidder.clone(),
+            price: max_price,
+        })?;
+
+        let highest_bid = auction.highest_bid.unwrap();
+        let event = Event::new("raise-max-auction-bid")
+            .add_attribute("collection", &collection.to_string())
+            .add_attribute("token_id", &token_id.to_string())
+            .add_attribute("bidder", &highest_bid.bidder)
+            .add_attribute("price", &highest_bid.price.to_string());
+        response.events.push(event);
+
+        return Ok(response);
+    }
+
     // Validate bid is higher than the minimum viable bid
-    if auction_bid.price.amount < auction.get_next_bid_min(config.min_bid_increment) {
+    if auction_bid.price.amount < auction.get_next_bid_min(denom_config.min_bid_increment) {
         return Err(ContractError::BidTooLow {});
     }
     
-    // If previous bid exists, refund it
-    if let Some(prev_highest_bid) = &auction.highest_bid {
-        transfer_token(
-            prev_highest_bid.price.clone(),
-            prev_highest_bid.bidder.to_string(),
-            "refund-auction-bidder",
-            &mut response,
-        )?;
+    price_validate(&auction_bid.price, denom_config)?;
+    if let Some(_max_price) = &max_price {
+        price_validate(_max_price, denom_config)?;
+        if _max_price.amount < auction_bid.price.amount {
+            return Err(ContractError::InvalidMaxPrice(_max_price.amount, auction_bid.price.amount));
+        }
     }
 
-    price_validate(&auction_bid.price, &config)?;
-    let payment_amount = must_pay(&info, &config.denom)?;
-    if auction_bid.price.amount != payment_amount  {
-        return Err(ContractError::IncorrectBidPayment(auction_bid.price.amount, payment_amount));
+    // Proxy bidders escrow their max price
+    let escrow = max_price.clone().unwrap_or_else(|| auction_bid.price.clone());
+    let payment_amount = must_pay(&info, denom)?;
+    if escrow.amount != payment_amount  {
+        return Err(ContractError::IncorrectBidPayment(escrow.amount, payment_amount));
     }
 
-    auction.highest_bid = Some(auction_bid.clone());
+    match &prev_max_bid {
+        Some(_prev_max_bid) if _prev_max_bid.price.amount >= escrow.amount => {
+            // The highest bidder's max bid holds, raise their visible bid and refund the new bidder
+            let highest_bid = auction.highest_bid.as_mut().unwrap();
+            highest_bid.price.amount = _prev_max_bid.price.amount.min(escrow.amount + denom_config.min_bid_increment);
+            transfer_token(
+                escrow,
+                auction_bid.bidder.to_string(),
+                "refund-auction-bidder",
+                &mut response,
+            )?;
+
+            let event = Event::new("outbid-auction-bid")
+                .add_attribute("collection", &collection.to_string())
+                .add_attribute("token_id", &token_id.to_string())
+                .add_attribute("bidder", &auction_bid.bidder)
+                .add_attribute("price", &auction_bid.price.to_string());
+            response.events.push(event);
+        }
+        _ => {
+            // If previous bid exists, refund it
+            if let Some(_prev_max_bid) = &prev_max_bid {
+                transfer_token(
+                    _prev_max_bid.price.clone(),
+                    _prev_max_bid.bidder.to_string(),
+                    "refund-auction-bidder",
+                    &mut response,
+                )?;
+
+                let event = Event::new("outbid-auction-bid")
+                    .add_attribute("collection", &collection.to_string())
+                    .add_attribute("token_id", &token_id.to_string())
+                    .add_attribute("bidder", &_prev_max_bid.bidder)
+                    .add_attribute("price", &_prev_max_bid.price.to_string());
+                response.events.push(event);
+            }
+
+            let mut highest_bid = auction_bid.clone();
+            match max_price {
+                Some(_max_price) => {
+                    // Proxy bids only go as high as needed to beat the previous max bid
+                    if let Some(_prev_max_bid) = &prev_max_bid {
+                        let min_proxy_price = _max_price.amount.min(_prev_max_bid.price.amount + denom_config.min_bid_increment);
+                        highest_bid.price.amount = highest_bid.price.amount.max(min_proxy_price);
+                    }
+                    MAX_BIDS.save(deps.storage, key.clone(), &AuctionBid {
+                        bidder: auction_bid.bidder.clone(),
+                        price: _max_price,
+                    })?;
+                },
+                None => MAX_BIDS.remove(deps.storage, key.clone()),
+            }
+            auction.highest_bid = Some(highest_bid);
+        }
+    }
     
     // If auction end time is within buffer_duration, then update the end time
     let new_auction_end_time = env.block.time.plus_seconds(config.buffer_duration);
@@ -293,12 +488,15 @@ pub fn execute_set_auction_bid(
         auction.end_time = new_auction_end_time;
     }
     
-    auctions().save(deps.storage, auction.token_id.clone(), &auction)?;
+    auctions().save(deps.storage, key, &auction)?;
 
+    // The event names the highest bidder after the bid, which a proxy bid may have kept
+    let highest_bid = auction.highest_bid.unwrap();
     let event = Event::new("set-auction-bid")
+        .add_attribute("collection", &collection.to_string())
         .add_attribute("token_id", &token_id.to_string())
-        .add_attribute("bidder", &auction_bid.bidder)
-        .add_attribute("price", &auction_bid.price.to_string());
+        .add_attribute("bidder", &highest_bid.bidder)
+        .add_attribute("price", &highest_bid.price.to_string());
     response.events.push(event);
 
     Ok(response)
@@ -309,13 +507,15 @@ pub fn execute_close_auction(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
+    collection: Addr,
     token_id: TokenId,
     accept_highest_bid: bool,
 ) -> Result<Response, ContractError> {
     nonpayable(&info)?;
 
     // Validate auction exists, and if it exists, that it is being closed by the seller
-    let auction = auctions().load(deps.storage, token_id.clone())?;
+    let key = auction_key(&collection, &token_id);
+    let auction = auctions().load(deps.storage, key.clone())?;
     only_seller(&info, &auction.seller)?;
 
     // If reserve price has been met, seller cannot close auction
@@ -335,30 +535,26 @@ pub fn execute_close_auction(
         finalize_sale(
             deps.as_ref(),
             &bid.bidder,
+            &auction.collection,
             &auction.token_id,
-            bid.price.amount,
+            &bid.price,
             &auction.get_recipient(),
             &config,
+            None,
             &mut response,
         )?;
+        refund_bid_escrow(deps.storage, &auction, bid.price.amount, &mut response)?;
     } else {
         // if sale does not occur return NFT to seller, then refund highest_bid if it exists
-        transfer_nft(&auction.token_id, &auction.seller, &config.cw721_address, &mut response)?;
-        if auction.highest_bid.is_some() {
-            let bid = auction.highest_bid.unwrap();
-            transfer_token(
-                bid.price.clone(),
-                bid.bidder.to_string(),
-                "refund-auction-bidder",
-                &mut response,
-            )?;
-        }   
+        transfer_nft(&auction.token_id, &auction.seller, &auction.collection, &mut response)?;
+        refund_bid_escrow(deps.storage, &auction, Uint128::zero(), &mut response)?;
     }
 
-    auctions().remove(deps.storage, token_id)?;
+    auctions().remove(deps.storage, key.clone())?;
+    MAX_BIDS.remove(deps.storage, key);
 
     let event = Event::new("close-auction")
-        .add_attribute("collection", &config.cw721_address.to_string())
+        .add_attribute("collection", &auction.collection.to_string())
         .add_attribute("token_id", &auction.token_id.to_string())
         .add_attribute("is_sale", &is_sale.to_string());
     
@@ -370,20 +566,21 @@ pub fn execute_finalize_auction(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    collection: Addr,
     token_id: TokenId,
 ) -> Result<Response, ContractError> {
     nonpayable(&info)?;
 
     // Validate auction exists
-    let auction = auctions().load(deps.storage, token_id.clone())?;
+    let key = auction_key(&collection, &token_id);
+    let auction = auctions().load(deps.storage, key.clone())?;
 
     // Validate that a bid exists
-    let bid = match &auction.highest_bid {
-        Some(bid) => bid,
-        None => return Err(ContractError::ReservePriceRestriction(
+    if auction.highest_bid.is_none() {
+        return Err(ContractError::ReservePriceRestriction(
             "auction has no bid".to_string(),
-        )),
-    };
+        ));
+    }
 
     // Validate reserve price is met
     if !auction.is_reserve_price_met() {
@@ -402,23 +599,12 @@ pub fn execute_finalize_auction(
 
     // Perform sale
     let mut response = Response::new();
-    finalize_sale(
-        deps.as_ref(),
-        &bid.bidder,
-        &auction.token_id,
-        bid.price.amount,
-        &auction.get_recipient(),
-        &config,
-        &mut response,
-    )?;
+    finalize_auction(deps.as_ref(), &auction, &config, None, &mut response)?;
 
-    auctions().remove(deps.storage, token_id)?;
+    auctions().remove(deps.storage, key.clone())?;
+    MAX_BIDS.remove(deps.storage, key);
 
-    let event = Event::new("finalize-auction")
-        .add_attribute("collection", &config.cw721_address.to_string())
-        .add_attribute("token_id", &auction.token_id.to_string());
-    
-    Ok(response.add_event(event))
+    Ok(response)
 }
 
 /// If an auction is expired, and the seller has not made a determination within the closed_duration window,
@@ -428,10 +614,12 @@ pub fn execute_void_auction(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    collection: Addr,
     token_id: TokenId,
 ) -> Result<Response, ContractError> {
     nonpayable(&info)?;
-    let auction = auctions().load(deps.storage, token_id.clone())?;
+    let key = auction_key(&collection, &token_id);
+    let auction = auctions().load(deps.storage, key.clone())?;
     
     // If reserve price has been met, the auction must be finalized
     if auction.is_reserve_price_met() {
@@ -449,25 +637,116 @@ pub fn execute_void_auction(
     }
     
     let mut response = Response::new();
-    // Refund the bidder the bid amount, if a bid exists
-    if auction.highest_bid.is_some() {
-        let bid = auction.highest_bid.unwrap();
-        transfer_token(
-            bid.price.clone(),
-            bid.bidder.to_string(),
-            "refund-auction-bidder",
-            &mut response,
-        )?;
+    void_auction(deps.as_ref(), &auction, &mut response)?;
+
+    // Remove the auction
+    auctions().remove(deps.storage, key.clone())?;
+    MAX_BIDS.remove(deps.storage, key);
+
+    Ok(response)
+}
+
+/// Anyone can settle Expired auctions in order of end time. Auctions that have met the reserve price
+/// are finalized, and the caller is paid the keeper fee out of the market fee. All other Expired
+/// auctions are voided.
+pub fn execute_settle_expired(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let limit = limit.unwrap_or(DEFAULT_SETTLE_LIMIT).min(MAX_SETTLE_LIMIT) as usize;
+
+    // Auctions are Expired in order of end time, so stop at the first one that is not
+    let expired_auctions = auctions()
+        .idx
+        .end_time
+        .range(deps.storage, None, None, Order::Ascending)
+        .take_while(|item| match item {
+            Ok((_, auction)) => matches!(
+                auction.get_auction_status(&env.block.time, config.closed_duration),
+                AuctionStatus::Expired
+            ),
+            Err(_) => true,
+        })
+        .take(limit)
+        .map(|res| res.map(|item| item.1))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut response = Response::new();
+    let mut num_finalized = 0u32;
+    let mut num_voided = 0u32;
+
+    for auction in expired_auctions {
+        if auction.is_reserve_price_met() {
+            finalize_auction(deps.as_ref(), &auction, &config, Some(&info.sender), &mut response)?;
+            num_finalized += 1;
+        } else {
+            void_auction(deps.as_ref(), &auction, &mut response)?;
+            num_voided += 1;
+        }
+        let key = auction_key(&auction.collection, &auction.token_id);
+        auctions().remove(deps.storage, key.clone())?;
+        MAX_BIDS.remove(deps.storage, key);
     }
+
+    let event = Event::new("settle-expired")
+        .add_attribute("keeper", info.sender.to_string())
+        .add_attribute("num_finalized", num_finalized.to_string())
+        .add_attribute("num_voided", num_voided.to_string());
+
+    Ok(response.add_event(event))
+}
+
+/// Performs the sale of an auction to the highest bidder
+fn finalize_auction(
+    deps: Deps,
+    auction: &Auction,
+    config: &Config,
+    keeper: Option<&Addr>,
+    response: &mut Response,
+) -> StdResult<()> {
+    let bid = auction.highest_bid.as_ref().unwrap();
+    finalize_sale(
+        deps,
+        &bid.bidder,
+        &auction.collection,
+        &auction.token_id,
+        &bid.price,
+        &auction.get_recipient(),
+        config,
+        keeper,
+        response,
+    )?;
+    refund_bid_escrow(deps.storage, auction, bid.price.amount, response)?;
+
+    let event = Event::new("finalize-auction")
+        .add_attribute("collection", &auction.collection.to_string())
+        .add_attribute("token_id", &auction.token_id.to_string());
+    response.events.push(event);
+
+    Ok(())
+}
+
+/// Refunds the highest bid, if it exists, and returns the NFT to the seller
+fn void_auction(
+    deps: Deps,
+    auction: &Auction,
+    response: &mut Response,
+) -> StdResult<()> {
+    // Refund the bidder the bid amount, if a bid exists
+    refund_bid_escrow(deps.storage, auction, Uint128::zero(), response)?;
     // Return the NFT to the seller
-    transfer_nft(&auction.token_id, &auction.seller, &config.cw721_address, &mut response)?;
-    // Remove the auction
-    auctions().remove(deps.storage, token_id)?;
+    transfer_nft(&auction.token_id, &auction.seller, &auction.collection, response)?;
 
     let event = Event::new("void-auction")
+        .add_attribute("collection", &auction.collection.to_string())
         .add_attribute("token_id", &auction.token_id.to_string())
         .add_attribute("seller", &auction.seller.to_string());
     response.events.push(event);
 
-    Ok(response)
+    Ok(())
 }
\ No newline at end of file
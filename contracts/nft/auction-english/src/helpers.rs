@@ -1,14 +1,15 @@
 use crate::error::ContractError;
 use crate::state::{
-    Config, TokenId, Auction
+    Config, DenomConfig, TokenId, Auction, AuctionBid, BidGate, MAX_BIDS, auction_key
 };
 use cosmwasm_std::{
-    to_binary, Addr, Api, StdResult, Timestamp, WasmMsg, Order, Deps,
+    to_binary, Addr, Api, StdResult, Storage, Timestamp, WasmMsg, Order, Deps,
     Event, Coin, coin, Uint128, Response, MessageInfo, BankMsg, SubMsg, Decimal
 };
 use pg721::msg::{CollectionInfoResponse, QueryMsg as Pg721QueryMsg};
-use cw721::{Cw721ExecuteMsg};
+use cw721::{Cw721ExecuteMsg, Cw721QueryMsg, TokensResponse};
 use cw721_base::helpers::Cw721Contract;
+use whitelist::msg::{HasMemberResponse, QueryMsg as WhitelistQueryMsg};
 
 pub fn map_validate(api: &dyn Api, addresses: &[String]) -> StdResult<Vec<Addr>> {
     addresses
@@ -28,77 +29,121 @@ pub fn option_bool_to_order(descending: Option<bool>) -> Order {
 pub fn finalize_sale(
     deps: Deps,
     bidder: &Addr,
+    collection: &Addr,
     token_id: &TokenId,
-    payment_amount: Uint128,
+    payment: &Coin,
     payment_recipient: &Addr,
     config: &Config,
+    keeper: Option<&Addr>,
     res: &mut Response,
 ) -> StdResult<()> {
-    payout(deps, payment_amount, payment_recipient, &config, res)?;
+    payout(deps, collection, payment, payment_recipient, &config, keeper, res)?;
 
-    transfer_nft(&token_id, bidder, &config.cw721_address, res)?;
+    transfer_nft(&token_id, bidder, collection, res)?;
 
     let event = Event::new("finalize-sale")
-        .add_attribute("collection", config.cw721_address.to_string())
+        .add_attribute("collection", collection.to_string())
         .add_attribute("buyer", bidder.to_string())
         .add_attribute("token_id", token_id.to_string())
-        .add_attribute("payment_amount", payment_amount.to_string())
+        .add_attribute("payment_amount", payment.to_string())
         .add_attribute("payment_recipient", payment_recipient.to_string());
     res.events.push(event);
 
     Ok(())
 }
 
+/// Amounts distributed when a sale is settled at a given price
+pub struct PayoutAmounts {
+    pub market_fee: Uint128,
+    pub keeper_fee: Uint128,
+    pub royalty: Option<(Uint128, String)>,
+    pub seller_amount: Uint128,
+}
+
+/// Calculates the market fee, keeper fee, royalty and seller proceeds for a sale,
+/// the keeper fee is only charged when the sale is settled by a keeper
+pub fn calculate_payout(
+    deps: Deps,
+    collection: &Addr,
+    payment_amount: Uint128,
+    config: &Config,
+    is_keeper: bool,
+) -> StdResult<PayoutAmounts> {
+    // Charge market fee
+    let market_fee = payment_amount * config.trading_fee_percent / Uint128::from(100u128);
+
+    // Pay keeper out of the market fee
+    let keeper_fee = match is_keeper {
+        true => market_fee * config.keeper_fee_percent / Uint128::from(100u128),
+        false => Uint128::zero(),
+    };
+
+    // Query royalties
+    let collection_info: CollectionInfoResponse = deps
+        .querier
+        .query_wasm_smart(collection, &Pg721QueryMsg::CollectionInfo {})?;
+
+    // Charge royalties if they exist
+    let royalty = match collection_info.royalty_info {
+        Some(royalty) => Some((payment_amount * royalty.share, royalty.payment_address)),
+        None => None
+    };
+
+    // Pay seller
+    let mut seller_amount = payment_amount - market_fee;
+    if let Some(_royalty) = &royalty {
+        seller_amount -= _royalty.0;
+    };
+
+    Ok(PayoutAmounts { market_fee, keeper_fee, royalty, seller_amount })
+}
+
 /// Payout a bid
 fn payout(
     deps: Deps,
-    payment_amount: Uint128,
+    collection: &Addr,
+    payment: &Coin,
     payment_recipient: &Addr,
     config: &Config,
+    keeper: Option<&Addr>,
     response: &mut Response,
 ) -> StdResult<()> {
-    let cw721_address = config.cw721_address.to_string();
+    let payout_amounts = calculate_payout(deps, collection, payment.amount, config, keeper.is_some())?;
+    let market_fee = payout_amounts.market_fee;
 
-    // Charge market fee
-    let market_fee = payment_amount * config.trading_fee_percent / Uint128::from(100u128);
-    if market_fee > Uint128::zero() {
+    let keeper_fee = payout_amounts.keeper_fee;
+    if keeper_fee > Uint128::zero() {
         transfer_token(
-            coin(market_fee.u128(), &config.denom),
+            coin(keeper_fee.u128(), &payment.denom),
+            keeper.unwrap().to_string(),
+            "payout-keeper",
+            response
+        )?;
+    }
+
+    let collector_fee = market_fee - keeper_fee;
+    if collector_fee > Uint128::zero() {
+        transfer_token(
+            coin(collector_fee.u128(), &payment.denom),
             config.collector_address.to_string(),
             "payout-market",
             response
         )?;
     }
 
-    // Query royalties
-    let collection_info: CollectionInfoResponse = deps
-        .querier
-        .query_wasm_smart(&cw721_address, &Pg721QueryMsg::CollectionInfo {})?;
-
-    // Charge royalties if they exist
-    let royalties = match &collection_info.royalty_info {
-        Some(royalty) => Some((payment_amount * royalty.share, &royalty.payment_address)),
-        None => None
-    };
-    if let Some(_royalties) = &royalties {
-        if _royalties.0 > Uint128::zero() {
+    if let Some(_royalty) = &payout_amounts.royalty {
+        if _royalty.0 > Uint128::zero() {
             transfer_token(
-                coin(_royalties.0.u128(), &config.denom),
-                _royalties.1.to_string(),
+                coin(_royalty.0.u128(), &payment.denom),
+                _royalty.1.to_string(),
                 "payout-royalty",
                 response
             )?;
         }
     };
 
-    // Pay seller
-    let mut seller_amount = payment_amount - market_fee;
-    if let Some(_royalties) = &royalties {
-        seller_amount -= _royalties.0;
-    };
-
     transfer_token(
-        coin(seller_amount.u128(), &config.denom),
+        coin(payout_amounts.seller_amount.u128(), &payment.denom),
         payment_recipient.to_string(),
         "payout-seller",
         response
@@ -107,12 +152,39 @@ fn payout(
     Ok(())
 }
 
+/// Load the max bid of the highest bidder, which is the highest bid when not proxy bidding
+pub fn load_max_bid(storage: &dyn Storage, auction: &Auction) -> StdResult<Option<AuctionBid>> {
+    let max_bid = MAX_BIDS.may_load(storage, auction_key(&auction.collection, &auction.token_id))?;
+    Ok(max_bid.or_else(|| auction.highest_bid.clone()))
+}
+
+/// Refunds the funds escrowed by the highest bidder, less the amount paid for the NFT
+pub fn refund_bid_escrow(
+    storage: &dyn Storage,
+    auction: &Auction,
+    paid_amount: Uint128,
+    response: &mut Response,
+) -> StdResult<()> {
+    if let Some(max_bid) = load_max_bid(storage, auction)? {
+        let refund_amount = max_bid.price.amount - paid_amount;
+        if refund_amount > Uint128::zero() {
+            transfer_token(
+                coin(refund_amount.u128(), &max_bid.price.denom),
+                max_bid.bidder.to_string(),
+                "refund-auction-bidder",
+                response,
+            )?;
+        }
+    }
+    Ok(())
+}
+
 // Validate Bid or Ask price
-pub fn price_validate(price: &Coin, config: &Config) -> Result<(), ContractError> {
+pub fn price_validate(price: &Coin, denom_config: &DenomConfig) -> Result<(), ContractError> {
     if
         price.amount.is_zero() ||
-        price.denom != config.denom ||
-        price.amount < config.min_price
+        price.denom != denom_config.denom ||
+        price.amount < denom_config.min_price
     {
         return Err(ContractError::InvalidPrice {});
     }
@@ -120,6 +192,17 @@ pub fn price_validate(price: &Coin, config: &Config) -> Result<(), ContractError
     Ok(())
 }
 
+/// Checks to enforce only approved collections and denoms are auctioned,
+/// returning the price limits for the auction's denom
+pub fn validate_collection_denom<'a>(auction: &Auction, config: &'a Config) -> Result<&'a DenomConfig, ContractError> {
+    if !config.collections.contains(&auction.collection) {
+        return Err(ContractError::UnapprovedCollection(auction.collection.to_string()));
+    }
+    config
+        .denom_config(&auction.starting_price.denom)
+        .ok_or_else(|| ContractError::UnapprovedDenom(auction.starting_price.denom.clone()))
+}
+
 /// Checks to enforce only NFT owner can call
 pub fn only_owner(
     deps: Deps,
@@ -134,6 +217,46 @@ pub fn only_owner(
     Ok(())
 }
 
+/// Checks to enforce only bidders allowed by the auction bid gate can bid,
+/// the proof is passed on to Merkle whitelists
+pub fn only_gated_bidder(
+    deps: Deps,
+    bid_gate: &Option<BidGate>,
+    bidder: &Addr,
+    proof: Option<Vec<String>>,
+) -> Result<(), ContractError> {
+    match bid_gate {
+        Some(BidGate::Whitelist(whitelist)) => {
+            let res: HasMemberResponse = deps.querier.query_wasm_smart(
+                whitelist,
+                &WhitelistQueryMsg::HasMember {
+                    member: bidder.to_string(),
+                    proof,
+                    stage_id: None,
+                },
+            )?;
+            if !res.has_member {
+                return Err(ContractError::BidGateRestriction(String::from("bidder is not a whitelist member")));
+            }
+        },
+        Some(BidGate::Collection(collection)) => {
+            let res: TokensResponse = deps.querier.query_wasm_smart(
+                collection,
+                &Cw721QueryMsg::Tokens {
+                    owner: bidder.to_string(),
+                    start_after: None,
+                    limit: Some(1),
+                },
+            )?;
+            if res.tokens.is_empty() {
+                return Err(ContractError::BidGateRestriction(String::from("bidder does not hold a collection token")));
+            }
+        },
+        None => {},
+    }
+    Ok(())
+}
+
 /// Checks to enforce only Ask seller can call
 pub fn only_seller(
     info: &MessageInfo,
@@ -212,14 +335,25 @@ pub fn validate_config(config: &Config) -> Result<(), ContractError> {
     if config.trading_fee_percent > Decimal::percent(10000) {
         return Err(ContractError::InvalidConfig(String::from("trading_fee_percent must be less than or equal to 100")));
     }
+    if config.collections.is_empty() {
+        return Err(ContractError::InvalidConfig(String::from("collections must be non-empty")));
+    }
+    if config.denoms.is_empty() {
+        return Err(ContractError::InvalidConfig(String::from("denoms must be non-empty")));
+    }
     if config.operators.is_empty() {
         return Err(ContractError::InvalidConfig(String::from("operators must be non-empty")));
     }
-    if config.min_price.is_zero() {
-        return Err(ContractError::InvalidConfig(String::from("min_price must be greater than zero")));
-    }
-    if config.min_bid_increment.is_zero() {
-        return Err(ContractError::InvalidConfig(String::from("min_bid_increment must be greater than zero")));
+    for (i, denom_config) in config.denoms.iter().enumerate() {
+        if config.denoms[..i].iter().any(|d| d.denom == denom_config.denom) {
+            return Err(ContractError::InvalidConfig(format!("duplicate denom {}", denom_config.denom)));
+        }
+        if denom_config.min_price.is_zero() {
+            return Err(ContractError::InvalidConfig(format!("min_price for {} must be greater than zero", denom_config.denom)));
+        }
+        if denom_config.min_bid_increment.is_zero() {
+            return Err(ContractError::InvalidConfig(format!("min_bid_increment for {} must be greater than zero", denom_config.denom)));
+        }
     }
     if config.min_duration == 0 {
         return Err(ContractError::InvalidConfig(String::from("min_duration must be greater than zero")));
@@ -233,5 +367,8 @@ pub fn validate_config(config: &Config) -> Result<(), ContractError> {
     if config.closed_duration == 0 {
         return Err(ContractError::InvalidConfig(String::from("closed_duration must be greater than zero")));
     }
+    if config.keeper_fee_percent > Decimal::percent(10000) {
+        return Err(ContractError::InvalidConfig(String::from("keeper_fee_percent must be less than or equal to 100")));
+    }
     Ok(())
 }
\ No newline at end of file
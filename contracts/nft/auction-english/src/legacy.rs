@@ -0,0 +1,138 @@
+use crate::state::{
+    Auction, AuctionBid, Config, CONFIG, DenomConfig, TokenId, auctions, auction_key,
+};
+use cosmwasm_std::{Addr, Coin, Decimal, Order, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, MultiIndex};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Config stored by versions prior to 0.2.0, which supported a single collection and denom
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigV0_1 {
+    pub cw721_address: Addr,
+    pub denom: String,
+    pub collector_address: Addr,
+    pub trading_fee_percent: Decimal,
+    pub operators: Vec<Addr>,
+    pub min_price: Uint128,
+    pub min_bid_increment: Uint128,
+    pub min_duration: u64,
+    pub max_duration: u64,
+    pub closed_duration: u64,
+    pub buffer_duration: u64,
+}
+
+pub const CONFIG_V0_1: Item<ConfigV0_1> = Item::new("config");
+
+/// Auction stored by versions prior to 0.2.0, keyed by token_id
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AuctionV0_1 {
+    pub token_id: TokenId,
+    pub seller: Addr,
+    pub start_time: Timestamp,
+    pub end_time: Timestamp,
+    pub starting_price: Coin,
+    pub reserve_price: Option<Coin>,
+    pub funds_recipient: Option<Addr>,
+    pub highest_bid: Option<AuctionBid>
+}
+
+/// Indices for accessing Auctions prior to 0.2.0, needed to clear their index entries
+pub struct AuctionV0_1Indices<'a> {
+    pub start_time: MultiIndex<'a, u64, AuctionV0_1, TokenId>,
+    pub end_time: MultiIndex<'a, u64, AuctionV0_1, TokenId>,
+    pub highest_bid_price: MultiIndex<'a, u128, AuctionV0_1, TokenId>,
+    pub seller_end_time: MultiIndex<'a, (String, u64), AuctionV0_1, TokenId>,
+    pub highest_bidder_end_time: MultiIndex<'a, (String, u64), AuctionV0_1, TokenId>,
+}
+
+impl<'a> IndexList<AuctionV0_1> for AuctionV0_1Indices<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<AuctionV0_1>> + '_> {
+        let v: Vec<&dyn Index<AuctionV0_1>> = vec![
+            &self.start_time,
+            &self.end_time,
+            &self.highest_bid_price,
+            &self.seller_end_time,
+            &self.highest_bidder_end_time,
+        ];
+        Box::new(v.into_iter())
+    }
+}
+
+pub fn auctions_v0_1<'a>() -> IndexedMap<'a, TokenId, AuctionV0_1, AuctionV0_1Indices<'a>> {
+    let indexes = AuctionV0_1Indices {
+        start_time: MultiIndex::new(
+            |a: &AuctionV0_1|  a.start_time.seconds(),
+            "auctions",
+            "auctions__start_time",
+        ),
+        end_time: MultiIndex::new(
+            |a: &AuctionV0_1|  a.end_time.seconds(),
+            "auctions",
+            "auctions__end_time",
+        ),
+        highest_bid_price: MultiIndex::new(
+            |a: &AuctionV0_1|  a.highest_bid.as_ref().map_or(0, |b| b.price.amount.u128()),
+            "auctions",
+            "auctions__highest_bid_price"
+        ),
+        seller_end_time: MultiIndex::new(
+            |a: &AuctionV0_1|  (a.seller.to_string(), a.end_time.seconds()),
+            "auctions",
+            "auctions__seller_end_time",
+        ),
+        highest_bidder_end_time: MultiIndex::new(
+            |a: &AuctionV0_1|  (a.highest_bid.as_ref().map_or(String::from(""), |b| b.bidder.to_string()), a.end_time.seconds()),
+            "auctions",
+            "auctions__highest_bidder_end_time",
+        ),
+    };
+    IndexedMap::new("auctions", indexes)
+}
+
+/// Migrates the Config and Auctions stored prior to 0.2.0 to the current schema,
+/// returns the number of Auctions migrated
+pub fn migrate_v0_1_storage(storage: &mut dyn Storage) -> StdResult<u32> {
+    let legacy_config = CONFIG_V0_1.load(storage)?;
+    let config = Config {
+        collections: vec![legacy_config.cw721_address.clone()],
+        denoms: vec![DenomConfig {
+            denom: legacy_config.denom,
+            min_price: legacy_config.min_price,
+            min_bid_increment: legacy_config.min_bid_increment,
+        }],
+        collector_address: legacy_config.collector_address,
+        trading_fee_percent: legacy_config.trading_fee_percent,
+        operators: legacy_config.operators,
+        min_duration: legacy_config.min_duration,
+        max_duration: legacy_config.max_duration,
+        closed_duration: legacy_config.closed_duration,
+        buffer_duration: legacy_config.buffer_duration,
+        keeper_fee_percent: Decimal::zero(),
+    };
+    CONFIG.save(storage, &config)?;
+
+    // Re-key each Auction by (collection, token_id), the escrowed highest bid is unchanged
+    let legacy_auctions = auctions_v0_1()
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (token_id, legacy_auction) in &legacy_auctions {
+        auctions_v0_1().remove(storage, token_id.clone())?;
+
+        let auction = Auction {
+            collection: legacy_config.cw721_address.clone(),
+            token_id: legacy_auction.token_id.clone(),
+            seller: legacy_auction.seller.clone(),
+            start_time: legacy_auction.start_time,
+            end_time: legacy_auction.end_time,
+            starting_price: legacy_auction.starting_price.clone(),
+            reserve_price: legacy_auction.reserve_price.clone(),
+            funds_recipient: legacy_auction.funds_recipient.clone(),
+            highest_bid: legacy_auction.highest_bid.clone(),
+            bid_gate: None,
+        };
+        auctions().save(storage, auction_key(&auction.collection, &auction.token_id), &auction)?;
+    }
+
+    Ok(legacy_auctions.len() as u32)
+}
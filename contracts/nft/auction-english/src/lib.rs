@@ -1,6 +1,7 @@
 mod error;
 pub mod execute;
 mod helpers;
+pub mod legacy;
 pub mod msg;
 
 #[cfg(test)]
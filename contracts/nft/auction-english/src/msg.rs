@@ -1,14 +1,14 @@
-use crate::state::{TokenId, Config, Auction, AuctionStatus};
-use cosmwasm_std::{Coin, Timestamp, Uint128};
+use crate::state::{TokenId, Config, Auction, AuctionStatus, DenomConfig};
+use cosmwasm_std::{Addr, Coin, Timestamp, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
-    /// The NFT contract
-    pub cw721_address: String,
-    /// The token used to pay for NFTs
-    pub denom: String,
+    /// The NFT contracts approved for auction
+    pub collections: Vec<String>,
+    /// The tokens approved to pay for NFTs, with their price limits
+    pub denoms: Vec<DenomConfig>,
     /// The address collecting marketplace fees
     pub collector_address: String,
     /// Fair Burn fee for winning bids
@@ -17,10 +17,6 @@ pub struct InstantiateMsg {
     /// Operators are entites that are responsible for maintaining the active state of Asks.
     /// They listen to NFT transfer events, and update the active state of Asks.
     pub operators: Vec<String>,
-    /// Min value for an Auction starting price
-    pub min_price: Uint128,
-    /// The minimum difference between incremental bids
-    pub min_bid_increment: Uint128,
     /// The minimum duration of an auction 
     pub min_duration: u64,
     /// The maximum duration of an auction 
@@ -29,6 +25,9 @@ pub struct InstantiateMsg {
     pub closed_duration: u64,
     /// The duration an Auction is extended by when a bid is placed in the final minutes
     pub buffer_duration: u64,
+    /// Portion of the market fee paid to the caller of SettleExpired
+    /// 0.25% = 25, 0.5% = 50, 1% = 100, 2.5% = 250
+    pub keeper_fee_bps: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -36,45 +35,82 @@ pub struct InstantiateMsg {
 pub enum ExecuteMsg {
     /// Update the contract parameters
     UpdateConfig {
+        collections: Option<Vec<String>>,
+        denoms: Option<Vec<DenomConfig>>,
         collector_address: Option<String>,
         trading_fee_bps: Option<u64>,
         operators: Option<Vec<String>>,
-        min_price: Option<Uint128>,
-        min_bid_increment: Option<Uint128>,
         min_duration: Option<u64>,
         max_duration: Option<u64>,
         closed_duration: Option<u64>,
         buffer_duration: Option<u64>,
+        keeper_fee_bps: Option<u64>,
     },
     /// Create an auction for a specified token
     SetAuction {
+        collection: String,
         token_id: TokenId,
         start_time: Timestamp,
         end_time: Timestamp,
         starting_price: Coin,
         reserve_price: Option<Coin>,
         funds_recipient: Option<String>,
+        bid_gate: Option<BidGateMsg>,
     },
-    /// Place a bid on an existing auction
+    /// Place a bid on an existing auction. If max_price is set, the max_price is escrowed
+    /// and the bid is automatically raised when outbid, up to the max_price.
+    /// The highest bidder raises their max_price by paying the difference, their bid is unchanged
     SetAuctionBid {
+        collection: String,
         token_id: TokenId,
         price: Coin,
+        max_price: Option<Coin>,
+        /// Hex encoded sibling hashes proving membership of a Merkle whitelist bid gate
+        proof: Option<Vec<String>>,
     },
     /// Sellers can close a previously created auction that has
     /// not met the reserve price
     CloseAuction {
+        collection: String,
         token_id: TokenId,
         accept_highest_bid: bool,
     },
     /// Anyone can finalize an auction that has met the reserve price
     FinalizeAuction {
+        collection: String,
         token_id: TokenId,
     },
     /// The bidder can void an expired Auction that has not been determined
     /// by the seller
     VoidAuction {
+        collection: String,
         token_id: TokenId,
     },
+    /// Anyone can settle expired auctions, they are finalized or voided
+    /// according to whether the reserve price has been met
+    SettleExpired {
+        limit: Option<u32>,
+    },
+}
+
+/// Restricts which addresses may bid on an auction
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BidGateMsg {
+    /// Only members of the whitelist contract may bid
+    Whitelist(String),
+    /// Only holders of a token in the cw721 collection may bid
+    Collection(String),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {
+    /// Replaces the NFT contracts approved for auction
+    pub collections: Option<Vec<String>>,
+    /// Replaces the tokens approved to pay for NFTs, with their price limits
+    pub denoms: Option<Vec<DenomConfig>>,
+    /// Portion of the market fee paid to the caller of SettleExpired
+    pub keeper_fee_bps: Option<u64>,
 }
 
 /// Options when querying for Asks and Bids
@@ -88,12 +124,14 @@ pub struct QueryOptions<T> {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct TokenTimestampOffset {
+    pub collection: Addr,
     pub token_id: TokenId,
     pub timestamp: Timestamp,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct TokenPriceOffset {
+    pub collection: Addr,
     pub token_id: TokenId,
     pub price: Uint128,
 }
@@ -107,6 +145,7 @@ pub enum QueryMsg {
     /// Get the auction for a specific NFT
     /// Return type: `AuctionResponse`
     Auction {
+        collection: String,
         token_id: TokenId,
     },
     /// Get the auctions sorted by the start time
@@ -136,6 +175,21 @@ pub enum QueryMsg {
         bidder: String,
         query_options: QueryOptions<TokenTimestampOffset>
     },
+    /// Get all auctions for a collection sorted by end time
+    /// Return type: `AuctionsResponse`
+    AuctionsByCollectionEndTime {
+        collection: String,
+        query_options: QueryOptions<TokenTimestampOffset>
+    },
+    /// Get the fee breakdown for settling an auction at the highest bid price,
+    /// or at a hypothetical price. Assumes a keeper settles the auction through
+    /// SettleExpired, so the keeper fee is taken out of the market fee
+    /// Return type: `SettlementPreviewResponse`
+    SettlementPreview {
+        collection: String,
+        token_id: TokenId,
+        price: Option<Uint128>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -155,3 +209,15 @@ pub struct AuctionResponse {
 pub struct AuctionsResponse {
     pub auctions: Vec<Auction>,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SettlementPreviewResponse {
+    pub price: Coin,
+    pub market_fee: Coin,
+    pub keeper_fee: Coin,
+    pub collector_fee: Coin,
+    pub royalty_recipient: Option<String>,
+    pub royalty_amount: Coin,
+    pub seller_recipient: Addr,
+    pub seller_amount: Coin,
+}
@@ -1,15 +1,24 @@
 #![cfg(test)]
 use crate::error::ContractError;
+use crate::legacy::{AuctionV0_1, ConfigV0_1, CONFIG_V0_1, auctions_v0_1};
 use crate::msg::{
     ExecuteMsg, QueryMsg, QueryOptions, AuctionResponse, AuctionsResponse, TokenTimestampOffset,
+    SettlementPreviewResponse, ConfigResponse, MigrateMsg, BidGateMsg,
 };
-use crate::state::{Auction, AuctionStatus, AuctionBid};
-use cosmwasm_std::{Addr, Empty, Timestamp, coin, coins, Coin, Decimal, Uint128};
+use crate::state::{Auction, AuctionStatus, AuctionBid, DenomConfig};
+use cosmwasm_std::{
+    Addr, DepsMut, Empty, Env, MessageInfo, Response, StdResult, Timestamp, coin, coins, Coin,
+    Decimal, Uint128,
+};
+use cw2::set_contract_version;
 use cw721::{Cw721QueryMsg, OwnerOfResponse};
 use cw721_base::msg::{ExecuteMsg as Cw721ExecuteMsg, MintMsg};
 use cw_multi_test::{App, AppBuilder, BankSudo, Contract, ContractWrapper, Executor, SudoMsg as CwSudoMsg};
 use pg721::msg::{InstantiateMsg as Pg721InstantiateMsg, RoyaltyInfoResponse};
 use pg721::state::CollectionInfo;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use whitelist::msg::InstantiateMsg as WhitelistInstantiateMsg;
 
 const TOKEN_ID: &str = "123";
 const CREATION_FEE: u128 = 1_000_000_000;
@@ -19,10 +28,19 @@ const USER: &str = "USER";
 
 // Governance parameters
 const TRADING_FEE_BPS: u64 = 200; // 2%
+const KEEPER_FEE_BPS: u64 = 1000; // 10% of the trading fee
 const TEN_MINS: u64 = 60 * 10; // 24 hours (in seconds)
 const ONE_DAY: u64 = 24 * 60 * 60; // 24 hours (in seconds)
 const SIX_MOS: u64 = 180 * 24 * 60 * 60; // 6 months (in seconds)
 
+fn native_denom_config() -> DenomConfig {
+    DenomConfig {
+        denom: NATIVE_DENOM.to_string(),
+        min_price: Uint128::from(5u128),
+        min_bid_increment: Uint128::from(3u128),
+    }
+}
+
 fn custom_mock_app() -> App {
     AppBuilder::new().build(|router, _, storage| {
         router
@@ -44,12 +62,43 @@ pub fn contract_auction_english() -> Box<dyn Contract<Empty>> {
         crate::execute::execute,
         crate::execute::instantiate,
         crate::query::query,
-    );
+    )
+    .with_migrate(crate::execute::migrate);
     // .with_sudo(crate::sudo::sudo)
     // .with_reply(crate::execute::reply);
     Box::new(contract)
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct LegacyInstantiateMsg {
+    config: ConfigV0_1,
+    auctions: Vec<AuctionV0_1>,
+}
+
+// Writes the storage of an auction_english contract prior to 0.2.0
+fn legacy_instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: LegacyInstantiateMsg,
+) -> StdResult<Response> {
+    set_contract_version(deps.storage, "crates.io:marketplace-v2", "0.1.0")?;
+    CONFIG_V0_1.save(deps.storage, &msg.config)?;
+    for auction in msg.auctions {
+        auctions_v0_1().save(deps.storage, auction.token_id.clone(), &auction)?;
+    }
+    Ok(Response::new())
+}
+
+pub fn contract_auction_english_v0_1() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        crate::execute::execute,
+        legacy_instantiate,
+        crate::query::query,
+    );
+    Box::new(contract)
+}
+
 pub fn contract_pg721() -> Box<dyn Contract<Empty>> {
     let contract = ContractWrapper::new(
         pg721::contract::execute,
@@ -59,6 +108,15 @@ pub fn contract_pg721() -> Box<dyn Contract<Empty>> {
     Box::new(contract)
 }
 
+pub fn contract_whitelist() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        whitelist::contract::execute,
+        whitelist::contract::instantiate,
+        whitelist::contract::query,
+    );
+    Box::new(contract)
+}
+
 fn setup_block_time(router: &mut App, seconds: u64) {
     let mut block = router.block_info();
     block.time = Timestamp::from_seconds(seconds);
@@ -103,17 +161,16 @@ fn setup_contracts(
     // Instantiate auction_english contract
     let auction_english_id = router.store_code(contract_auction_english());
     let msg = crate::msg::InstantiateMsg {
-        cw721_address: collection.to_string(),
-        denom: String::from(NATIVE_DENOM),
+        collections: vec![collection.to_string()],
+        denoms: vec![native_denom_config()],
         collector_address: creator.to_string(),
         trading_fee_bps: TRADING_FEE_BPS,
         operators: vec!["operator".to_string()],
-        min_price: Uint128::from(5u128),
-        min_bid_increment: Uint128::from(3u128),
         min_duration: ONE_DAY,
         max_duration: SIX_MOS,
         closed_duration: ONE_DAY,
         buffer_duration: TEN_MINS,
+        keeper_fee_bps: KEEPER_FEE_BPS,
     };
     let auction_english = router
         .instantiate_contract(
@@ -224,6 +281,7 @@ fn auction(
     router: &mut App,
     creator: &Addr,
     auction_english: &Addr,
+    collection: &Addr,
     token_id: String,
     start_time: Timestamp,
     end_time: Timestamp,
@@ -232,28 +290,54 @@ fn auction(
     funds_recipient: Option<String>
 ) {
     let set_auction = ExecuteMsg::SetAuction {
+        collection: collection.to_string(),
         token_id,
         start_time,
         end_time,
         starting_price: coin(starting_price, NATIVE_DENOM),
         reserve_price: Some(coin(reserve_price, NATIVE_DENOM)),
         funds_recipient,
+        bid_gate: None,
     };
     let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
     assert!(res.is_ok());
 }
 
+fn proxy_auction_bid(
+    router: &mut App,
+    creator: &Addr,
+    auction_english: &Addr,
+    collection: &Addr,
+    token_id: String,
+    price: u128,
+    max_price: u128,
+) {
+    let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection.to_string(),
+        token_id: token_id,
+        price: coin(price, NATIVE_DENOM),
+        max_price: Some(coin(max_price, NATIVE_DENOM)),
+        proof: None,
+    };
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction_bid, &[coin(max_price, NATIVE_DENOM)]);
+    assert!(res.is_ok());
+}
+
 fn auction_bid(
     router: &mut App,
     creator: &Addr,
     auction_english: &Addr,
+    collection: &Addr,
     token_id: String,
     price: u128,
 ) {
     let coin_send = coin(price, NATIVE_DENOM);
     let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection.to_string(),
         token_id: token_id,
         price: coin_send.clone(),
+        max_price: None,
+        proof: None,
     };
     let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction_bid, &[coin_send]);
     assert!(res.is_ok());
@@ -275,48 +359,56 @@ fn try_auction_creation_and_removal() {
 
     // Should error with duration lower than min
     let set_auction = ExecuteMsg::SetAuction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
         start_time: block_time.plus_seconds(ONE_DAY),
         end_time: block_time.plus_seconds(ONE_DAY),
         starting_price: coin(110, NATIVE_DENOM),
         reserve_price: Some(coin(210, NATIVE_DENOM)),
         funds_recipient: None,
+        bid_gate: None,
     };
     let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
     assert!(res.is_err());
 
     // Should error with duration above_max
     let set_auction = ExecuteMsg::SetAuction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
         start_time: block_time.plus_seconds(ONE_DAY),
         end_time: block_time.plus_seconds(SIX_MOS * 2),
         starting_price: coin(110, NATIVE_DENOM),
         reserve_price: Some(coin(210, NATIVE_DENOM)),
         funds_recipient: None,
+        bid_gate: None,
     };
     let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
     assert!(res.is_err());
 
     // Should error with invalid denom
     let set_auction = ExecuteMsg::SetAuction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
         start_time: block_time.plus_seconds(ONE_DAY),
         end_time: block_time.plus_seconds(ONE_DAY * 2),
         starting_price: coin(110, NATIVE_DENOM),
         reserve_price: Some(coin(210, "ujuno")),
         funds_recipient: None,
+        bid_gate: None,
     };
     let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
     assert!(res.is_err());
 
     // Should error with reserve price below starting price
     let set_auction = ExecuteMsg::SetAuction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
         start_time: block_time.plus_seconds(ONE_DAY),
         end_time: block_time.plus_seconds(ONE_DAY * 2),
         starting_price: coin(200, NATIVE_DENOM),
         reserve_price: Some(coin(100, NATIVE_DENOM)),
         funds_recipient: None,
+        bid_gate: None,
     };
     let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
     assert!(res.is_err());
@@ -326,6 +418,7 @@ fn try_auction_creation_and_removal() {
         &mut router,
         &creator,
         &auction_english,
+        &collection,
         TOKEN_ID.to_string(),
         block_time.plus_seconds(ONE_DAY),
         block_time.plus_seconds(ONE_DAY * 2),
@@ -336,6 +429,7 @@ fn try_auction_creation_and_removal() {
 
     // Validate Auction data is correct
     let query_auction = QueryMsg::Auction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
     };
     let res: AuctionResponse = router
@@ -348,6 +442,7 @@ fn try_auction_creation_and_removal() {
         None => Err("Auction not found")
     }.unwrap();
     assert_eq!(Auction {
+        collection: collection.clone(),
         token_id: TOKEN_ID.to_string(),
         start_time: block_time.plus_seconds(ONE_DAY),
         end_time: block_time.plus_seconds(ONE_DAY * 2),
@@ -356,6 +451,7 @@ fn try_auction_creation_and_removal() {
         seller: creator.clone(),
         funds_recipient: None,
         highest_bid: None,
+        bid_gate: None,
     }, current_auction);
     
     // Check NFT is transferred to auction_english contract
@@ -371,6 +467,7 @@ fn try_auction_creation_and_removal() {
 
     // Close an auction with no bids
     let close_auction = ExecuteMsg::CloseAuction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
         accept_highest_bid: false
     };
@@ -379,6 +476,7 @@ fn try_auction_creation_and_removal() {
 
     // Validate Auction is deleted
     let query_auction = QueryMsg::Auction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
     };
     let res: AuctionResponse = router
@@ -416,6 +514,7 @@ fn try_auction_bid_creation_and_removal() {
         &mut router,
         &creator,
         &auction_english,
+        &collection,
         TOKEN_ID.to_string(),
         block_time.plus_seconds(ONE_DAY),
         block_time.plus_seconds(ONE_DAY * 2),
@@ -426,16 +525,22 @@ fn try_auction_bid_creation_and_removal() {
 
     // AuctionBid creation should error without a matching auction
     let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection.to_string(),
         token_id: String::from("999"),
         price: coin(120u128, NATIVE_DENOM),
+        max_price: None,
+        proof: None,
     };
     let res = router.execute_contract(bidder.clone(), auction_english.clone(), &set_auction_bid, &[]);
     assert_eq!(&res.unwrap_err().root_cause().to_string(), "auction_english::state::Auction not found");
 
     // AuctionBid creation should error when auction status is pending
     let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
         price: coin(120u128, NATIVE_DENOM),
+        max_price: None,
+        proof: None,
     };
     let res = router.execute_contract(bidder.clone(), auction_english.clone(), &set_auction_bid, &[]);
     assert_eq!(&res.unwrap_err().root_cause().to_string(), "Auction invalid status: Pending");
@@ -444,16 +549,22 @@ fn try_auction_bid_creation_and_removal() {
 
     // AuctionBid creation should error when funds are not sent
     let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
         price: coin(120u128, NATIVE_DENOM),
+        max_price: None,
+        proof: None,
     };
     let res = router.execute_contract(bidder.clone(), auction_english.clone(), &set_auction_bid, &[]);
     assert_eq!(&res.unwrap_err().root_cause().to_string(), "No funds sent");
 
     // AuctionBid creation should error when bid is below starting price
     let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
         price: coin(100u128, NATIVE_DENOM),
+        max_price: None,
+        proof: None,
     };
     let res = router.execute_contract(bidder.clone(), auction_english.clone(), &set_auction_bid, &[coin(100u128, NATIVE_DENOM)]);
     assert_eq!(&res.unwrap_err().root_cause().to_string(), "Auction bid too low");
@@ -462,17 +573,21 @@ fn try_auction_bid_creation_and_removal() {
     let bidder2_balance_a = router.wrap().query_all_balances(bidder2.clone()).unwrap().into_iter().nth(0).unwrap();
 
     // AuctionBid creation should error when bid is less than or equal to the highest bid + minimum increment
-    auction_bid(&mut router, &bidder, &auction_english, TOKEN_ID.to_string(), 140u128);
+    auction_bid(&mut router, &bidder, &auction_english, &collection, TOKEN_ID.to_string(), 140u128);
     let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
         price: coin(142u128, NATIVE_DENOM),
+        max_price: None,
+        proof: None,
     };
     let res = router.execute_contract(bidder.clone(), auction_english.clone(), &set_auction_bid, &[coin(142u128, NATIVE_DENOM)]);
     assert_eq!(&res.unwrap_err().root_cause().to_string(), "Auction bid too low");
 
     // Verify that new auction bids update the auction obj
-    auction_bid(&mut router, &bidder2, &auction_english, TOKEN_ID.to_string(), 150u128);
+    auction_bid(&mut router, &bidder2, &auction_english, &collection, TOKEN_ID.to_string(), 150u128);
     let query_auction = QueryMsg::Auction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string()
     };
     let res: AuctionResponse = router
@@ -480,6 +595,7 @@ fn try_auction_bid_creation_and_removal() {
         .query_wasm_smart(auction_english.clone(), &query_auction)
         .unwrap();
     assert_eq!(Auction {
+        collection: collection.clone(),
         token_id: TOKEN_ID.to_string(),
         seller: creator.clone(),
         start_time: block_time.plus_seconds(ONE_DAY),
@@ -491,6 +607,7 @@ fn try_auction_bid_creation_and_removal() {
             bidder: bidder2.clone(),
             price: coin(150u128, NATIVE_DENOM),
         }),
+        bid_gate: None,
     }, res.auction.unwrap());
 
     // Verify that new auction bids refund the previous high bidder
@@ -501,6 +618,7 @@ fn try_auction_bid_creation_and_removal() {
 
     // Auction with bids can be closed, and the highest bid can be accepted
     let close_auction = ExecuteMsg::CloseAuction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
         accept_highest_bid: true
     };
@@ -544,6 +662,7 @@ fn try_auction_bid_reserve_price_met() {
         &mut router,
         &creator,
         &auction_english,
+        &collection,
         TOKEN_ID.to_string(),
         block_time.plus_seconds(ONE_DAY),
         block_time.plus_seconds(ONE_DAY * 2),
@@ -555,10 +674,11 @@ fn try_auction_bid_reserve_price_met() {
     // Meet reserve price
     let bid_amount = 220u128;
     setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY + 10u64).seconds());
-    auction_bid(&mut router, &bidder, &auction_english, TOKEN_ID.to_string(), 220u128);
+    auction_bid(&mut router, &bidder, &auction_english, &collection, TOKEN_ID.to_string(), 220u128);
 
     // Verify auctions that have met reserve price cannot be closed
     let close_auction = ExecuteMsg::CloseAuction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
         accept_highest_bid: false
     };
@@ -567,6 +687,7 @@ fn try_auction_bid_reserve_price_met() {
 
     // Auction cannot be finalized while Auction is still open
     let finalize_auction = ExecuteMsg::FinalizeAuction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
     };
     let res = router.execute_contract(bidder.clone(), auction_english.clone(), &finalize_auction, &[]);
@@ -575,6 +696,7 @@ fn try_auction_bid_reserve_price_met() {
     // Auction can be finalized when Auction is closed
     setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY * 2 + 10u64).seconds());
     let finalize_auction = ExecuteMsg::FinalizeAuction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
     };
     let res = router.execute_contract(bidder.clone(), auction_english.clone(), &finalize_auction, &[]);
@@ -615,6 +737,7 @@ fn try_auction_void() {
         &mut router,
         &creator,
         &auction_english,
+        &collection,
         TOKEN_ID.to_string(),
         block_time.plus_seconds(ONE_DAY),
         block_time.plus_seconds(ONE_DAY * 2),
@@ -625,10 +748,11 @@ fn try_auction_void() {
 
     // Create an auction bid
     setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY + TEN_MINS).seconds());
-    auction_bid(&mut router, &bidder, &auction_english, TOKEN_ID.to_string(), 150u128);
+    auction_bid(&mut router, &bidder, &auction_english, &collection, TOKEN_ID.to_string(), 150u128);
 
     // Auction cannot be voided while Auction is still Open
     let query_auction = QueryMsg::Auction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string()
     };
     let res: AuctionResponse = router
@@ -638,6 +762,7 @@ fn try_auction_void() {
     assert_eq!(AuctionStatus::Open, res.auction_status.unwrap());
 
     let void_auction = ExecuteMsg::VoidAuction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
     };
     let res = router.execute_contract(bidder.clone(), auction_english.clone(), &void_auction, &[]);
@@ -646,6 +771,7 @@ fn try_auction_void() {
     // Auction cannot be voided while Auction is still Closed
     setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY * 2 + TEN_MINS).seconds());
     let query_auction = QueryMsg::Auction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string()
     };
     let res: AuctionResponse = router
@@ -655,6 +781,7 @@ fn try_auction_void() {
     assert_eq!(AuctionStatus::Closed, res.auction_status.unwrap());
 
     let void_auction = ExecuteMsg::VoidAuction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
     };
     let res = router.execute_contract(bidder.clone(), auction_english.clone(), &void_auction, &[]);
@@ -662,11 +789,12 @@ fn try_auction_void() {
 
     // Meet the reserve price
     setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY + TEN_MINS).seconds());
-    auction_bid(&mut router, &bidder, &auction_english, TOKEN_ID.to_string(), 240u128);
+    auction_bid(&mut router, &bidder, &auction_english, &collection, TOKEN_ID.to_string(), 240u128);
 
     // Auction cannot be voided if Auction reserve price is met
     setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY * 3 + TEN_MINS).seconds());
     let query_auction = QueryMsg::Auction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string()
     };
     let res: AuctionResponse = router
@@ -676,6 +804,7 @@ fn try_auction_void() {
     assert_eq!(AuctionStatus::Expired, res.auction_status.unwrap());
 
     let void_auction = ExecuteMsg::VoidAuction {
+        collection: collection.to_string(),
         token_id: TOKEN_ID.to_string(),
     };
     let res = router.execute_contract(bidder.clone(), auction_english.clone(), &void_auction, &[]);
@@ -690,6 +819,7 @@ fn try_auction_void() {
         &mut router,
         &creator,
         &auction_english,
+        &collection,
         token_id.to_string(),
         block_time.plus_seconds(ONE_DAY),
         block_time.plus_seconds(ONE_DAY * 2),
@@ -701,7 +831,7 @@ fn try_auction_void() {
     // Create an auction bid
     setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY + TEN_MINS).seconds());
     let prev_bidder_balance = router.wrap().query_all_balances(bidder.clone()).unwrap().into_iter().nth(0).unwrap();
-    auction_bid(&mut router, &bidder, &auction_english, token_id.to_string(), 150u128);
+    auction_bid(&mut router, &bidder, &auction_english, &collection, token_id.to_string(), 150u128);
 
     setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY * 3 + TEN_MINS).seconds());
 
@@ -717,6 +847,7 @@ fn try_auction_void() {
     assert_eq!(res.owner, auction_english.to_string());
 
     let void_auction = ExecuteMsg::VoidAuction {
+        collection: collection.to_string(),
         token_id: token_id.to_string(),
     };
     let res = router.execute_contract(bidder2.clone(), auction_english.clone(), &void_auction, &[]);
@@ -756,6 +887,7 @@ fn try_auction_queries() {
             &mut router,
             &creator,
             &auction_english,
+            &collection,
             idx.to_string(),
             block_time.plus_seconds(ONE_DAY + idx as u64),
             block_time.plus_seconds(ONE_DAY * 2 + idx as u64),
@@ -768,6 +900,7 @@ fn try_auction_queries() {
     // Verify that auctions can be queried by token id
     let token_id = 2u64;
     let query_auction = QueryMsg::Auction {
+        collection: collection.to_string(),
         token_id: token_id.to_string()
     };
     let res: AuctionResponse = router
@@ -775,6 +908,7 @@ fn try_auction_queries() {
         .query_wasm_smart(auction_english.clone(), &query_auction)
         .unwrap();
     assert_eq!(Auction {
+        collection: collection.clone(),
         token_id: token_id.to_string(),
         seller: creator.clone(),
         start_time: block_time.plus_seconds(ONE_DAY + token_id),
@@ -783,6 +917,7 @@ fn try_auction_queries() {
         reserve_price: Some(coin(200u128 + token_id as u128, NATIVE_DENOM)),
         funds_recipient: None,
         highest_bid: None,
+        bid_gate: None,
     }, res.auction.unwrap());
     assert_eq!(AuctionStatus::Pending, res.auction_status.unwrap());
 
@@ -801,6 +936,7 @@ fn try_auction_queries() {
         .unwrap();
     for n in 1..5 {
         assert_eq!(Auction {
+            collection: collection.clone(),
             token_id: n.to_string(),
             seller: creator.clone(),
             start_time: block_time.plus_seconds(ONE_DAY + n),
@@ -808,7 +944,8 @@ fn try_auction_queries() {
             starting_price: coin(100u128 + n as u128, NATIVE_DENOM),
             reserve_price: Some(coin(200u128 + n as u128, NATIVE_DENOM)),
             funds_recipient: None,
-            highest_bid: None
+            highest_bid: None,
+            bid_gate: None,
         }, res.clone().auctions.into_iter().nth(n as usize - 1).unwrap());
     }
 
@@ -827,6 +964,7 @@ fn try_auction_queries() {
         .unwrap();
     for n in 4..0 {
         assert_eq!(Auction {
+            collection: collection.clone(),
             token_id: token_id.to_string(),
             seller: creator.clone(),
             start_time: block_time.plus_seconds(ONE_DAY + n),
@@ -834,14 +972,15 @@ fn try_auction_queries() {
             starting_price: coin(100u128 + n as u128, NATIVE_DENOM),
             reserve_price: Some(coin(200u128 + n as u128, NATIVE_DENOM)),
             funds_recipient: None,
-            highest_bid: None
+            highest_bid: None,
+            bid_gate: None,
         }, res.clone().auctions.into_iter().nth(n as usize).unwrap());
     }
 
     // Verify that auctions can be sorted by highest bid price
     setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY + 10u64).seconds());
-    auction_bid(&mut router, &bidder, &auction_english, "1".to_string(), 140u128);
-    auction_bid(&mut router, &bidder2, &auction_english, "3".to_string(), 250u128);
+    auction_bid(&mut router, &bidder, &auction_english, &collection, "1".to_string(), 140u128);
+    auction_bid(&mut router, &bidder2, &auction_english, &collection, "3".to_string(), 250u128);
     let query_auctions = QueryMsg::AuctionsByHighestBidPrice {
         query_options: QueryOptions {
             descending: Some(true),
@@ -856,6 +995,7 @@ fn try_auction_queries() {
         .unwrap();
     let n = 3;
     assert_eq!(Auction {
+        collection: collection.clone(),
         token_id: n.to_string(),
         seller: creator.clone(),
         start_time: block_time.plus_seconds(ONE_DAY + n),
@@ -864,9 +1004,11 @@ fn try_auction_queries() {
         reserve_price: Some(coin(200u128 + n as u128, NATIVE_DENOM)),
         funds_recipient: None,
         highest_bid: Some(AuctionBid { price: coin(250u128, "ujunox".to_string()), bidder: bidder2.clone() }),
+        bid_gate: None,
     }, res.clone().auctions.into_iter().nth(0).unwrap());
     let n = 1;
     assert_eq!(Auction {
+        collection: collection.clone(),
         token_id: n.to_string(),
         seller: creator.clone(),
         start_time: block_time.plus_seconds(ONE_DAY + n),
@@ -875,9 +1017,11 @@ fn try_auction_queries() {
         reserve_price: Some(coin(200u128 + n as u128, NATIVE_DENOM)),
         funds_recipient: None,
         highest_bid: Some(AuctionBid { price: coin(140u128, "ujunox".to_string()), bidder: bidder.clone() }),
+        bid_gate: None,
     }, res.clone().auctions.into_iter().nth(1).unwrap());
     let n = 4;
     assert_eq!(Auction {
+        collection: collection.clone(),
         token_id: n.to_string(),
         seller: creator.clone(),
         start_time: block_time.plus_seconds(ONE_DAY + n),
@@ -886,6 +1030,7 @@ fn try_auction_queries() {
         reserve_price: Some(coin(200u128 + n as u128, NATIVE_DENOM)),
         funds_recipient: None,
         highest_bid: None,
+        bid_gate: None,
     }, res.clone().auctions.into_iter().nth(2).unwrap());
 
     // Verify that auctions can be queried by seller
@@ -895,6 +1040,7 @@ fn try_auction_queries() {
             descending: None,
             filter_expiry: None,
             start_after: Some(TokenTimestampOffset {
+                collection: collection.clone(),
                 token_id: "1".to_string(),
                 timestamp: block_time.plus_seconds(ONE_DAY * 2 + 1),
             }),
@@ -911,6 +1057,7 @@ fn try_auction_queries() {
             _ => None,
         };
         assert_eq!(Auction {
+            collection: collection.clone(),
             token_id: n.to_string(),
             seller: creator.clone(),
             start_time: block_time.plus_seconds(ONE_DAY + n),
@@ -918,7 +1065,8 @@ fn try_auction_queries() {
             starting_price: coin(100u128 + n as u128, NATIVE_DENOM),
             reserve_price: Some(coin(200u128 + n as u128, NATIVE_DENOM)),
             funds_recipient: None,
-            highest_bid: highest_bid
+            highest_bid: highest_bid,
+            bid_gate: None,
         }, res.clone().auctions.into_iter().nth(n as usize - 2).unwrap());
     }
 
@@ -940,6 +1088,7 @@ fn try_auction_queries() {
     assert_eq!(res.auctions.len(), 1);
     let n = 1;
     assert_eq!(Auction {
+        collection: collection.clone(),
         token_id: n.to_string(),
         seller: creator.clone(),
         start_time: block_time.plus_seconds(ONE_DAY + n),
@@ -948,5 +1097,828 @@ fn try_auction_queries() {
         reserve_price: Some(coin(200u128 + n as u128, NATIVE_DENOM)),
         funds_recipient: None,
         highest_bid: Some(AuctionBid { price: coin(140u128, "ujunox".to_string()), bidder: bidder.clone() }),
+        bid_gate: None,
     }, res.clone().auctions.into_iter().nth(0).unwrap());
-}
\ No newline at end of file
+}
+
+#[test]
+fn try_settle_expired() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+    let keeper = Addr::unchecked("keeper");
+
+    // Instantiate and configure contracts
+    let (auction_english, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Auction "1" meets the reserve price, "2" does not, "3" has no bids, "4" ends later
+    for idx in 1..5 {
+        let end_time = match idx {
+            4 => block_time.plus_seconds(ONE_DAY * 4),
+            _ => block_time.plus_seconds(ONE_DAY * 2 + idx as u64),
+        };
+        mint(&mut router, &creator, &collection, idx.to_string());
+        approve(&mut router, &creator, &collection, &auction_english, idx.to_string());
+        auction(
+            &mut router,
+            &creator,
+            &auction_english,
+            &collection,
+            idx.to_string(),
+            block_time.plus_seconds(ONE_DAY),
+            end_time,
+            110u128,
+            210u128,
+            None,
+        );
+    }
+
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY + 10u64).seconds());
+    let prev_bidder2_balance = router.wrap().query_all_balances(bidder2.clone()).unwrap().into_iter().nth(0).unwrap();
+    auction_bid(&mut router, &bidder, &auction_english, &collection, "1".to_string(), 1000u128);
+    auction_bid(&mut router, &bidder2, &auction_english, &collection, "2".to_string(), 150u128);
+
+    // Nothing is settled while auctions are Closed
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY * 2 + TEN_MINS).seconds());
+    let settle_expired = ExecuteMsg::SettleExpired { limit: None };
+    let res = router.execute_contract(keeper.clone(), auction_english.clone(), &settle_expired, &[]);
+    assert!(res.is_ok());
+    let res: AuctionsResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &QueryMsg::AuctionsByEndTime {
+            query_options: QueryOptions {
+                descending: None,
+                filter_expiry: None,
+                start_after: None,
+                limit: None,
+            }
+        })
+        .unwrap();
+    assert_eq!(res.auctions.len(), 4);
+
+    // Expired auctions are settled according to the reserve price, later auctions are untouched
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY * 3 + TEN_MINS).seconds());
+    let res = router.execute_contract(keeper.clone(), auction_english.clone(), &settle_expired, &[]);
+    assert!(res.is_ok());
+    let res: AuctionsResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &QueryMsg::AuctionsByEndTime {
+            query_options: QueryOptions {
+                descending: None,
+                filter_expiry: None,
+                start_after: None,
+                limit: None,
+            }
+        })
+        .unwrap();
+    assert_eq!(res.auctions.len(), 1);
+    assert_eq!(res.auctions[0].token_id, "4".to_string());
+
+    // Check NFTs are transferred to the winning bidder and back to the seller
+    for (token_id, owner) in [("1", &bidder), ("2", &creator), ("3", &creator)] {
+        let res: OwnerOfResponse = router
+            .wrap()
+            .query_wasm_smart(collection.clone(), &Cw721QueryMsg::OwnerOf {
+                token_id: token_id.to_string(),
+                include_expired: None,
+            })
+            .unwrap();
+        assert_eq!(res.owner, owner.to_string());
+    }
+
+    // Check the losing bidder was refunded, and the keeper was paid 10% of the 2% market fee
+    let post_bidder2_balance = router.wrap().query_all_balances(bidder2.clone()).unwrap().into_iter().nth(0).unwrap();
+    assert_eq!(prev_bidder2_balance.amount, post_bidder2_balance.amount);
+    let keeper_balance = router.wrap().query_all_balances(keeper.clone()).unwrap().into_iter().nth(0).unwrap();
+    assert_eq!(Uint128::from(2u128), keeper_balance.amount);
+}
+
+#[test]
+fn try_settlement_preview() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (auction_english, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &auction_english, TOKEN_ID.to_string());
+    auction(
+        &mut router,
+        &creator,
+        &auction_english,
+        &collection,
+        TOKEN_ID.to_string(),
+        block_time.plus_seconds(ONE_DAY),
+        block_time.plus_seconds(ONE_DAY * 2),
+        110u128,
+        210u128,
+        Some(bidder.to_string()),
+    );
+
+    // Preview should error without a price when the auction has no bids
+    let query_preview = QueryMsg::SettlementPreview {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID.to_string(),
+        price: None,
+    };
+    let res: Result<SettlementPreviewResponse, _> = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_preview);
+    assert!(res.is_err());
+
+    // Preview a hypothetical price
+    let query_preview = QueryMsg::SettlementPreview {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID.to_string(),
+        price: Some(Uint128::from(500u128)),
+    };
+    let res: SettlementPreviewResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_preview)
+        .unwrap();
+    assert_eq!(SettlementPreviewResponse {
+        price: coin(500u128, NATIVE_DENOM),
+        market_fee: coin(10u128, NATIVE_DENOM),
+        keeper_fee: coin(1u128, NATIVE_DENOM),
+        collector_fee: coin(9u128, NATIVE_DENOM),
+        royalty_recipient: Some(creator.to_string()),
+        royalty_amount: coin(50u128, NATIVE_DENOM),
+        seller_recipient: bidder.clone(),
+        seller_amount: coin(440u128, NATIVE_DENOM),
+    }, res);
+
+    // Preview defaults to the highest bid price
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY + 10u64).seconds());
+    auction_bid(&mut router, &bidder, &auction_english, &collection, TOKEN_ID.to_string(), 1000u128);
+    let query_preview = QueryMsg::SettlementPreview {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID.to_string(),
+        price: None,
+    };
+    let res: SettlementPreviewResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_preview)
+        .unwrap();
+    assert_eq!(SettlementPreviewResponse {
+        price: coin(1000u128, NATIVE_DENOM),
+        market_fee: coin(20u128, NATIVE_DENOM),
+        keeper_fee: coin(2u128, NATIVE_DENOM),
+        collector_fee: coin(18u128, NATIVE_DENOM),
+        royalty_recipient: Some(creator.to_string()),
+        royalty_amount: coin(100u128, NATIVE_DENOM),
+        seller_recipient: bidder.clone(),
+        seller_amount: coin(880u128, NATIVE_DENOM),
+    }, res);
+}
+
+#[test]
+fn try_proxy_auction_bid() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (auction_english, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &auction_english, TOKEN_ID.to_string());
+    auction(
+        &mut router,
+        &creator,
+        &auction_english,
+        &collection,
+        TOKEN_ID.to_string(),
+        block_time.plus_seconds(ONE_DAY),
+        block_time.plus_seconds(ONE_DAY * 2),
+        110u128,
+        210u128,
+        None,
+    );
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY + 10u64).seconds());
+
+    let bidder_balance_a = router.wrap().query_all_balances(bidder.clone()).unwrap().into_iter().nth(0).unwrap();
+    let bidder2_balance_a = router.wrap().query_all_balances(bidder2.clone()).unwrap().into_iter().nth(0).unwrap();
+
+    // Proxy bid should error when max price is below the bid price
+    let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID.to_string(),
+        price: coin(120u128, NATIVE_DENOM),
+        max_price: Some(coin(115u128, NATIVE_DENOM)),
+        proof: None,
+    };
+    let res = router.execute_contract(bidder.clone(), auction_english.clone(), &set_auction_bid, &[coin(115u128, NATIVE_DENOM)]);
+    assert_eq!(&res.unwrap_err().root_cause().to_string(), "Invalid max price: max_price 115 < price 120");
+
+    // Proxy bid should error when the max price is not escrowed
+    let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID.to_string(),
+        price: coin(120u128, NATIVE_DENOM),
+        max_price: Some(coin(300u128, NATIVE_DENOM)),
+        proof: None,
+    };
+    let res = router.execute_contract(bidder.clone(), auction_english.clone(), &set_auction_bid, &[coin(120u128, NATIVE_DENOM)]);
+    assert_eq!(&res.unwrap_err().root_cause().to_string(), "Incorrect bid payment: expected 300, actual 120");
+
+    // Proxy bidder escrows the max price, the visible bid is the bid price
+    proxy_auction_bid(&mut router, &bidder, &auction_english, &collection, TOKEN_ID.to_string(), 120u128, 300u128);
+    let query_auction = QueryMsg::Auction {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID.to_string()
+    };
+    let res: AuctionResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_auction)
+        .unwrap();
+    assert_eq!(Some(AuctionBid {
+        bidder: bidder.clone(),
+        price: coin(120u128, NATIVE_DENOM),
+    }), res.auction.unwrap().highest_bid);
+
+    // A lower bid raises the proxy bid by the min increment, and is refunded
+    let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID.to_string(),
+        price: coin(150u128, NATIVE_DENOM),
+        max_price: None,
+        proof: None,
+    };
+    let bid_res = router
+        .execute_contract(bidder2.clone(), auction_english.clone(), &set_auction_bid, &[coin(150u128, NATIVE_DENOM)])
+        .unwrap();
+    let res: AuctionResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_auction)
+        .unwrap();
+    assert_eq!(Some(AuctionBid {
+        bidder: bidder.clone(),
+        price: coin(153u128, NATIVE_DENOM),
+    }), res.auction.unwrap().highest_bid);
+    let bidder2_balance_b = router.wrap().query_all_balances(bidder2.clone()).unwrap().into_iter().nth(0).unwrap();
+    assert_eq!(bidder2_balance_a.amount, bidder2_balance_b.amount);
+
+    // The bid event names the highest bidder, the outbid event names the refunded bidder
+    for (ty, expected_bidder, expected_price) in [
+        ("wasm-set-auction-bid", &bidder, "153"),
+        ("wasm-outbid-auction-bid", &bidder2, "150"),
+    ] {
+        let event = bid_res.events.iter().find(|e| e.ty == ty).unwrap();
+        let attr = |key: &str| event.attributes.iter().find(|a| a.key == key).unwrap().value.clone();
+        assert_eq!(attr("bidder"), expected_bidder.to_string());
+        assert_eq!(attr("price"), format!("{}{}", expected_price, NATIVE_DENOM));
+    }
+
+    // The highest bidder raises their max bid by topping up the escrow, the visible bid is unchanged
+    let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID.to_string(),
+        price: coin(160u128, NATIVE_DENOM),
+        max_price: Some(coin(350u128, NATIVE_DENOM)),
+        proof: None,
+    };
+    let res = router.execute_contract(bidder.clone(), auction_english.clone(), &set_auction_bid, &[coin(350u128, NATIVE_DENOM)]);
+    assert_eq!(&res.unwrap_err().root_cause().to_string(), "Incorrect bid payment: expected 50, actual 350");
+    let res = router.execute_contract(bidder.clone(), auction_english.clone(), &set_auction_bid, &[coin(50u128, NATIVE_DENOM)]);
+    assert!(res.is_ok());
+    let res: AuctionResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_auction)
+        .unwrap();
+    assert_eq!(Some(AuctionBid {
+        bidder: bidder.clone(),
+        price: coin(153u128, NATIVE_DENOM),
+    }), res.auction.unwrap().highest_bid);
+
+    // A higher max bid wins, starting just above the previous max bid
+    proxy_auction_bid(&mut router, &bidder2, &auction_english, &collection, TOKEN_ID.to_string(), 200u128, 400u128);
+    let res: AuctionResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_auction)
+        .unwrap();
+    assert_eq!(Some(AuctionBid {
+        bidder: bidder2.clone(),
+        price: coin(353u128, NATIVE_DENOM),
+    }), res.auction.unwrap().highest_bid);
+    let bidder_balance_b = router.wrap().query_all_balances(bidder.clone()).unwrap().into_iter().nth(0).unwrap();
+    assert_eq!(bidder_balance_a.amount, bidder_balance_b.amount);
+
+    // Finalized auction charges the visible bid, and refunds the unused escrow
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY * 2 + TEN_MINS * 2).seconds());
+    let finalize_auction = ExecuteMsg::FinalizeAuction {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res = router.execute_contract(bidder.clone(), auction_english.clone(), &finalize_auction, &[]);
+    assert!(res.is_ok());
+
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &Cw721QueryMsg::OwnerOf {
+            token_id: TOKEN_ID.to_string(),
+            include_expired: None,
+        })
+        .unwrap();
+    assert_eq!(res.owner, bidder2.to_string());
+    let bidder2_balance_c = router.wrap().query_all_balances(bidder2.clone()).unwrap().into_iter().nth(0).unwrap();
+    assert_eq!(bidder2_balance_a.amount - Uint128::from(353u128), bidder2_balance_c.amount);
+}
+
+#[test]
+fn try_gated_auction_bid() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (auction_english, collection) = setup_contracts(&mut router, &creator).unwrap();
+    let whitelist_id = router.store_code(contract_whitelist());
+    let msg = WhitelistInstantiateMsg {
+        members: vec![bidder.to_string()],
+        start_time: block_time.plus_seconds(ONE_DAY),
+        end_time: block_time.plus_seconds(ONE_DAY * 2),
+        unit_price: coin(100, NATIVE_DENOM),
+        per_address_limit: 1,
+        member_limit: 10,
+        merkle_root: None,
+    };
+    let whitelist = router
+        .instantiate_contract(whitelist_id, creator.clone(), &msg, &[], "Whitelist", None)
+        .unwrap();
+
+    // Merkle whitelist of bidder2 and another member
+    let leaf = Sha256::digest(bidder2.as_bytes()).to_vec();
+    let sibling = Sha256::digest(b"other").to_vec();
+    let mut hasher = Sha256::new();
+    if leaf <= sibling {
+        hasher.update(&leaf);
+        hasher.update(&sibling);
+    } else {
+        hasher.update(&sibling);
+        hasher.update(&leaf);
+    }
+    let msg = WhitelistInstantiateMsg {
+        members: vec![],
+        merkle_root: Some(hex::encode(hasher.finalize())),
+        ..msg
+    };
+    let merkle_whitelist = router
+        .instantiate_contract(whitelist_id, creator.clone(), &msg, &[], "Merkle Whitelist", None)
+        .unwrap();
+
+    // Bid gate addresses are validated
+    mint(&mut router, &creator, &collection, "0".to_string());
+    approve(&mut router, &creator, &collection, &auction_english, "0".to_string());
+    let set_auction = ExecuteMsg::SetAuction {
+        collection: collection.to_string(),
+        token_id: "0".to_string(),
+        start_time: block_time.plus_seconds(ONE_DAY),
+        end_time: block_time.plus_seconds(ONE_DAY * 2),
+        starting_price: coin(110, NATIVE_DENOM),
+        reserve_price: Some(coin(210, NATIVE_DENOM)),
+        funds_recipient: None,
+        bid_gate: Some(BidGateMsg::Whitelist("INVALID".to_string())),
+    };
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
+    assert!(res.is_err());
+
+    // Create a whitelist gated auction and a collection gated auction
+    let gates = vec![
+        ("1", BidGateMsg::Whitelist(whitelist.to_string())),
+        ("2", BidGateMsg::Collection(collection.to_string())),
+        ("4", BidGateMsg::Whitelist(merkle_whitelist.to_string())),
+    ];
+    for (token_id, bid_gate) in gates {
+        mint(&mut router, &creator, &collection, token_id.to_string());
+        approve(&mut router, &creator, &collection, &auction_english, token_id.to_string());
+        let set_auction = ExecuteMsg::SetAuction {
+            collection: collection.to_string(),
+            token_id: token_id.to_string(),
+            start_time: block_time.plus_seconds(ONE_DAY),
+            end_time: block_time.plus_seconds(ONE_DAY * 2),
+            starting_price: coin(110, NATIVE_DENOM),
+            reserve_price: Some(coin(210, NATIVE_DENOM)),
+            funds_recipient: None,
+            bid_gate: Some(bid_gate),
+        };
+        let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
+        assert!(res.is_ok());
+    }
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY + 10u64).seconds());
+
+    // Only whitelist members can bid on the whitelist gated auction
+    let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection.to_string(),
+        token_id: "1".to_string(),
+        price: coin(120u128, NATIVE_DENOM),
+        max_price: None,
+        proof: None,
+    };
+    let res = router.execute_contract(bidder2.clone(), auction_english.clone(), &set_auction_bid, &[coin(120u128, NATIVE_DENOM)]);
+    assert_eq!(&res.unwrap_err().root_cause().to_string(), "Bid gate restriction: bidder is not a whitelist member");
+    auction_bid(&mut router, &bidder, &auction_english, &collection, "1".to_string(), 120u128);
+
+    // Only collection holders can bid on the collection gated auction
+    let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection.to_string(),
+        token_id: "2".to_string(),
+        price: coin(120u128, NATIVE_DENOM),
+        max_price: None,
+        proof: None,
+    };
+    let res = router.execute_contract(bidder2.clone(), auction_english.clone(), &set_auction_bid, &[coin(120u128, NATIVE_DENOM)]);
+    assert_eq!(&res.unwrap_err().root_cause().to_string(), "Bid gate restriction: bidder does not hold a collection token");
+
+    let mint_for_bidder2_msg = Cw721ExecuteMsg::Mint(MintMsg {
+        token_id: "3".to_string(),
+        owner: bidder2.to_string(),
+        token_uri: None,
+        extension: Empty {},
+    });
+    let res = router.execute_contract(creator.clone(), collection.clone(), &mint_for_bidder2_msg, &[]);
+    assert!(res.is_ok());
+    auction_bid(&mut router, &bidder2, &auction_english, &collection, "2".to_string(), 120u128);
+
+    // Merkle whitelist members bid with a proof of membership
+    let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection.to_string(),
+        token_id: "4".to_string(),
+        price: coin(120u128, NATIVE_DENOM),
+        max_price: None,
+        proof: None,
+    };
+    let res = router.execute_contract(bidder2.clone(), auction_english.clone(), &set_auction_bid, &[coin(120u128, NATIVE_DENOM)]);
+    assert_eq!(&res.unwrap_err().root_cause().to_string(), "Bid gate restriction: bidder is not a whitelist member");
+    let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection.to_string(),
+        token_id: "4".to_string(),
+        price: coin(120u128, NATIVE_DENOM),
+        max_price: None,
+        proof: Some(vec![hex::encode(&sibling)]),
+    };
+    let res = router.execute_contract(bidder.clone(), auction_english.clone(), &set_auction_bid, &[coin(120u128, NATIVE_DENOM)]);
+    assert_eq!(&res.unwrap_err().root_cause().to_string(), "Bid gate restriction: bidder is not a whitelist member");
+    let res = router.execute_contract(bidder2.clone(), auction_english.clone(), &set_auction_bid, &[coin(120u128, NATIVE_DENOM)]);
+    assert!(res.is_ok());
+}
+
+#[test]
+fn try_auction_house() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+    // Setup intial accounts
+    let (_owner, bidder, creator, _bidder2) = setup_accounts(&mut router).unwrap();
+    let operator = Addr::unchecked("operator");
+    let alt_denom = "uatom";
+    router
+        .sudo(CwSudoMsg::Bank({
+            BankSudo::Mint {
+                to_address: bidder.to_string(),
+                amount: coins(INITIAL_BALANCE, alt_denom),
+            }
+        }))
+        .unwrap();
+
+    // Instantiate and configure contracts
+    let (auction_english, collection) = setup_contracts(&mut router, &creator).unwrap();
+    let pg721_id = router.store_code(contract_pg721());
+    let msg = Pg721InstantiateMsg {
+        name: String::from("Second Coin"),
+        symbol: String::from("SECOND"),
+        minter: creator.to_string(),
+        collection_info: CollectionInfo {
+            creator: creator.to_string(),
+            description: String::from("Passage Apes"),
+            image: "https://example.com/image.png".to_string(),
+            external_link: None,
+            royalty_info: None,
+        },
+    };
+    let collection2 = router
+        .instantiate_contract(pg721_id, creator.clone(), &msg, &[], "NFT", None)
+        .unwrap();
+
+    mint(&mut router, &creator, &collection, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection, &auction_english, TOKEN_ID.to_string());
+    mint(&mut router, &creator, &collection2, TOKEN_ID.to_string());
+    approve(&mut router, &creator, &collection2, &auction_english, TOKEN_ID.to_string());
+
+    // Auctions can only be created for approved collections and denoms
+    let set_auction = ExecuteMsg::SetAuction {
+        collection: collection2.to_string(),
+        token_id: TOKEN_ID.to_string(),
+        start_time: block_time.plus_seconds(ONE_DAY),
+        end_time: block_time.plus_seconds(ONE_DAY * 2),
+        starting_price: coin(110, alt_denom),
+        reserve_price: Some(coin(210, alt_denom)),
+        funds_recipient: None,
+        bid_gate: None,
+    };
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
+    assert_eq!(&res.unwrap_err().root_cause().to_string(), &format!("Collection not approved: {}", collection2));
+
+    let update_config = ExecuteMsg::UpdateConfig {
+        collections: Some(vec![collection.to_string(), collection2.to_string()]),
+        denoms: None,
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: None,
+        min_duration: None,
+        max_duration: None,
+        closed_duration: None,
+        buffer_duration: None,
+        keeper_fee_bps: None,
+    };
+    let res = router.execute_contract(operator.clone(), auction_english.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
+    assert_eq!(&res.unwrap_err().root_cause().to_string(), "Denom not approved: uatom");
+
+    let update_config = ExecuteMsg::UpdateConfig {
+        collections: None,
+        denoms: Some(vec![
+            native_denom_config(),
+            DenomConfig {
+                denom: alt_denom.to_string(),
+                min_price: Uint128::from(100u128),
+                min_bid_increment: Uint128::from(20u128),
+            },
+        ]),
+        collector_address: None,
+        trading_fee_bps: None,
+        operators: None,
+        min_duration: None,
+        max_duration: None,
+        closed_duration: None,
+        buffer_duration: None,
+        keeper_fee_bps: None,
+    };
+    let res = router.execute_contract(operator.clone(), auction_english.clone(), &update_config, &[]);
+    assert!(res.is_ok());
+
+    // The starting price must meet the min price of the auction denom
+    let low_set_auction = ExecuteMsg::SetAuction {
+        collection: collection2.to_string(),
+        token_id: TOKEN_ID.to_string(),
+        start_time: block_time.plus_seconds(ONE_DAY),
+        end_time: block_time.plus_seconds(ONE_DAY * 2),
+        starting_price: coin(50, alt_denom),
+        reserve_price: None,
+        funds_recipient: None,
+        bid_gate: None,
+    };
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &low_set_auction, &[]);
+    assert_eq!(&res.unwrap_err().root_cause().to_string(), "Invalid price");
+    let res = router.execute_contract(creator.clone(), auction_english.clone(), &set_auction, &[]);
+    assert!(res.is_ok());
+
+    // The same token id can be auctioned from each collection
+    auction(
+        &mut router,
+        &creator,
+        &auction_english,
+        &collection,
+        TOKEN_ID.to_string(),
+        block_time.plus_seconds(ONE_DAY),
+        block_time.plus_seconds(ONE_DAY * 3),
+        110u128,
+        210u128,
+        None,
+    );
+    let query_auctions = QueryMsg::AuctionsByCollectionEndTime {
+        collection: collection2.to_string(),
+        query_options: QueryOptions {
+            descending: None,
+            filter_expiry: None,
+            start_after: None,
+            limit: None,
+        }
+    };
+    let res: AuctionsResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_auctions)
+        .unwrap();
+    assert_eq!(res.auctions.len(), 1);
+    assert_eq!(res.auctions[0].collection, collection2);
+
+    // Bids must be paid in the auction denom
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY + 10u64).seconds());
+    let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection2.to_string(),
+        token_id: TOKEN_ID.to_string(),
+        price: coin(250u128, NATIVE_DENOM),
+        max_price: None,
+        proof: None,
+    };
+    let res = router.execute_contract(bidder.clone(), auction_english.clone(), &set_auction_bid, &[coin(250u128, NATIVE_DENOM)]);
+    assert_eq!(&res.unwrap_err().root_cause().to_string(), "Invalid price");
+    let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection2.to_string(),
+        token_id: TOKEN_ID.to_string(),
+        price: coin(250u128, alt_denom),
+        max_price: None,
+        proof: None,
+    };
+    let res = router.execute_contract(bidder.clone(), auction_english.clone(), &set_auction_bid, &[coin(250u128, alt_denom)]);
+    assert!(res.is_ok());
+
+    // The next bid must clear the min bid increment of the auction denom
+    let query_auction = QueryMsg::Auction {
+        collection: collection2.to_string(),
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res: AuctionResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_auction)
+        .unwrap();
+    assert_eq!(res.next_bid_min, Some(Uint128::from(270u128)));
+    let set_auction_bid = ExecuteMsg::SetAuctionBid {
+        collection: collection2.to_string(),
+        token_id: TOKEN_ID.to_string(),
+        price: coin(260u128, alt_denom),
+        max_price: None,
+        proof: None,
+    };
+    let res = router.execute_contract(bidder.clone(), auction_english.clone(), &set_auction_bid, &[coin(260u128, alt_denom)]);
+    assert_eq!(&res.unwrap_err().root_cause().to_string(), "Auction bid too low");
+
+    // Sale is paid out in the auction denom, without royalties from the other collection
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY * 2 + 10u64).seconds());
+    let finalize_auction = ExecuteMsg::FinalizeAuction {
+        collection: collection2.to_string(),
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res = router.execute_contract(bidder.clone(), auction_english.clone(), &finalize_auction, &[]);
+    assert!(res.is_ok());
+
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection2.clone(), &Cw721QueryMsg::OwnerOf {
+            token_id: TOKEN_ID.to_string(),
+            include_expired: None,
+        })
+        .unwrap();
+    assert_eq!(res.owner, bidder.to_string());
+    let creator_balance = router.wrap().query_balance(creator.clone(), alt_denom).unwrap();
+    assert_eq!(Uint128::from(250u128), creator_balance.amount);
+
+    // The auction for the first collection is unaffected
+    let query_auction = QueryMsg::Auction {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID.to_string(),
+    };
+    let res: AuctionResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &query_auction)
+        .unwrap();
+    assert_eq!(AuctionStatus::Open, res.auction_status.unwrap());
+}
+
+#[test]
+fn try_migrate_v0_1() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+    // Setup intial accounts
+    let (_owner, bidder, creator, bidder2) = setup_accounts(&mut router).unwrap();
+    let keeper = Addr::unchecked("keeper");
+
+    // Instantiate and configure contracts
+    let (_, collection) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Auction "1" meets the reserve price, "2" does not
+    let legacy_auction = |token_id: &str, bidder: &Addr, price: u128| AuctionV0_1 {
+        token_id: token_id.to_string(),
+        seller: creator.clone(),
+        start_time: block_time.plus_seconds(ONE_DAY),
+        end_time: block_time.plus_seconds(ONE_DAY * 2),
+        starting_price: coin(110, NATIVE_DENOM),
+        reserve_price: Some(coin(210, NATIVE_DENOM)),
+        funds_recipient: None,
+        highest_bid: Some(AuctionBid {
+            bidder: bidder.clone(),
+            price: coin(price, NATIVE_DENOM),
+        }),
+    };
+    let msg = LegacyInstantiateMsg {
+        config: ConfigV0_1 {
+            cw721_address: collection.clone(),
+            denom: NATIVE_DENOM.to_string(),
+            collector_address: creator.clone(),
+            trading_fee_percent: Decimal::percent(TRADING_FEE_BPS),
+            operators: vec![Addr::unchecked("operator")],
+            min_price: Uint128::from(5u128),
+            min_bid_increment: Uint128::from(3u128),
+            min_duration: ONE_DAY,
+            max_duration: SIX_MOS,
+            closed_duration: ONE_DAY,
+            buffer_duration: TEN_MINS,
+        },
+        auctions: vec![
+            legacy_auction("1", &bidder, 1000u128),
+            legacy_auction("2", &bidder2, 150u128),
+        ],
+    };
+    let legacy_id = router.store_code(contract_auction_english_v0_1());
+    let auction_english = router
+        .instantiate_contract(
+            legacy_id,
+            creator.clone(),
+            &msg,
+            &[],
+            "English Auction",
+            Some(creator.to_string()),
+        )
+        .unwrap();
+
+    // The legacy contract holds the NFTs and the escrowed bids
+    for token_id in ["1", "2"] {
+        mint(&mut router, &creator, &collection, token_id.to_string());
+        let transfer_nft = Cw721ExecuteMsg::<Empty>::TransferNft {
+            recipient: auction_english.to_string(),
+            token_id: token_id.to_string(),
+        };
+        let res = router.execute_contract(creator.clone(), collection.clone(), &transfer_nft, &[]);
+        assert!(res.is_ok());
+    }
+    router
+        .sudo(CwSudoMsg::Bank({
+            BankSudo::Mint {
+                to_address: auction_english.to_string(),
+                amount: coins(1150, NATIVE_DENOM),
+            }
+        }))
+        .unwrap();
+
+    // Migrate to the current version, enabling the keeper fee
+    let auction_english_id = router.store_code(contract_auction_english());
+    let migrate_msg = MigrateMsg {
+        collections: None,
+        denoms: None,
+        keeper_fee_bps: Some(KEEPER_FEE_BPS),
+    };
+    let res = router.migrate_contract(creator.clone(), auction_english.clone(), &migrate_msg, auction_english_id);
+    assert!(res.is_ok());
+
+    let res: ConfigResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &QueryMsg::Config {})
+        .unwrap();
+    assert_eq!(res.config.collections, vec![collection.clone()]);
+    assert_eq!(res.config.denoms, vec![native_denom_config()]);
+    assert_eq!(res.config.keeper_fee_percent, Decimal::percent(KEEPER_FEE_BPS));
+
+    // Auctions are keyed by collection, and remain indexed by end time
+    let res: AuctionResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &QueryMsg::Auction {
+            collection: collection.to_string(),
+            token_id: "1".to_string(),
+        })
+        .unwrap();
+    let migrated_auction = res.auction.unwrap();
+    assert_eq!(migrated_auction.collection, collection);
+    assert_eq!(migrated_auction.highest_bid.unwrap().price, coin(1000, NATIVE_DENOM));
+    assert_eq!(migrated_auction.bid_gate, None);
+
+    let res: AuctionsResponse = router
+        .wrap()
+        .query_wasm_smart(auction_english.clone(), &QueryMsg::AuctionsByEndTime {
+            query_options: QueryOptions {
+                descending: None,
+                filter_expiry: None,
+                start_after: None,
+                limit: None,
+            }
+        })
+        .unwrap();
+    assert_eq!(res.auctions.len(), 2);
+
+    // Migrated auctions are settled from the escrowed bids
+    setup_block_time(&mut router, block_time.plus_seconds(ONE_DAY * 3 + TEN_MINS).seconds());
+    let settle_expired = ExecuteMsg::SettleExpired { limit: None };
+    let res = router.execute_contract(keeper.clone(), auction_english.clone(), &settle_expired, &[]);
+    assert!(res.is_ok());
+
+    for (token_id, owner) in [("1", &bidder), ("2", &creator)] {
+        let res: OwnerOfResponse = router
+            .wrap()
+            .query_wasm_smart(collection.clone(), &Cw721QueryMsg::OwnerOf {
+                token_id: token_id.to_string(),
+                include_expired: None,
+            })
+            .unwrap();
+        assert_eq!(res.owner, owner.to_string());
+    }
+    let bidder2_balance = router.wrap().query_balance(bidder2.clone(), NATIVE_DENOM).unwrap();
+    assert_eq!(Uint128::from(INITIAL_BALANCE + 150), bidder2_balance.amount);
+    let keeper_balance = router.wrap().query_balance(keeper.clone(), NATIVE_DENOM).unwrap();
+    assert_eq!(Uint128::from(2u128), keeper_balance.amount);
+    let contract_balance = router.wrap().query_balance(auction_english.clone(), NATIVE_DENOM).unwrap();
+    assert_eq!(Uint128::zero(), contract_balance.amount);
+}
@@ -1,12 +1,12 @@
 use crate::msg::{
     QueryMsg, QueryOptions, TokenTimestampOffset, TokenPriceOffset,
-    AuctionResponse, AuctionsResponse, ConfigResponse
+    AuctionResponse, AuctionsResponse, ConfigResponse, SettlementPreviewResponse
 };
 use crate::state::{
-    CONFIG, TokenId, auctions, AuctionStatus
+    CONFIG, TokenId, auctions, auction_key, AuctionStatus
 };
-use crate::helpers::option_bool_to_order;
-use cosmwasm_std::{entry_point, to_binary, Addr, Binary, Deps, Env, StdResult, Uint128};
+use crate::helpers::{calculate_payout, option_bool_to_order};
+use cosmwasm_std::{entry_point, coin, to_binary, Addr, Binary, Deps, Env, StdError, StdResult, Uint128};
 use cw_storage_plus::Bound;
 
 // Query limits
@@ -20,8 +20,9 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config { } => to_binary(&query_config(deps)?),
         QueryMsg::Auction {
+            collection,
             token_id,
-        } => to_binary(&query_auction(deps, env, token_id)?),
+        } => to_binary(&query_auction(deps, env, api.addr_validate(&collection)?, token_id)?),
         QueryMsg::AuctionsByStartTime {
             query_options
         } => to_binary(&query_auctions_by_start_time(
@@ -56,6 +57,19 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             api.addr_validate(&bidder)?,
             &query_options,
         )?),
+        QueryMsg::AuctionsByCollectionEndTime {
+            collection,
+            query_options
+        } => to_binary(&query_auctions_by_collection_end_time(
+            deps,
+            api.addr_validate(&collection)?,
+            &query_options,
+        )?),
+        QueryMsg::SettlementPreview {
+            collection,
+            token_id,
+            price,
+        } => to_binary(&query_settlement_preview(deps, api.addr_validate(&collection)?, token_id, price)?),
     }
 }
 
@@ -65,8 +79,8 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     Ok(ConfigResponse { config })
 }
 
-pub fn query_auction(deps: Deps, env: Env, token_id: TokenId) -> StdResult<AuctionResponse> {
-    let auction = auctions().may_load(deps.storage, token_id)?;
+pub fn query_auction(deps: Deps, env: Env, collection: Addr, token_id: TokenId) -> StdResult<AuctionResponse> {
+    let auction = auctions().may_load(deps.storage, auction_key(&collection, &token_id))?;
     let config = CONFIG.load(deps.storage)?;
 
     let mut auction_status: Option<AuctionStatus> = None;
@@ -76,19 +90,58 @@ pub fn query_auction(deps: Deps, env: Env, token_id: TokenId) -> StdResult<Aucti
     if let Some(_auction) = &auction {
         auction_status = Some(_auction.get_auction_status(&env.block.time, config.closed_duration));
         is_reserve_price_met = Some(_auction.is_reserve_price_met());
-        next_bid_min = Some(_auction.get_next_bid_min(config.min_bid_increment));
+        next_bid_min = config
+            .denom_config(&_auction.starting_price.denom)
+            .map(|denom_config| _auction.get_next_bid_min(denom_config.min_bid_increment));
     }
 
     Ok(AuctionResponse { auction, auction_status, is_reserve_price_met, next_bid_min })
 }
 
+pub fn query_settlement_preview(
+    deps: Deps,
+    collection: Addr,
+    token_id: TokenId,
+    price: Option<Uint128>,
+) -> StdResult<SettlementPreviewResponse> {
+    let auction = auctions().load(deps.storage, auction_key(&collection, &token_id))?;
+    let config = CONFIG.load(deps.storage)?;
+
+    // Default to the highest bid price
+    let price = match price {
+        Some(_price) => _price,
+        None => match &auction.highest_bid {
+            Some(bid) => bid.price.amount,
+            None => return Err(StdError::generic_err("auction has no bid")),
+        },
+    };
+
+    let payout_amounts = calculate_payout(deps, &auction.collection, price, &config, true)?;
+    let denom = &auction.starting_price.denom;
+    let (royalty_amount, royalty_recipient) = match payout_amounts.royalty {
+        Some((amount, recipient)) => (amount, Some(recipient)),
+        None => (Uint128::zero(), None),
+    };
+
+    Ok(SettlementPreviewResponse {
+        price: coin(price.u128(), denom),
+        market_fee: coin(payout_amounts.market_fee.u128(), denom),
+        keeper_fee: coin(payout_amounts.keeper_fee.u128(), denom),
+        collector_fee: coin((payout_amounts.market_fee - payout_amounts.keeper_fee).u128(), denom),
+        royalty_recipient,
+        royalty_amount: coin(royalty_amount.u128(), denom),
+        seller_recipient: auction.get_recipient(),
+        seller_amount: coin(payout_amounts.seller_amount.u128(), denom),
+    })
+}
+
 pub fn query_auctions_by_start_time(
     deps: Deps,
     query_options: &QueryOptions<TokenTimestampOffset>
 ) -> StdResult<AuctionsResponse> {
     let limit = query_options.limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
     let start = query_options.start_after.as_ref().map(|offset| {
-        Bound::exclusive((offset.timestamp.seconds(), offset.token_id.clone()))
+        Bound::exclusive((offset.timestamp.seconds(), auction_key(&offset.collection, &offset.token_id)))
     });
     let order = option_bool_to_order(query_options.descending);
 
@@ -116,7 +169,7 @@ pub fn query_auctions_by_end_time(
 ) -> StdResult<AuctionsResponse> {
     let limit = query_options.limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
     let start = query_options.start_after.as_ref().map(|offset| {
-        Bound::exclusive((offset.timestamp.seconds(), offset.token_id.clone()))
+        Bound::exclusive((offset.timestamp.seconds(), auction_key(&offset.collection, &offset.token_id)))
     });
     let order = option_bool_to_order(query_options.descending);
 
@@ -144,7 +197,7 @@ pub fn query_auctions_by_highest_bid_price(
 ) -> StdResult<AuctionsResponse> {
     let limit = query_options.limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
     let start = query_options.start_after.as_ref().map(|offset| {
-        Bound::exclusive((offset.price.u128(), offset.token_id.clone()))
+        Bound::exclusive((offset.price.u128(), auction_key(&offset.collection, &offset.token_id)))
     });
     let order = option_bool_to_order(query_options.descending);
 
@@ -173,7 +226,7 @@ pub fn query_auctions_by_seller_end_time(
 ) -> StdResult<AuctionsResponse> {
     let limit = query_options.limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
     let start = query_options.start_after.as_ref().map(|offset| {
-        Bound::exclusive((offset.timestamp.seconds(), offset.token_id.clone()))
+        Bound::exclusive((offset.timestamp.seconds(), auction_key(&offset.collection, &offset.token_id)))
     });
     let order = option_bool_to_order(query_options.descending);
 
@@ -203,7 +256,7 @@ pub fn query_auctions_by_highest_bidder_end_time(
 ) -> StdResult<AuctionsResponse> {
     let limit = query_options.limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
     let start = query_options.start_after.as_ref().map(|offset| {
-        Bound::exclusive((offset.timestamp.seconds(), offset.token_id.clone()))
+        Bound::exclusive((offset.timestamp.seconds(), auction_key(&offset.collection, &offset.token_id)))
     });
     let order = option_bool_to_order(query_options.descending);
 
@@ -225,3 +278,33 @@ pub fn query_auctions_by_highest_bidder_end_time(
 
     Ok(AuctionsResponse { auctions })
 }
+
+pub fn query_auctions_by_collection_end_time(
+    deps: Deps,
+    collection: Addr,
+    query_options: &QueryOptions<TokenTimestampOffset>
+) -> StdResult<AuctionsResponse> {
+    let limit = query_options.limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
+    let start = query_options.start_after.as_ref().map(|offset| {
+        Bound::exclusive((offset.timestamp.seconds(), auction_key(&offset.collection, &offset.token_id)))
+    });
+    let order = option_bool_to_order(query_options.descending);
+
+    let auctions = auctions()
+        .idx
+        .collection_end_time
+        .sub_prefix(collection.to_string())
+        .range(deps.storage, start, None, order)
+        .filter(|item| match item {
+            Ok((_, auction)) => match query_options.filter_expiry {
+                Some(ts) => ts < auction.end_time,
+                _ => true,
+            },
+            Err(_) => true,
+        })
+        .take(limit)
+        .map(|res| res.map(|item| item.1))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(AuctionsResponse { auctions })
+}
@@ -1,25 +1,21 @@
 use std::fmt::{Display, Formatter, Result};
 use cosmwasm_std::{Addr, Decimal, Timestamp, Uint128, Coin};
-use cw_storage_plus::{Index, IndexList, IndexedMap, Item, MultiIndex};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
-    /// The NFT contract
-    pub cw721_address: Addr,
-    /// The token used to pay for NFTs
-    pub denom: String,
+    /// The NFT contracts approved for auction
+    pub collections: Vec<Addr>,
+    /// The tokens approved to pay for NFTs, with their price limits
+    pub denoms: Vec<DenomConfig>,
     /// Marketplace fee collector address
     pub collector_address: Addr,
     /// Marketplace fee
     pub trading_fee_percent: Decimal,
     /// The operator addresses that have access to certain functionality
     pub operators: Vec<Addr>,
-    /// Min value for an Auction starting price
-    pub min_price: Uint128,
-    /// The minimum difference between incremental bids
-    pub min_bid_increment: Uint128,
     /// The minimum duration of an auction 
     pub min_duration: u64,
     /// The maximum duration of an auction 
@@ -28,10 +24,29 @@ pub struct Config {
     pub closed_duration: u64,
     /// The duration an Auction is extended by when a bid is placed in the final minutes
     pub buffer_duration: u64,
+    /// Portion of the market fee paid to the caller of SettleExpired
+    pub keeper_fee_percent: Decimal,
+}
+
+impl Config {
+    /// Price limits for an approved denom
+    pub fn denom_config(&self, denom: &str) -> Option<&DenomConfig> {
+        self.denoms.iter().find(|d| d.denom == denom)
+    }
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
+/// Price limits for a token approved to pay for NFTs
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DenomConfig {
+    pub denom: String,
+    /// Min value for an Auction starting price
+    pub min_price: Uint128,
+    /// The minimum difference between incremental bids
+    pub min_bid_increment: Uint128,
+}
+
 pub type TokenId = String;
 
 /// Represents a bid (offer) on an auction in the marketplace
@@ -41,9 +56,20 @@ pub struct AuctionBid {
     pub price: Coin,
 }
 
+/// Restricts which addresses may bid on an auction
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BidGate {
+    /// Only members of the whitelist contract may bid
+    Whitelist(Addr),
+    /// Only holders of a token in the cw721 collection may bid
+    Collection(Addr),
+}
+
 /// Represents an auction on the marketplace
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Auction {
+    pub collection: Addr,
     pub token_id: TokenId,
     pub seller: Addr,
     pub start_time: Timestamp,
@@ -51,7 +77,8 @@ pub struct Auction {
     pub starting_price: Coin,
     pub reserve_price: Option<Coin>,
     pub funds_recipient: Option<Addr>,
-    pub highest_bid: Option<AuctionBid>
+    pub highest_bid: Option<AuctionBid>,
+    pub bid_gate: Option<BidGate>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -102,8 +129,16 @@ impl Auction {
     }
 }
 
-/// Primary key for asks
-pub type AuctionKey = TokenId;
+/// Primary key for auctions: (collection, token_id)
+pub type AuctionKey = (Addr, TokenId);
+
+/// Convenience auction key constructor
+pub fn auction_key(collection: &Addr, token_id: &str) -> AuctionKey {
+    (collection.clone(), token_id.to_string())
+}
+
+/// The max bid escrowed by the highest bidder of an auction, kept separate from the visible highest_bid
+pub const MAX_BIDS: Map<AuctionKey, AuctionBid> = Map::new("max_bids");
 
 /// Defines indices for accessing Auctions
 pub struct AuctionIndices<'a> {
@@ -112,6 +147,7 @@ pub struct AuctionIndices<'a> {
     pub highest_bid_price: MultiIndex<'a, u128, Auction, AuctionKey>,
     pub seller_end_time: MultiIndex<'a, (String, u64), Auction, AuctionKey>,
     pub highest_bidder_end_time: MultiIndex<'a, (String, u64), Auction, AuctionKey>,
+    pub collection_end_time: MultiIndex<'a, (String, u64), Auction, AuctionKey>,
 }
 
 impl<'a> IndexList<Auction> for AuctionIndices<'a> {
@@ -122,6 +158,7 @@ impl<'a> IndexList<Auction> for AuctionIndices<'a> {
             &self.highest_bid_price,
             &self.seller_end_time,
             &self.highest_bidder_end_time,
+            &self.collection_end_time,
         ];
         Box::new(v.into_iter())
     }
@@ -154,6 +191,11 @@ pub fn auctions<'a>() -> IndexedMap<'a, AuctionKey, Auction, AuctionIndices<'a>>
             "auctions",
             "auctions__highest_bidder_end_time",
         ),
+        collection_end_time: MultiIndex::new(
+            |a: &Auction|  (a.collection.to_string(), a.end_time.seconds()),
+            "auctions",
+            "auctions__collection_end_time",
+        ),
     };
     IndexedMap::new("auctions", indexes)
 }
@@ -2,16 +2,17 @@
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
     coin, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Env,
-    MessageInfo, Order, Reply, ReplyOn, StdError, StdResult, Timestamp, WasmMsg,
+    MessageInfo, Order, Reply, ReplyOn, StdError, StdResult, Storage, Timestamp, WasmMsg,
     Response, SubMsg, Event
 };
-use cw_storage_plus::{Bound};
+use cw_storage_plus::{Bound, Item};
 use cw2::{set_contract_version, get_contract_version};
 use cw721_base::MintMsg;
 use cw_utils::{may_pay, parse_reply_instantiate_data};
 use pg721_metadata_onchain::msg::{
     InstantiateMsg as Pg721InstantiateMsg, ExecuteMsg as Pg721ExecuteMsg, Metadata
 };
+use sha2::{Digest, Sha256};
 
 use crate::error::ContractError;
 use crate::msg::{
@@ -20,11 +21,13 @@ use crate::msg::{
     NumMintedResponse, NumRemainingResponse, MigrateMsg
 };
 use crate::state::{
-    CONFIG, MINTER_ADDRS, CW721_ADDRESS, MINTABLE_TOKEN_IDS,
-    Config, TokenMint, token_mints, 
+    CONFIG, MINTER_ADDRS, CW721_ADDRESS, MINTABLE_NUM_TOKENS, MINTABLE_TOKEN_POSITIONS,
+    MINT_SEED, TOKEN_POSITIONS, Config, TokenMint, token_mints, 
 };
 use whitelist::msg::{
-    ConfigResponse as WhitelistConfigResponse, HasMemberResponse, QueryMsg as WhitelistQueryMsg,
+    ActiveStageResponse, ConfigResponse as WhitelistConfigResponse, HasMemberResponse,
+    ExecuteMsg as WhitelistExecuteMsg, QueryMsg as WhitelistQueryMsg,
+    RemainingAllocationResponse,
 };
 
 // version info for migration info
@@ -88,7 +91,8 @@ pub fn instantiate(
         start_time: msg.start_time,
     };
     CONFIG.save(deps.storage, &config)?;
-    MINTABLE_TOKEN_IDS.save(deps.storage, &vec![])?;
+    MINTABLE_NUM_TOKENS.save(deps.storage, &0)?;
+    MINT_SEED.save(deps.storage, &initial_mint_seed(&env, &info.sender))?;
 
     let response = match msg.cw721_address {
         Some(_addr) => {
@@ -139,7 +143,7 @@ pub fn execute(
 
     match msg {
         ExecuteMsg::UpsertTokenMetadatas { token_metadatas } => execute_upsert_token_metadatas(deps, info, token_metadatas ),
-        ExecuteMsg::Mint {} => execute_mint_sender(deps, env, info),
+        ExecuteMsg::Mint { proof } => execute_mint_sender(deps, env, info, proof),
         ExecuteMsg::UpdateStartTime(time) => execute_update_start_time(deps, env, info, time),
         ExecuteMsg::UpdatePerAddressLimit { per_address_limit } => {
             execute_update_per_address_limit(deps, env, info, per_address_limit)
@@ -194,9 +198,9 @@ pub fn execute_upsert_token_metadatas(
         append_token_ids.push(token_metadata.token_id);
     }
 
-    let mut mintable_token_ids = MINTABLE_TOKEN_IDS.load(deps.storage)?;
-    mintable_token_ids.append(&mut append_token_ids.clone());
-    MINTABLE_TOKEN_IDS.save(deps.storage, &mintable_token_ids)?;
+    for token_id in append_token_ids.iter() {
+        push_mintable_token(deps.storage, *token_id)?;
+    }
 
     let mut response = Response::new();
     let append_token_ids_fmt: Vec<String> = append_token_ids
@@ -278,16 +282,28 @@ pub fn execute_set_whitelist(
     }
 
     if let Some(wl) = config.whitelist {
-        let res: WhitelistConfigResponse = deps
+        let res: ActiveStageResponse = deps
             .querier
-            .query_wasm_smart(wl, &WhitelistQueryMsg::Config {})?;
+            .query_wasm_smart(wl, &WhitelistQueryMsg::ActiveStage {})?;
 
-        if res.is_active {
+        if res.stage.is_some() {
             return Err(ContractError::WhitelistAlreadyStarted {});
         }
     }
 
-    config.whitelist = Some(deps.api.addr_validate(whitelist)?);
+    // Whitelist mints are recorded on the whitelist, which only accepts them
+    // from its registered minters
+    let whitelist_addr = deps.api.addr_validate(whitelist)?;
+    let wl_config: WhitelistConfigResponse = deps
+        .querier
+        .query_wasm_smart(whitelist_addr.clone(), &WhitelistQueryMsg::Config {})?;
+    if !wl_config.minters.contains(&env.contract.address.to_string()) {
+        return Err(ContractError::MinterNotRegistered {
+            whitelist: whitelist.to_string(),
+        });
+    }
+
+    config.whitelist = Some(whitelist_addr);
     CONFIG.save(deps.storage, &config)?;
 
     Ok(Response::default()
@@ -299,28 +315,53 @@ pub fn execute_mint_sender(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    proof: Option<Vec<String>>,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     let action = "mint_sender";
 
     // If there is no active whitelist right now, check public mint
     // Check if after start_time
-    if is_public_mint(deps.as_ref(), &info, &config)? && (env.block.time < config.start_time) {
+    let public_mint = is_public_mint(deps.as_ref(), &info, &config, proof)?;
+    if public_mint && (env.block.time < config.start_time) {
         return Err(ContractError::BeforeMintStartTime {});
     }
 
-    // Check if already minted max per address limit
-    let mint_count = mint_count(deps.as_ref(), &info)?;
-    if mint_count >= config.per_address_limit {
-        return Err(ContractError::MaxPerAddressLimitExceeded {});
+    // Check if already minted max per address limit, whitelist mints are
+    // limited by the member's remaining allocation instead
+    if public_mint {
+        let mint_count = mint_count(deps.as_ref(), &info)?;
+        if mint_count >= config.per_address_limit {
+            return Err(ContractError::MaxPerAddressLimitExceeded {});
+        }
     }
 
-    _execute_mint(deps, env, info, &config, action, false, None, None)
+    let sender = info.sender.to_string();
+    let res = _execute_mint(deps, env, info, &config, action, false, None, None)?;
+    if public_mint {
+        return Ok(res);
+    }
+
+    // Record the mint against the member's whitelist allocation
+    let record_msg = WasmMsg::Execute {
+        contract_addr: config.whitelist.unwrap().to_string(),
+        msg: to_binary(&WhitelistExecuteMsg::RecordMint {
+            member: sender,
+            count: 1,
+        })?,
+        funds: vec![],
+    };
+    Ok(res.add_message(record_msg))
 }
 
 // Check if a whitelist exists and not ended
 // Sender has to be whitelisted to mint
-fn is_public_mint(deps: Deps, info: &MessageInfo, config: &Config) -> Result<bool, ContractError> {
+fn is_public_mint(
+    deps: Deps,
+    info: &MessageInfo,
+    config: &Config,
+    proof: Option<Vec<String>>,
+) -> Result<bool, ContractError> {
     let config = config.clone();
 
     // If there is no whitelist, there's only a public mint
@@ -330,18 +371,21 @@ fn is_public_mint(deps: Deps, info: &MessageInfo, config: &Config) -> Result<boo
 
     let whitelist = config.whitelist.unwrap();
 
-    let wl_config: WhitelistConfigResponse = deps
+    let res: ActiveStageResponse = deps
         .querier
-        .query_wasm_smart(whitelist.clone(), &WhitelistQueryMsg::Config {})?;
+        .query_wasm_smart(whitelist.clone(), &WhitelistQueryMsg::ActiveStage {})?;
 
-    if !wl_config.is_active {
-        return Ok(true);
-    }
+    let stage = match res.stage {
+        Some(stage) => stage,
+        None => return Ok(true),
+    };
 
     let res: HasMemberResponse = deps.querier.query_wasm_smart(
-        whitelist,
+        whitelist.clone(),
         &WhitelistQueryMsg::HasMember {
             member: info.sender.to_string(),
+            proof,
+            stage_id: Some(stage.stage_id),
         },
     )?;
     if !res.has_member {
@@ -350,9 +394,15 @@ fn is_public_mint(deps: Deps, info: &MessageInfo, config: &Config) -> Result<boo
         });
     }
 
-    // Check wl per address limit
-    let mint_count = mint_count(deps, info)?;
-    if mint_count >= wl_config.per_address_limit {
+    // Check wl member allocation, tracked by the whitelist across minters
+    let allocation: RemainingAllocationResponse = deps.querier.query_wasm_smart(
+        whitelist,
+        &WhitelistQueryMsg::RemainingAllocation {
+            member: info.sender.to_string(),
+            stage_id: Some(stage.stage_id),
+        },
+    )?;
+    if allocation.remaining == 0 {
         return Err(ContractError::MaxPerAddressLimitExceeded {});
     }
 
@@ -431,8 +481,8 @@ fn _execute_mint(
         ));
     }
 
-    let mut mintable_token_ids = MINTABLE_TOKEN_IDS.load(deps.storage)?;
-    if mintable_token_ids.is_empty() {
+    let mintable_num_tokens = MINTABLE_NUM_TOKENS.load(deps.storage)?;
+    if mintable_num_tokens == 0 {
         return Err(ContractError::SoldOut {});
     }
 
@@ -441,17 +491,20 @@ fn _execute_mint(
             if token_id == 0 {
                 return Err(ContractError::InvalidTokenId {});
             }
-            match mintable_token_ids.iter().position(|_id| *_id == token_id) {
+            match TOKEN_POSITIONS.may_load(deps.storage, token_id)? {
                 Some(position) => position,
                 None => return Err(ContractError::TokenAlreadyMinted { token_id })
             }
         }
         None => {
-            env.block.time.nanos() as usize % mintable_token_ids.len()
+            let seed = next_mint_seed(deps.storage, &env, &info.sender)?;
+            let mut random_bytes = [0u8; 8];
+            random_bytes.copy_from_slice(&seed[0..8]);
+            (u64::from_be_bytes(random_bytes) % mintable_num_tokens as u64) as u32
         }
     };
 
-    let mintable_token_id = mintable_token_ids[mintable_token_position];
+    let mintable_token_id = MINTABLE_TOKEN_POSITIONS.load(deps.storage, mintable_token_position)?;
     let token_mint = token_mints().load(deps.storage, mintable_token_id)?;
     if token_mint.is_minted {
         return Err(ContractError::TokenAlreadyMinted { token_id: mintable_token_id });
@@ -482,8 +535,11 @@ fn _execute_mint(
     )?;
 
     // Remove mintable token id
-    mintable_token_ids.remove(mintable_token_position);
-    MINTABLE_TOKEN_IDS.save(deps.storage, &mintable_token_ids)?;
+    pop_mintable_token(
+        deps.storage,
+        mintable_token_position,
+        mintable_num_tokens - 1,
+    )?;
 
     // Save the new mint count for the sender's address
     let new_mint_count = mint_count(deps.as_ref(), &info)? + 1;
@@ -498,6 +554,57 @@ fn _execute_mint(
         .add_message(msg))
 }
 
+fn initial_mint_seed(env: &Env, sender: &Addr) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(env.contract.address.as_bytes());
+    hasher.update(sender.as_bytes());
+    hasher.update(env.block.height.to_be_bytes());
+    hasher.update(env.block.time.nanos().to_be_bytes());
+    hasher.finalize().to_vec()
+}
+
+// Roll the stored seed forward with the sender, tx index and block, so the
+// next token id can't be predicted from the block time alone. The seed is
+// readable from storage, so a mint can still be simulated before it is sent.
+// Metadata is written into the token at mint time, which leaves no room for a
+// seed revealed after sell-out to change the outcome.
+fn next_mint_seed(storage: &mut dyn Storage, env: &Env, sender: &Addr) -> StdResult<Vec<u8>> {
+    let tx_index = env.transaction.as_ref().map_or(0, |tx| tx.index);
+    let mut hasher = Sha256::new();
+    hasher.update(MINT_SEED.load(storage)?);
+    hasher.update(sender.as_bytes());
+    hasher.update(tx_index.to_be_bytes());
+    hasher.update(env.block.height.to_be_bytes());
+    hasher.update(env.block.time.nanos().to_be_bytes());
+    let seed = hasher.finalize().to_vec();
+    MINT_SEED.save(storage, &seed)?;
+    Ok(seed)
+}
+
+// Append a token id to the end of the mintable positions, unless it's already there
+fn push_mintable_token(storage: &mut dyn Storage, token_id: u32) -> StdResult<()> {
+    if TOKEN_POSITIONS.has(storage, token_id) {
+        return Ok(());
+    }
+    let position = MINTABLE_NUM_TOKENS.load(storage)?;
+    MINTABLE_TOKEN_POSITIONS.save(storage, position, &token_id)?;
+    TOKEN_POSITIONS.save(storage, token_id, &position)?;
+    MINTABLE_NUM_TOKENS.save(storage, &(position + 1))
+}
+
+// Swap the token id at `position` with the one at `last` and pop it
+fn pop_mintable_token(storage: &mut dyn Storage, position: u32, last: u32) -> StdResult<()> {
+    let token_id = MINTABLE_TOKEN_POSITIONS.load(storage, position)?;
+    if position != last {
+        let last_token_id = MINTABLE_TOKEN_POSITIONS.load(storage, last)?;
+        MINTABLE_TOKEN_POSITIONS.save(storage, position, &last_token_id)?;
+        TOKEN_POSITIONS.save(storage, last_token_id, &position)?;
+    }
+    MINTABLE_TOKEN_POSITIONS.remove(storage, last);
+    TOKEN_POSITIONS.remove(storage, token_id);
+    MINTABLE_NUM_TOKENS.save(storage, &last)
+}
+
 pub fn execute_update_start_time(
     deps: DepsMut,
     env: Env,
@@ -576,7 +683,7 @@ pub fn execute_update_unit_price(
 }
 
 // if admin_no_fee => no fee,
-// else if in whitelist => whitelist price
+// else if in whitelist stage => whitelist stage price
 // else => config unit price
 pub fn mint_price(deps: Deps, config: &Config, is_admin: bool) -> Result<Coin, StdError> {
     let config = config.clone();
@@ -590,14 +697,13 @@ pub fn mint_price(deps: Deps, config: &Config, is_admin: bool) -> Result<Coin, S
 
     let whitelist = config.whitelist.unwrap();
 
-    let wl_config: WhitelistConfigResponse = deps
+    let res: ActiveStageResponse = deps
         .querier
-        .query_wasm_smart(whitelist, &WhitelistQueryMsg::Config {})?;
+        .query_wasm_smart(whitelist, &WhitelistQueryMsg::ActiveStage {})?;
 
-    if wl_config.is_active {
-        Ok(wl_config.unit_price)
-    } else {
-        Ok(config.unit_price)
+    match res.stage {
+        Some(stage) => Ok(stage.unit_price),
+        None => Ok(config.unit_price),
     }
 }
 
@@ -658,14 +764,14 @@ fn query_start_time(deps: Deps) -> StdResult<StartTimeResponse> {
 
 fn query_num_minted(deps: Deps) -> StdResult<NumMintedResponse> {
     let config = CONFIG.load(deps.storage)?;
-    let mintable_token_ids = MINTABLE_TOKEN_IDS.load(deps.storage)?;
-    let num_minted: u32 = config.max_num_tokens - mintable_token_ids.len() as u32;
+    let mintable_num_tokens = MINTABLE_NUM_TOKENS.load(deps.storage)?;
+    let num_minted: u32 = config.max_num_tokens - mintable_num_tokens;
     return Ok(NumMintedResponse { num_minted });
 }
 
 fn query_num_remaining(deps: Deps) -> StdResult<NumRemainingResponse> {
-    let mintable_token_ids = MINTABLE_TOKEN_IDS.load(deps.storage)?;
-    Ok(NumRemainingResponse { num_remaining: mintable_token_ids.len() as u32 })
+    let num_remaining = MINTABLE_NUM_TOKENS.load(deps.storage)?;
+    Ok(NumRemainingResponse { num_remaining })
 }
 
 fn query_mint_price(deps: Deps) -> StdResult<MintPriceResponse> {
@@ -673,10 +779,19 @@ fn query_mint_price(deps: Deps) -> StdResult<MintPriceResponse> {
     let current_price = mint_price(deps, &config, false)?;
     let public_price = config.unit_price;
     let whitelist_price: Option<Coin> = if let Some(whitelist) = config.whitelist {
-        let wl_config: WhitelistConfigResponse = deps
+        // price of the open stage, else the base stage price
+        let res: ActiveStageResponse = deps
             .querier
-            .query_wasm_smart(whitelist, &WhitelistQueryMsg::Config {})?;
-        Some(wl_config.unit_price)
+            .query_wasm_smart(whitelist.clone(), &WhitelistQueryMsg::ActiveStage {})?;
+        match res.stage {
+            Some(stage) => Some(stage.unit_price),
+            None => {
+                let wl_config: WhitelistConfigResponse = deps
+                    .querier
+                    .query_wasm_smart(whitelist, &WhitelistQueryMsg::Config {})?;
+                Some(wl_config.unit_price)
+            }
+        }
     } else {
         None
     };
@@ -742,9 +857,23 @@ pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractE
 }
 
 #[entry_point]
-pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
     let storage_version: &str = &get_contract_version(deps.storage)?.version.to_string();
 
+    // Move the mintable token id list into the swap-and-pop positions
+    const LEGACY_MINTABLE_TOKEN_IDS: Item<Vec<u32>> = Item::new("mintable_token_ids");
+    if let Some(token_ids) = LEGACY_MINTABLE_TOKEN_IDS.may_load(deps.storage)? {
+        MINTABLE_NUM_TOKENS.save(deps.storage, &0)?;
+        for token_id in token_ids {
+            push_mintable_token(deps.storage, token_id)?;
+        }
+        LEGACY_MINTABLE_TOKEN_IDS.remove(deps.storage);
+    }
+    if !MINT_SEED.exists(deps.storage) {
+        let config = CONFIG.load(deps.storage)?;
+        MINT_SEED.save(deps.storage, &initial_mint_seed(&env, &config.admin))?;
+    }
+
     let mut response = Response::new();
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
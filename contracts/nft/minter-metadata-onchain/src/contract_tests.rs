@@ -1,9 +1,11 @@
 use cosmwasm_std::testing::{mock_dependencies_with_balance, mock_env, mock_info};
-use cosmwasm_std::{coin, coins, Addr, Decimal, Empty, Timestamp, Uint128, Attribute};
+use cosmwasm_std::{coin, coins, Addr, Decimal, Empty, Timestamp, Uint128};
 use cosmwasm_std::{Api, Coin};
 use cw721::{Cw721QueryMsg, OwnerOfResponse, NftInfoResponse};
 use cw721_base::ExecuteMsg as Cw721ExecuteMsg;
-use cw_multi_test::{App, AppBuilder, BankSudo, Contract, ContractWrapper, Executor, SudoMsg};
+use cw_multi_test::{
+    App, AppBuilder, AppResponse, BankSudo, Contract, ContractWrapper, Executor, SudoMsg,
+};
 use pg721_metadata_onchain::msg::{
     InstantiateMsg as Pg721InstantiateMsg, RoyaltyInfoResponse, Metadata
 };
@@ -74,7 +76,8 @@ pub fn contract_cw721() -> Box<dyn Contract<Empty>> {
     Box::new(contract)
 }
 
-fn setup_whitelist_contract(router: &mut App, creator: &Addr) -> Addr {
+// Upload contract code, instantiate whitelist contract and register the minter
+fn setup_whitelist_contract(router: &mut App, creator: &Addr, minter_addr: &Addr) -> Addr {
     let whitelist_code_id = router.store_code(contract_whitelist());
 
     let msg = WhitelistInstantiateMsg {
@@ -84,8 +87,9 @@ fn setup_whitelist_contract(router: &mut App, creator: &Addr) -> Addr {
         unit_price: coin(WHITELIST_AMOUNT, NATIVE_DENOM),
         per_address_limit: WL_PER_ADDRESS_LIMIT,
         member_limit: 1000,
+        merkle_root: None,
     };
-    router
+    let whitelist_addr = router
         .instantiate_contract(
             whitelist_code_id,
             creator.clone(),
@@ -94,7 +98,15 @@ fn setup_whitelist_contract(router: &mut App, creator: &Addr) -> Addr {
             "whitelist",
             None,
         )
-        .unwrap()
+        .unwrap();
+
+    // Allow the minter to record whitelist mints
+    let wasm_msg = WhitelistExecuteMsg::UpdateMinters(vec![minter_addr.to_string()]);
+    router
+        .execute_contract(creator.clone(), whitelist_addr.clone(), &wasm_msg, &[])
+        .unwrap();
+
+    whitelist_addr
 }
 
 // Upload contract code and instantiate minter contract
@@ -233,6 +245,16 @@ fn setup_block_time(router: &mut App, nanos: u64) {
     router.set_block(block);
 }
 
+// Token id picked by a mint response
+fn minted_token_id(res: &AppResponse) -> String {
+    res.events
+        .iter()
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == "token_id")
+        .map(|attr| attr.value.clone())
+        .unwrap()
+}
+
 // Deal with zero and non-zero coin amounts for msgs
 fn coins_for_msg(msg_coin: Coin) -> Vec<Coin> {
     if msg_coin.amount > Uint128::zero() {
@@ -373,7 +395,7 @@ fn happy_path() {
     setup_block_time(&mut router, START_TIME + 1);
 
     // Fail with incorrect tokens
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let err = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -383,7 +405,7 @@ fn happy_path() {
     assert!(err.is_err());
 
     // Succeeds if funds are sent
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -391,6 +413,7 @@ fn happy_path() {
         &coins(UNIT_PRICE, NATIVE_DENOM),
     );
     assert!(res.is_ok());
+    let token_id = minted_token_id(&res.unwrap());
 
     // Balances are correct
     // The creator should get the unit price - mint fee for the mint above
@@ -417,7 +440,7 @@ fn happy_path() {
 
     // Check NFT is transferred
     let query_owner_msg = Cw721QueryMsg::OwnerOf {
-        token_id: String::from("2"),
+        token_id,
         include_expired: None,
     };
     let res: OwnerOfResponse = router
@@ -452,6 +475,7 @@ fn happy_path() {
         }),
     );
     assert!(res.is_ok());
+    let token_id = minted_token_id(&res.unwrap());
 
     // Mint count is not increased if admin mints for the user
     let res: MintCountResponse = router
@@ -476,7 +500,7 @@ fn happy_path() {
 
     // Check that NFT is transferred
     let query_owner_msg = Cw721QueryMsg::OwnerOf {
-        token_id: String::from("1"),
+        token_id,
         include_expired: None,
     };
     let res: OwnerOfResponse = router
@@ -486,7 +510,7 @@ fn happy_path() {
     assert_eq!(res.owner, buyer.to_string());
 
     // Errors if sold out
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer,
         minter_addr.clone(),
@@ -520,7 +544,7 @@ fn mint_count_query() {
     upsert_metadata(&mut router, &creator, &minter_addr, num_tokens, None);
 
     let cw721_addr = Addr::unchecked(config.cw721_address);
-    let whitelist_addr = setup_whitelist_contract(&mut router, &creator);
+    let whitelist_addr = setup_whitelist_contract(&mut router, &creator, &minter_addr);
     const EXPIRATION_TIME: Timestamp = Timestamp::from_nanos(START_TIME + 10_000);
 
     // Set block to before genesis mint start time
@@ -561,6 +585,9 @@ fn mint_count_query() {
     // Add buyer to whitelist
     let inner_msg = AddMembersMsg {
         to_add: vec![buyer.to_string()],
+        allocations: None,
+        stage_id: None,
+        skip_invalid: None,
     };
     let wasm_msg = WhitelistExecuteMsg::AddMembers(inner_msg);
     let res = router.execute_contract(creator.clone(), whitelist_addr, &wasm_msg, &[]);
@@ -569,7 +596,7 @@ fn mint_count_query() {
     setup_block_time(&mut router, START_TIME);
 
     // Mint succeeds
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -577,6 +604,7 @@ fn mint_count_query() {
         &coins(WHITELIST_AMOUNT, NATIVE_DENOM),
     );
     assert!(res.is_ok());
+    let token_id = minted_token_id(&res.unwrap());
 
     // Query count
     let res: MintCountResponse = router
@@ -592,7 +620,7 @@ fn mint_count_query() {
     assert_eq!(res.address, buyer.to_string());
 
     // Mint fails, over whitelist per address limit
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let err = router
         .execute_contract(
             buyer.clone(),
@@ -610,7 +638,7 @@ fn mint_count_query() {
     setup_block_time(&mut router, START_TIME + 20_000);
 
     // Public mint succeeds
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -635,7 +663,7 @@ fn mint_count_query() {
     // Buyer transfers NFT to creator
     let transfer_msg: Cw721ExecuteMsg<Empty> = Cw721ExecuteMsg::TransferNft {
         recipient: creator.to_string(),
-        token_id: "1".to_string(),
+        token_id,
     };
     let res = router.execute_contract(
         buyer.clone(),
@@ -646,7 +674,7 @@ fn mint_count_query() {
     assert!(res.is_ok());
 
     // Mint succeeds
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -669,7 +697,7 @@ fn mint_count_query() {
     assert_eq!(res.address, buyer.to_string());
 
     // Mint fails
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let err = router
         .execute_contract(
             buyer.clone(),
@@ -704,7 +732,7 @@ fn whitelist_already_started() {
     let num_tokens = 1;
     let (minter_addr, _) = setup_minter_contract(&mut router, &creator, num_tokens);
     upsert_metadata(&mut router, &creator, &minter_addr, num_tokens, None);
-    let whitelist_addr = setup_whitelist_contract(&mut router, &creator);
+    let whitelist_addr = setup_whitelist_contract(&mut router, &creator, &minter_addr);
 
     setup_block_time(&mut router, START_TIME + 101);
 
@@ -729,7 +757,7 @@ fn whitelist_can_update_before_start() {
     let num_tokens = 1;
     let (minter_addr, _) = setup_minter_contract(&mut router, &creator, num_tokens);
     upsert_metadata(&mut router, &creator, &minter_addr, num_tokens, None);
-    let whitelist_addr = setup_whitelist_contract(&mut router, &creator);
+    let whitelist_addr = setup_whitelist_contract(&mut router, &creator, &minter_addr);
 
     setup_block_time(&mut router, START_TIME - 1000);
 
@@ -757,6 +785,37 @@ fn whitelist_can_update_before_start() {
         .unwrap();
 }
 
+#[test]
+fn whitelist_requires_registered_minter() {
+    let mut router = custom_mock_app();
+    let (creator, _) = setup_accounts(&mut router);
+    let num_tokens = 1;
+    let (minter_addr, _) = setup_minter_contract(&mut router, &creator, num_tokens);
+    let whitelist_addr = setup_whitelist_contract(&mut router, &creator, &minter_addr);
+
+    setup_block_time(&mut router, START_TIME - 1000);
+
+    // Remove the minter from the whitelist minters
+    let wasm_msg = WhitelistExecuteMsg::UpdateMinters(vec![]);
+    let res = router.execute_contract(creator.clone(), whitelist_addr.clone(), &wasm_msg, &[]);
+    assert!(res.is_ok());
+
+    // set whitelist in minter contract fails
+    let set_whitelist_msg = ExecuteMsg::SetWhitelist {
+        whitelist: whitelist_addr.to_string(),
+    };
+    let err = router
+        .execute_contract(creator, minter_addr, &set_whitelist_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        ContractError::MinterNotRegistered {
+            whitelist: whitelist_addr.to_string()
+        }
+        .to_string()
+    );
+}
+
 #[test]
 fn whitelist_access_len_add_remove_expiration() {
     let mut router = custom_mock_app();
@@ -766,7 +825,7 @@ fn whitelist_access_len_add_remove_expiration() {
     upsert_metadata(&mut router, &creator, &minter_addr, num_tokens, None);
     
     let cw721_addr = config.cw721_address;
-    let whitelist_addr = setup_whitelist_contract(&mut router, &creator);
+    let whitelist_addr = setup_whitelist_contract(&mut router, &creator, &minter_addr);
     const AFTER_GENESIS_TIME: Timestamp = Timestamp::from_nanos(START_TIME + 100);
 
     // Set to just before genesis mint start time
@@ -800,7 +859,7 @@ fn whitelist_access_len_add_remove_expiration() {
     assert!(res.is_ok());
 
     // Mint fails, buyer is not on whitelist
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -812,13 +871,16 @@ fn whitelist_access_len_add_remove_expiration() {
     // Add buyer to whitelist
     let inner_msg = AddMembersMsg {
         to_add: vec![buyer.to_string()],
+        allocations: None,
+        stage_id: None,
+        skip_invalid: None,
     };
     let wasm_msg = WhitelistExecuteMsg::AddMembers(inner_msg);
     let res = router.execute_contract(creator.clone(), whitelist_addr.clone(), &wasm_msg, &[]);
     assert!(res.is_ok());
 
     // Mint fails, not whitelist price
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     router
         .execute_contract(
             buyer.clone(),
@@ -849,7 +911,7 @@ fn whitelist_access_len_add_remove_expiration() {
     );
 
     // Mint succeeds with whitelist price
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -859,7 +921,7 @@ fn whitelist_access_len_add_remove_expiration() {
     assert!(res.is_ok());
 
     // Mint fails, over whitelist per address limit
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let err = router
         .execute_contract(
             buyer.clone(),
@@ -887,7 +949,7 @@ fn whitelist_access_len_add_remove_expiration() {
     assert!(res.is_ok());
 
     // Mint fails, buyer exceeded per address limit
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let err = router
         .execute_contract(
             buyer.clone(),
@@ -902,13 +964,18 @@ fn whitelist_access_len_add_remove_expiration() {
     );
 
     // Remove buyer from whitelist
-    let inner_msg = AddMembersMsg { to_add: vec![] };
+    let inner_msg = AddMembersMsg {
+        to_add: vec![],
+        allocations: None,
+        stage_id: None,
+        skip_invalid: None,
+    };
     let wasm_msg = WhitelistExecuteMsg::AddMembers(inner_msg);
     let res = router.execute_contract(creator.clone(), whitelist_addr, &wasm_msg, &[]);
     assert!(res.is_ok());
 
     // Mint fails
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer,
         minter_addr,
@@ -940,7 +1007,7 @@ fn before_start_time() {
     assert!(res.is_err());
 
     // Buyer can't mint before start_time
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -963,7 +1030,7 @@ fn before_start_time() {
     setup_block_time(&mut router, START_TIME + 10_000_000);
 
     // Mint succeeds
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer,
         minter_addr,
@@ -1021,7 +1088,7 @@ fn check_per_address_limit() {
     assert!(res.is_ok());
 
     // First mint succeeds
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -1032,7 +1099,7 @@ fn check_per_address_limit() {
     assert!(res.is_ok());
 
     // Second mint fails from exceeding per address limit
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer,
         minter_addr,
@@ -1042,6 +1109,91 @@ fn check_per_address_limit() {
     assert!(res.is_err());
 }
 
+#[test]
+fn whitelist_allocation_over_per_address_limit() {
+    let mut router = custom_mock_app();
+    let (creator, buyer) = setup_accounts(&mut router);
+    let num_tokens = 5;
+    let (minter_addr, _config) = setup_minter_contract(&mut router, &creator, num_tokens);
+    upsert_metadata(&mut router, &creator, &minter_addr, num_tokens, None);
+    let whitelist_addr = setup_whitelist_contract(&mut router, &creator, &minter_addr);
+
+    setup_block_time(&mut router, START_TIME - 10);
+
+    let per_address_limit_msg = ExecuteMsg::UpdatePerAddressLimit {
+        per_address_limit: 1,
+    };
+    let res = router.execute_contract(
+        creator.clone(),
+        minter_addr.clone(),
+        &per_address_limit_msg,
+        &[],
+    );
+    assert!(res.is_ok());
+
+    let set_whitelist_msg = ExecuteMsg::SetWhitelist {
+        whitelist: whitelist_addr.to_string(),
+    };
+    let res = router.execute_contract(
+        creator.clone(),
+        minter_addr.clone(),
+        &set_whitelist_msg,
+        &[],
+    );
+    assert!(res.is_ok());
+
+    // Buyer's allocation is larger than the public per address limit
+    let inner_msg = AddMembersMsg {
+        to_add: vec![],
+        allocations: Some(vec![(buyer.to_string(), 3)]),
+        stage_id: None,
+        skip_invalid: None,
+    };
+    let wasm_msg = WhitelistExecuteMsg::AddMembers(inner_msg);
+    let res = router.execute_contract(creator.clone(), whitelist_addr, &wasm_msg, &[]);
+    assert!(res.is_ok());
+
+    // Whitelist mints are only limited by the allocation
+    setup_block_time(&mut router, START_TIME + 100);
+    let mint_msg = ExecuteMsg::Mint { proof: None };
+    for _ in 0..3 {
+        let res = router.execute_contract(
+            buyer.clone(),
+            minter_addr.clone(),
+            &mint_msg,
+            &coins(WHITELIST_AMOUNT, NATIVE_DENOM),
+        );
+        assert!(res.is_ok());
+    }
+    let err = router
+        .execute_contract(
+            buyer.clone(),
+            minter_addr.clone(),
+            &mint_msg,
+            &coins(WHITELIST_AMOUNT, NATIVE_DENOM),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        ContractError::MaxPerAddressLimitExceeded {}.to_string()
+    );
+
+    // Public mints count the whitelist mints against the per address limit
+    setup_block_time(&mut router, START_TIME + 10000000);
+    let err = router
+        .execute_contract(
+            buyer,
+            minter_addr,
+            &mint_msg,
+            &coins(UNIT_PRICE, NATIVE_DENOM),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        ContractError::MaxPerAddressLimitExceeded {}.to_string()
+    );
+}
+
 #[test]
 fn mint_for_token_id_addr() {
     let mut router = custom_mock_app();
@@ -1075,9 +1227,9 @@ fn mint_for_token_id_addr() {
     );
 
     // Test token id already sold
-    // 1. mint token_id 1
-    // 2. mint_for token_id 1
-    let mint_msg = ExecuteMsg::Mint {};
+    // 1. mint a random token_id
+    // 2. mint_for the same token_id
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -1085,6 +1237,7 @@ fn mint_for_token_id_addr() {
         &coins(UNIT_PRICE, NATIVE_DENOM),
     );
     assert!(res.is_ok());
+    let sold_token_id: u32 = minted_token_id(&res.unwrap()).parse().unwrap();
 
     // Minter contract should have a balance
     let minter_balance = router
@@ -1117,7 +1270,7 @@ fn mint_for_token_id_addr() {
     );
 
     // Mint fails, token_id already sold
-    let token_id = 1;
+    let token_id = sold_token_id;
     let mint_for_msg = ExecuteMsg::MintFor {
         token_id,
         recipient: buyer.to_string(),
@@ -1150,8 +1303,8 @@ fn mint_for_token_id_addr() {
         .unwrap();
     assert_eq!(num_remaining_response, NumRemainingResponse { num_remaining: 3 });
 
-    // Test mint_for token_id 2 then normal mint
-    let token_id = 2;
+    // Test mint_for an unsold token_id then normal mint
+    let token_id = if sold_token_id == 2 { 3 } else { 2 };
     let mint_for_msg = ExecuteMsg::MintFor {
         token_id,
         recipient: buyer.to_string(),
@@ -1410,7 +1563,7 @@ fn unhappy_path() {
     upsert_metadata(&mut router, &creator, &minter_addr, num_tokens, None);
 
     // Fails if too little funds are sent
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -1420,7 +1573,7 @@ fn unhappy_path() {
     assert!(res.is_err());
 
     // Fails if too many funds are sent
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -1430,7 +1583,7 @@ fn unhappy_path() {
     assert!(res.is_err());
 
     // Fails wrong denom is sent
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(buyer, minter_addr, &mint_msg, &coins(UNIT_PRICE, "uatom"));
     assert!(res.is_err());
 }
@@ -1458,7 +1611,7 @@ fn can_withdraw() {
         .unwrap_err();
 
     // do a mint
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer,
         minter_addr.clone(),
@@ -1513,7 +1666,7 @@ fn metadata_test() {
     
     // Fails with missing metadata
     upsert_metadata(&mut router, &creator, &minter_addr, 2, None);
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -1524,7 +1677,7 @@ fn metadata_test() {
 
     // Succeeds with enough metadata
     upsert_metadata(&mut router, &creator, &minter_addr, 2, Some(3));
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -1532,20 +1685,17 @@ fn metadata_test() {
         &coins(UNIT_PRICE, NATIVE_DENOM),
     );
     assert!(res.is_ok());
-    assert_eq!(res.unwrap().events[1].attributes[4], Attribute {
-        key: String::from("token_id"),
-        value: String::from("3")
-    });
+    let token_id: u32 = minted_token_id(&res.unwrap()).parse().unwrap();
 
     // Check NFT is transferred
     let query_info = Cw721QueryMsg::NftInfo {
-        token_id: String::from("3"),
+        token_id: token_id.to_string(),
     };
     let res: NftInfoResponse<Metadata> = router
         .wrap()
         .query_wasm_smart(config.cw721_address.clone(), &query_info)
         .unwrap();
-    assert_eq!(res.extension.image, Some(String::from("image-3.png")));
+    assert_eq!(res.extension.image, Some(format!("image-{}.png", token_id)));
 
     // Check minter TokenMints
     let query_info = QueryMsg::TokenMints {
@@ -1558,9 +1708,21 @@ fn metadata_test() {
         .wrap()
         .query_wasm_smart(minter_addr, &query_info)
         .unwrap();
-    assert_eq!(res.token_mints.len(), 1);
-    assert_eq!(res.token_mints[0].token_id, 4);
-    assert_eq!(res.token_mints[0].is_minted, false);
+    let unminted_token_ids: Vec<u32> = vec![4, 3]
+        .into_iter()
+        .filter(|id| *id != token_id)
+        .collect();
+    assert_eq!(
+        res.token_mints
+            .iter()
+            .map(|token_mint| token_mint.token_id)
+            .collect::<Vec<u32>>(),
+        unminted_token_ids
+    );
+    assert!(res
+        .token_mints
+        .iter()
+        .all(|token_mint| !token_mint.is_minted));
 }
 
 #[test]
@@ -1592,4 +1754,58 @@ fn update_admin() {
         .query_wasm_smart(minter_addr.clone(), &QueryMsg::Config {})
         .unwrap();
     assert_eq!(config.admin, buyer.to_string());
-}
\ No newline at end of file
+}
+#[test]
+fn shuffled_mint() {
+    let mut router = custom_mock_app();
+    let (creator, buyer) = setup_accounts(&mut router);
+    let num_tokens = 5;
+    let (minter_addr, _config) = setup_minter_contract(&mut router, &creator, num_tokens);
+
+    // Upserting metadata again doesn't add the token ids twice
+    upsert_metadata(&mut router, &creator, &minter_addr, num_tokens, None);
+    upsert_metadata(&mut router, &creator, &minter_addr, num_tokens, None);
+    let res: NumRemainingResponse = router
+        .wrap()
+        .query_wasm_smart(minter_addr.clone(), &QueryMsg::NumRemaining {})
+        .unwrap();
+    assert_eq!(
+        res,
+        NumRemainingResponse {
+            num_remaining: num_tokens
+        }
+    );
+
+    setup_block_time(&mut router, START_TIME + 1);
+
+    // Every token id is minted exactly once
+    let mint_msg = ExecuteMsg::Mint { proof: None };
+    let mut token_ids: Vec<u32> = (0..num_tokens)
+        .map(|_| {
+            let res = router
+                .execute_contract(
+                    buyer.clone(),
+                    minter_addr.clone(),
+                    &mint_msg,
+                    &coins(UNIT_PRICE, NATIVE_DENOM),
+                )
+                .unwrap();
+            minted_token_id(&res).parse().unwrap()
+        })
+        .collect();
+    token_ids.sort_unstable();
+    assert_eq!(token_ids, vec![1, 2, 3, 4, 5]);
+
+    let err = router
+        .execute_contract(
+            buyer,
+            minter_addr.clone(),
+            &mint_msg,
+            &coins(UNIT_PRICE, NATIVE_DENOM),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        ContractError::SoldOut {}.to_string()
+    );
+}
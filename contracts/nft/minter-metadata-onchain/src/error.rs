@@ -46,6 +46,9 @@ pub enum ContractError {
     #[error("WhitelistAlreadyStarted")]
     WhitelistAlreadyStarted {},
 
+    #[error("Minter is not registered with whitelist {whitelist}")]
+    MinterNotRegistered { whitelist: String },
+
     #[error("InvalidStartTime {0} < {1}")]
     InvalidStartTime(Timestamp, Timestamp),
 
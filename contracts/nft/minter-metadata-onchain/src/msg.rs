@@ -21,8 +21,13 @@ pub struct InstantiateMsg {
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
     UpsertTokenMetadatas { token_metadatas: Vec<TokenMetadata> },
-    Mint {},
+    Mint {
+        /// Merkle proof of whitelist membership, when the whitelist uses a Merkle root
+        proof: Option<Vec<String>>,
+    },
     SetAdmin { admin: String },
+    /// The whitelist must already list this contract in its minters, as
+    /// whitelist mints are recorded there
     SetWhitelist { whitelist: String },
     UpdateStartTime(Timestamp),
     UpdatePerAddressLimit { per_address_limit: u32 },
@@ -19,7 +19,13 @@ pub struct Config {
 pub const CONFIG: Item<Config> = Item::new("config");
 pub const CW721_ADDRESS: Item<Addr> = Item::new("cw721_address");
 pub const MINTER_ADDRS: Map<Addr, u32> = Map::new("minter_address");
-pub const MINTABLE_TOKEN_IDS: Item<Vec<u32>> = Item::new("mintable_token_ids");
+/// Unminted token ids packed into positions 0..MINTABLE_NUM_TOKENS, removed by swap-and-pop
+pub const MINTABLE_TOKEN_POSITIONS: Map<u32, u32> = Map::new("mintable_token_positions");
+/// Reverse lookup of MINTABLE_TOKEN_POSITIONS, token id -> position
+pub const TOKEN_POSITIONS: Map<u32, u32> = Map::new("token_positions");
+pub const MINTABLE_NUM_TOKENS: Item<u32> = Item::new("mintable_num_tokens");
+/// Seed for token id selection, rolled forward on every mint
+pub const MINT_SEED: Item<Vec<u8>> = Item::new("mint_seed");
 
 pub type TokenId = u32;
 
@@ -2,24 +2,28 @@
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
     coin, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Empty, Env,
-    MessageInfo, Order, Reply, ReplyOn, StdError, StdResult, Timestamp, WasmMsg, Response, SubMsg
+    MessageInfo, Reply, ReplyOn, StdError, StdResult, Storage, Timestamp, WasmMsg, Response, SubMsg
 };
 use cw2::set_contract_version;
 use cw721_base::{msg::ExecuteMsg as Cw721ExecuteMsg, MintMsg};
 use cw_utils::{may_pay, parse_reply_instantiate_data};
 use pg721::msg::InstantiateMsg as Pg721InstantiateMsg;
+use sha2::{Digest, Sha256};
 use url::Url;
 
 use crate::error::ContractError;
 use crate::msg::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, MintCountResponse, MintPriceResponse,
-    MintableNumTokensResponse, QueryMsg, StartTimeResponse,
+    ConfigResponse, ExecuteMsg, InstantiateMsg, MetadataIdResponse, MintCountResponse,
+    MintPriceResponse, MintableNumTokensResponse, QueryMsg, StartTimeResponse,
 };
 use crate::state::{
-    Config, CONFIG, MINTABLE_NUM_TOKENS, MINTABLE_TOKEN_IDS, MINTER_ADDRS, CW721_ADDRESS,
+    Config, CONFIG, METADATA_OFFSET, MINTABLE_NUM_TOKENS, MINTABLE_TOKEN_POSITIONS, MINTER_ADDRS,
+    MINT_SEED, REVEAL_TIME, SEED_COMMITMENT, TOKEN_POSITIONS, CW721_ADDRESS,
 };
 use whitelist::msg::{
-    ConfigResponse as WhitelistConfigResponse, HasMemberResponse, QueryMsg as WhitelistQueryMsg,
+    ActiveStageResponse, ConfigResponse as WhitelistConfigResponse, HasMemberResponse,
+    ExecuteMsg as WhitelistExecuteMsg, QueryMsg as WhitelistQueryMsg,
+    RemainingAllocationResponse,
 };
 
 // version info for migration info
@@ -80,12 +84,16 @@ pub fn instantiate(
         start_time: msg.start_time,
     };
     CONFIG.save(deps.storage, &config)?;
+    // Token ids 1..=num_tokens are implicit until a mint swaps them around
     MINTABLE_NUM_TOKENS.save(deps.storage, &msg.num_tokens)?;
 
-    // Save mintable token ids map
-    for token_id in 1..=msg.num_tokens {
-        MINTABLE_TOKEN_IDS.save(deps.storage, token_id, &true)?;
-    }
+    // Initial token id selection seed, rolled forward on every mint
+    let mut hasher = Sha256::new();
+    hasher.update(env.contract.address.as_bytes());
+    hasher.update(info.sender.as_bytes());
+    hasher.update(env.block.height.to_be_bytes());
+    hasher.update(env.block.time.nanos().to_be_bytes());
+    MINT_SEED.save(deps.storage, &hasher.finalize().to_vec())?;
 
     // Submessage to instantiate cw721 contract
     let sub_msgs: Vec<SubMsg> = vec![SubMsg {
@@ -123,7 +131,7 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::Mint {} => execute_mint_sender(deps, env, info),
+        ExecuteMsg::Mint { proof } => execute_mint_sender(deps, env, info, proof),
         ExecuteMsg::UpdateStartTime(time) => execute_update_start_time(deps, env, info, time),
         ExecuteMsg::UpdatePerAddressLimit { per_address_limit } => {
             execute_update_per_address_limit(deps, env, info, per_address_limit)
@@ -137,6 +145,11 @@ pub fn execute(
             execute_set_whitelist(deps, env, info, &whitelist)
         }
         ExecuteMsg::Withdraw {} => execute_withdraw(deps, env, info),
+        ExecuteMsg::CommitSeed {
+            commitment,
+            reveal_time,
+        } => execute_commit_seed(deps, env, info, commitment, reveal_time),
+        ExecuteMsg::RevealSeed { seed } => execute_reveal_seed(deps, env, info, seed),
     }
 }
 
@@ -171,6 +184,102 @@ pub fn execute_withdraw(
         .add_message(send_msg))
 }
 
+pub fn execute_commit_seed(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    commitment: String,
+    reveal_time: Option<Timestamp>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.admin != info.sender {
+        return Err(ContractError::Unauthorized(
+            "Sender is not an admin".to_owned(),
+        ));
+    };
+
+    if env.block.time >= config.start_time {
+        return Err(ContractError::AlreadyStarted {});
+    }
+
+    // Stored lowercase to match the hex encoded hash of the revealed seed
+    let commitment = match hex::decode(&commitment) {
+        Ok(bytes) if bytes.len() == 32 => hex::encode(bytes),
+        _ => return Err(ContractError::InvalidSeedCommitment {}),
+    };
+    SEED_COMMITMENT.save(deps.storage, &commitment)?;
+
+    let mut res = Response::default()
+        .add_attribute("action", "commit_seed")
+        .add_attribute("commitment", commitment);
+    match reveal_time {
+        Some(reveal_time) => {
+            if reveal_time <= config.start_time {
+                return Err(ContractError::InvalidRevealTime(
+                    reveal_time,
+                    config.start_time,
+                ));
+            }
+            REVEAL_TIME.save(deps.storage, &reveal_time)?;
+            res = res.add_attribute("reveal_time", reveal_time.to_string());
+        }
+        None => REVEAL_TIME.remove(deps.storage),
+    }
+
+    Ok(res)
+}
+
+pub fn execute_reveal_seed(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    seed: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.admin != info.sender {
+        return Err(ContractError::Unauthorized(
+            "Sender is not an admin".to_owned(),
+        ));
+    };
+
+    let sold_out = MINTABLE_NUM_TOKENS.load(deps.storage)? == 0;
+    let past_reveal_time = REVEAL_TIME
+        .may_load(deps.storage)?
+        .map_or(false, |reveal_time| env.block.time >= reveal_time);
+    if !sold_out && !past_reveal_time {
+        return Err(ContractError::RevealTooEarly {});
+    }
+
+    let commitment = SEED_COMMITMENT
+        .may_load(deps.storage)?
+        .ok_or(ContractError::InvalidSeed {})?;
+    if hex::encode(Sha256::digest(seed.as_bytes())) != commitment {
+        return Err(ContractError::InvalidSeed {});
+    }
+    SEED_COMMITMENT.remove(deps.storage);
+
+    let offset = metadata_offset(&seed, &MINT_SEED.load(deps.storage)?, config.num_tokens);
+    METADATA_OFFSET.save(deps.storage, &offset)?;
+
+    Ok(Response::default()
+        .add_attribute("action", "reveal_seed")
+        .add_attribute("commitment", commitment)
+        .add_attribute("seed", seed)
+        .add_attribute("metadata_offset", offset.to_string()))
+}
+
+// Hash the revealed seed with the final mint seed, which the admin couldn't
+// know when committing, so the offset can't be picked by choosing the seed
+pub(crate) fn metadata_offset(seed: &str, mint_seed: &[u8], num_tokens: u32) -> u32 {
+    let mut hasher = Sha256::new();
+    hasher.update(seed.as_bytes());
+    hasher.update(mint_seed);
+    let hash = hasher.finalize();
+    let mut random_bytes = [0u8; 8];
+    random_bytes.copy_from_slice(&hash[0..8]);
+    (u64::from_be_bytes(random_bytes) % num_tokens as u64) as u32
+}
+
 pub fn execute_set_whitelist(
     deps: DepsMut,
     env: Env,
@@ -189,16 +298,28 @@ pub fn execute_set_whitelist(
     }
 
     if let Some(wl) = config.whitelist {
-        let res: WhitelistConfigResponse = deps
+        let res: ActiveStageResponse = deps
             .querier
-            .query_wasm_smart(wl, &WhitelistQueryMsg::Config {})?;
+            .query_wasm_smart(wl, &WhitelistQueryMsg::ActiveStage {})?;
 
-        if res.is_active {
+        if res.stage.is_some() {
             return Err(ContractError::WhitelistAlreadyStarted {});
         }
     }
 
-    config.whitelist = Some(deps.api.addr_validate(whitelist)?);
+    // Whitelist mints are recorded on the whitelist, which only accepts them
+    // from its registered minters
+    let whitelist_addr = deps.api.addr_validate(whitelist)?;
+    let wl_config: WhitelistConfigResponse = deps
+        .querier
+        .query_wasm_smart(whitelist_addr.clone(), &WhitelistQueryMsg::Config {})?;
+    if !wl_config.minters.contains(&env.contract.address.to_string()) {
+        return Err(ContractError::MinterNotRegistered {
+            whitelist: whitelist.to_string(),
+        });
+    }
+
+    config.whitelist = Some(whitelist_addr);
     CONFIG.save(deps.storage, &config)?;
 
     Ok(Response::default()
@@ -210,28 +331,52 @@ pub fn execute_mint_sender(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    proof: Option<Vec<String>>,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     let action = "mint_sender";
 
     // If there is no active whitelist right now, check public mint
     // Check if after start_time
-    if is_public_mint(deps.as_ref(), &info)? && (env.block.time < config.start_time) {
+    let public_mint = is_public_mint(deps.as_ref(), &info, proof)?;
+    if public_mint && (env.block.time < config.start_time) {
         return Err(ContractError::BeforeMintStartTime {});
     }
 
-    // Check if already minted max per address limit
-    let mint_count = mint_count(deps.as_ref(), &info)?;
-    if mint_count >= config.per_address_limit {
-        return Err(ContractError::MaxPerAddressLimitExceeded {});
+    // Check if already minted max per address limit, whitelist mints are
+    // limited by the member's remaining allocation instead
+    if public_mint {
+        let mint_count = mint_count(deps.as_ref(), &info)?;
+        if mint_count >= config.per_address_limit {
+            return Err(ContractError::MaxPerAddressLimitExceeded {});
+        }
+    }
+
+    let sender = info.sender.to_string();
+    let res = _execute_mint(deps, env, info, action, false, None, None)?;
+    if public_mint {
+        return Ok(res);
     }
 
-    _execute_mint(deps, env, info, action, false, None, None)
+    // Record the mint against the member's whitelist allocation
+    let record_msg = WasmMsg::Execute {
+        contract_addr: config.whitelist.unwrap().to_string(),
+        msg: to_binary(&WhitelistExecuteMsg::RecordMint {
+            member: sender,
+            count: 1,
+        })?,
+        funds: vec![],
+    };
+    Ok(res.add_message(record_msg))
 }
 
 // Check if a whitelist exists and not ended
 // Sender has to be whitelisted to mint
-fn is_public_mint(deps: Deps, info: &MessageInfo) -> Result<bool, ContractError> {
+fn is_public_mint(
+    deps: Deps,
+    info: &MessageInfo,
+    proof: Option<Vec<String>>,
+) -> Result<bool, ContractError> {
     let config = CONFIG.load(deps.storage)?;
 
     // If there is no whitelist, there's only a public mint
@@ -241,18 +386,21 @@ fn is_public_mint(deps: Deps, info: &MessageInfo) -> Result<bool, ContractError>
 
     let whitelist = config.whitelist.unwrap();
 
-    let wl_config: WhitelistConfigResponse = deps
+    let res: ActiveStageResponse = deps
         .querier
-        .query_wasm_smart(whitelist.clone(), &WhitelistQueryMsg::Config {})?;
+        .query_wasm_smart(whitelist.clone(), &WhitelistQueryMsg::ActiveStage {})?;
 
-    if !wl_config.is_active {
-        return Ok(true);
-    }
+    let stage = match res.stage {
+        Some(stage) => stage,
+        None => return Ok(true),
+    };
 
     let res: HasMemberResponse = deps.querier.query_wasm_smart(
-        whitelist,
+        whitelist.clone(),
         &WhitelistQueryMsg::HasMember {
             member: info.sender.to_string(),
+            proof,
+            stage_id: Some(stage.stage_id),
         },
     )?;
     if !res.has_member {
@@ -261,9 +409,15 @@ fn is_public_mint(deps: Deps, info: &MessageInfo) -> Result<bool, ContractError>
         });
     }
 
-    // Check wl per address limit
-    let mint_count = mint_count(deps, info)?;
-    if mint_count >= wl_config.per_address_limit {
+    // Check wl member allocation, tracked by the whitelist across minters
+    let allocation: RemainingAllocationResponse = deps.querier.query_wasm_smart(
+        whitelist,
+        &WhitelistQueryMsg::RemainingAllocation {
+            member: info.sender.to_string(),
+            stage_id: Some(stage.stage_id),
+        },
+    )?;
+    if allocation.remaining == 0 {
         return Err(ContractError::MaxPerAddressLimitExceeded {});
     }
 
@@ -343,30 +497,29 @@ fn _execute_mint(
     }
 
     let mintable_num_tokens = MINTABLE_NUM_TOKENS.load(deps.storage)?;
+    if mintable_num_tokens == 0 {
+        return Err(ContractError::SoldOut {});
+    }
 
-    let mintable_token_id = match token_id {
+    let position = match token_id {
         Some(token_id) => {
             if token_id == 0 || token_id > config.num_tokens {
                 return Err(ContractError::InvalidTokenId {});
             }
             // If token_id not on mintable map, throw err
-            if !MINTABLE_TOKEN_IDS.has(deps.storage, token_id) {
-                return Err(ContractError::TokenIdAlreadySold { token_id });
+            match mintable_position(deps.storage, token_id, mintable_num_tokens)? {
+                Some(position) => position,
+                None => return Err(ContractError::TokenIdAlreadySold { token_id }),
             }
-            token_id
         }
         None => {
-            let mintable_tokens_result: StdResult<Vec<u32>> = MINTABLE_TOKEN_IDS
-                .keys(deps.storage, None, None, Order::Ascending)
-                .collect();
-            let mintable_tokens = mintable_tokens_result?;
-            if mintable_tokens.is_empty() {
-                return Err(ContractError::SoldOut {});
-            }
-            let random_index = env.block.time.nanos() % mintable_num_tokens as u64;
-            mintable_tokens[random_index as usize]
+            let seed = next_mint_seed(deps.storage, &env, &info.sender)?;
+            let mut random_bytes = [0u8; 8];
+            random_bytes.copy_from_slice(&seed[0..8]);
+            (u64::from_be_bytes(random_bytes) % mintable_num_tokens as u64) as u32
         }
     };
+    let mintable_token_id = pop_mintable_token(deps.storage, position, mintable_num_tokens - 1)?;
 
     // Create mint msgs
     let mint_msg = Cw721ExecuteMsg::Mint(MintMsg::<Empty> {
@@ -381,8 +534,6 @@ fn _execute_mint(
         funds: vec![],
     });
 
-    // Remove mintable token id from map
-    MINTABLE_TOKEN_IDS.remove(deps.storage, mintable_token_id);
     // Decrement mintable num tokens
     MINTABLE_NUM_TOKENS.save(deps.storage, &(mintable_num_tokens - 1))?;
     // Save the new mint count for the sender's address
@@ -398,6 +549,56 @@ fn _execute_mint(
         .add_message(msg))
 }
 
+// Roll the stored seed forward with the sender, tx index and block, so the
+// next token id can't be predicted from the block time alone
+fn next_mint_seed(storage: &mut dyn Storage, env: &Env, sender: &Addr) -> StdResult<Vec<u8>> {
+    let tx_index = env.transaction.as_ref().map_or(0, |tx| tx.index);
+    let mut hasher = Sha256::new();
+    hasher.update(MINT_SEED.load(storage)?);
+    hasher.update(sender.as_bytes());
+    hasher.update(tx_index.to_be_bytes());
+    hasher.update(env.block.height.to_be_bytes());
+    hasher.update(env.block.time.nanos().to_be_bytes());
+    let seed = hasher.finalize().to_vec();
+    MINT_SEED.save(storage, &seed)?;
+    Ok(seed)
+}
+
+// Token id at a mintable position, unswapped positions hold their default id
+fn mintable_token_at(storage: &dyn Storage, position: u32) -> StdResult<u32> {
+    let token_id = MINTABLE_TOKEN_POSITIONS.may_load(storage, position)?;
+    Ok(token_id.unwrap_or(position + 1))
+}
+
+// Position of an unminted token id. A minted id's default position is either
+// popped or holds the token swapped into it.
+fn mintable_position(
+    storage: &dyn Storage,
+    token_id: u32,
+    mintable_num_tokens: u32,
+) -> StdResult<Option<u32>> {
+    let position = TOKEN_POSITIONS
+        .may_load(storage, token_id)?
+        .unwrap_or(token_id - 1);
+    if position < mintable_num_tokens && mintable_token_at(storage, position)? == token_id {
+        return Ok(Some(position));
+    }
+    Ok(None)
+}
+
+// Swap the token id at `position` with the one at `last` and pop it
+fn pop_mintable_token(storage: &mut dyn Storage, position: u32, last: u32) -> StdResult<u32> {
+    let token_id = mintable_token_at(storage, position)?;
+    if position != last {
+        let last_token_id = mintable_token_at(storage, last)?;
+        MINTABLE_TOKEN_POSITIONS.save(storage, position, &last_token_id)?;
+        TOKEN_POSITIONS.save(storage, last_token_id, &position)?;
+    }
+    MINTABLE_TOKEN_POSITIONS.remove(storage, last);
+    TOKEN_POSITIONS.remove(storage, token_id);
+    Ok(token_id)
+}
+
 pub fn execute_update_start_time(
     deps: DepsMut,
     env: Env,
@@ -420,6 +621,13 @@ pub fn execute_update_start_time(
         return Err(ContractError::InvalidStartTime(start_time, env.block.time));
     }
 
+    // The committed seed can't be revealed before minting starts
+    if let Some(reveal_time) = REVEAL_TIME.may_load(deps.storage)? {
+        if reveal_time <= start_time {
+            return Err(ContractError::InvalidRevealTime(reveal_time, start_time));
+        }
+    }
+
     config.start_time = start_time;
     CONFIG.save(deps.storage, &config)?;
     Ok(Response::new()
@@ -455,7 +663,7 @@ pub fn execute_update_per_address_limit(
 }
 
 // if admin_no_fee => no fee,
-// else if in whitelist => whitelist price
+// else if in whitelist stage => whitelist stage price
 // else => config unit price
 pub fn mint_price(deps: Deps, is_admin: bool) -> Result<Coin, StdError> {
     let config = CONFIG.load(deps.storage)?;
@@ -470,14 +678,13 @@ pub fn mint_price(deps: Deps, is_admin: bool) -> Result<Coin, StdError> {
 
     let whitelist = config.whitelist.unwrap();
 
-    let wl_config: WhitelistConfigResponse = deps
+    let res: ActiveStageResponse = deps
         .querier
-        .query_wasm_smart(whitelist, &WhitelistQueryMsg::Config {})?;
+        .query_wasm_smart(whitelist, &WhitelistQueryMsg::ActiveStage {})?;
 
-    if wl_config.is_active {
-        Ok(wl_config.unit_price)
-    } else {
-        Ok(config.unit_price)
+    match res.stage {
+        Some(stage) => Ok(stage.unit_price),
+        None => Ok(config.unit_price),
     }
 }
 
@@ -497,6 +704,7 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::MintableNumTokens {} => to_binary(&query_mintable_num_tokens(deps)?),
         QueryMsg::MintPrice {} => to_binary(&query_mint_price(deps)?),
         QueryMsg::MintCount { address } => to_binary(&query_mint_count(deps, address)?),
+        QueryMsg::MetadataId { token_id } => to_binary(&query_metadata_id(deps, token_id)?),
     }
 }
 
@@ -526,6 +734,26 @@ fn query_mint_count(deps: Deps, address: String) -> StdResult<MintCountResponse>
     })
 }
 
+fn query_metadata_id(deps: Deps, token_id: u32) -> StdResult<MetadataIdResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    if token_id == 0 || token_id > config.num_tokens {
+        return Err(StdError::generic_err("Invalid token id"));
+    }
+
+    // Without a commitment token ids are their own metadata ids
+    let metadata_id = if SEED_COMMITMENT.may_load(deps.storage)?.is_some() {
+        None
+    } else {
+        let offset = METADATA_OFFSET.may_load(deps.storage)?.unwrap_or(0);
+        let position = (token_id - 1) as u64 + offset as u64;
+        Some((position % config.num_tokens as u64) as u32 + 1)
+    };
+    Ok(MetadataIdResponse {
+        token_id,
+        metadata_id,
+    })
+}
+
 fn query_start_time(deps: Deps) -> StdResult<StartTimeResponse> {
     let config = CONFIG.load(deps.storage)?;
     Ok(StartTimeResponse {
@@ -543,10 +771,19 @@ fn query_mint_price(deps: Deps) -> StdResult<MintPriceResponse> {
     let current_price = mint_price(deps, false)?;
     let public_price = config.unit_price;
     let whitelist_price: Option<Coin> = if let Some(whitelist) = config.whitelist {
-        let wl_config: WhitelistConfigResponse = deps
+        // price of the open stage, else the base stage price
+        let res: ActiveStageResponse = deps
             .querier
-            .query_wasm_smart(whitelist, &WhitelistQueryMsg::Config {})?;
-        Some(wl_config.unit_price)
+            .query_wasm_smart(whitelist.clone(), &WhitelistQueryMsg::ActiveStage {})?;
+        match res.stage {
+            Some(stage) => Some(stage.unit_price),
+            None => {
+                let wl_config: WhitelistConfigResponse = deps
+                    .querier
+                    .query_wasm_smart(whitelist, &WhitelistQueryMsg::Config {})?;
+                Some(wl_config.unit_price)
+            }
+        }
     } else {
         None
     };
@@ -3,16 +3,19 @@ use cosmwasm_std::{coin, coins, Addr, Decimal, Empty, Timestamp, Uint128};
 use cosmwasm_std::{Api, Coin};
 use cw721::{Cw721QueryMsg, OwnerOfResponse};
 use cw721_base::ExecuteMsg as Cw721ExecuteMsg;
-use cw_multi_test::{App, AppBuilder, BankSudo, Contract, ContractWrapper, Executor, SudoMsg};
+use cw_multi_test::{
+    App, AppBuilder, AppResponse, BankSudo, Contract, ContractWrapper, Executor, SudoMsg,
+};
 use pg721::msg::{InstantiateMsg as Pg721InstantiateMsg, RoyaltyInfoResponse};
 use pg721::state::CollectionInfo;
+use sha2::{Digest, Sha256};
 use whitelist::msg::InstantiateMsg as WhitelistInstantiateMsg;
 use whitelist::msg::{AddMembersMsg, ExecuteMsg as WhitelistExecuteMsg};
 
-use crate::contract::instantiate;
+use crate::contract::{instantiate, metadata_offset};
 use crate::msg::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, MintCountResponse, MintPriceResponse,
-    MintableNumTokensResponse, QueryMsg, StartTimeResponse,
+    ConfigResponse, ExecuteMsg, InstantiateMsg, MetadataIdResponse, MintCountResponse,
+    MintPriceResponse, MintableNumTokensResponse, QueryMsg, StartTimeResponse,
 };
 use crate::ContractError;
 
@@ -71,7 +74,8 @@ pub fn contract_cw721() -> Box<dyn Contract<Empty>> {
     Box::new(contract)
 }
 
-fn setup_whitelist_contract(router: &mut App, creator: &Addr) -> Addr {
+// Upload contract code, instantiate whitelist contract and register the minter
+fn setup_whitelist_contract(router: &mut App, creator: &Addr, minter_addr: &Addr) -> Addr {
     let whitelist_code_id = router.store_code(contract_whitelist());
 
     let msg = WhitelistInstantiateMsg {
@@ -81,8 +85,9 @@ fn setup_whitelist_contract(router: &mut App, creator: &Addr) -> Addr {
         unit_price: coin(WHITELIST_AMOUNT, NATIVE_DENOM),
         per_address_limit: WL_PER_ADDRESS_LIMIT,
         member_limit: 1000,
+        merkle_root: None,
     };
-    router
+    let whitelist_addr = router
         .instantiate_contract(
             whitelist_code_id,
             creator.clone(),
@@ -91,7 +96,15 @@ fn setup_whitelist_contract(router: &mut App, creator: &Addr) -> Addr {
             "whitelist",
             None,
         )
-        .unwrap()
+        .unwrap();
+
+    // Allow the minter to record whitelist mints
+    let wasm_msg = WhitelistExecuteMsg::UpdateMinters(vec![minter_addr.to_string()]);
+    router
+        .execute_contract(creator.clone(), whitelist_addr.clone(), &wasm_msg, &[])
+        .unwrap();
+
+    whitelist_addr
 }
 
 // Upload contract code and instantiate minter contract
@@ -195,6 +208,16 @@ fn setup_block_time(router: &mut App, nanos: u64) {
     router.set_block(block);
 }
 
+// Token id picked by a mint response
+fn minted_token_id(res: &AppResponse) -> String {
+    res.events
+        .iter()
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == "token_id")
+        .map(|attr| attr.value.clone())
+        .unwrap()
+}
+
 // Deal with zero and non-zero coin amounts for msgs
 fn coins_for_msg(msg_coin: Coin) -> Vec<Coin> {
     if msg_coin.amount > Uint128::zero() {
@@ -292,7 +315,7 @@ fn happy_path() {
     setup_block_time(&mut router, START_TIME + 1);
 
     // Fail with incorrect tokens
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let err = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -302,7 +325,7 @@ fn happy_path() {
     assert!(err.is_err());
 
     // Succeeds if funds are sent
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -310,6 +333,7 @@ fn happy_path() {
         &coins(UNIT_PRICE, NATIVE_DENOM),
     );
     assert!(res.is_ok());
+    let token_id = minted_token_id(&res.unwrap());
 
     // Balances are correct
     // The creator should get the unit price - mint fee for the mint above
@@ -336,7 +360,7 @@ fn happy_path() {
 
     // Check NFT is transferred
     let query_owner_msg = Cw721QueryMsg::OwnerOf {
-        token_id: String::from("2"),
+        token_id,
         include_expired: None,
     };
     let res: OwnerOfResponse = router
@@ -371,6 +395,7 @@ fn happy_path() {
         }),
     );
     assert!(res.is_ok());
+    let token_id = minted_token_id(&res.unwrap());
 
     // Mint count is not increased if admin mints for the user
     let res: MintCountResponse = router
@@ -395,7 +420,7 @@ fn happy_path() {
 
     // Check that NFT is transferred
     let query_owner_msg = Cw721QueryMsg::OwnerOf {
-        token_id: String::from("1"),
+        token_id,
         include_expired: None,
     };
     let res: OwnerOfResponse = router
@@ -405,7 +430,7 @@ fn happy_path() {
     assert_eq!(res.owner, buyer.to_string());
 
     // Errors if sold out
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer,
         minter_addr.clone(),
@@ -437,7 +462,7 @@ fn mint_count_query() {
     let num_tokens = 10;
     let (minter_addr, config) = setup_minter_contract(&mut router, &creator, num_tokens);
     let cw721_addr = Addr::unchecked(config.cw721_address);
-    let whitelist_addr = setup_whitelist_contract(&mut router, &creator);
+    let whitelist_addr = setup_whitelist_contract(&mut router, &creator, &minter_addr);
     const EXPIRATION_TIME: Timestamp = Timestamp::from_nanos(START_TIME + 10_000);
 
     // Set block to before genesis mint start time
@@ -478,6 +503,9 @@ fn mint_count_query() {
     // Add buyer to whitelist
     let inner_msg = AddMembersMsg {
         to_add: vec![buyer.to_string()],
+        allocations: None,
+        stage_id: None,
+        skip_invalid: None,
     };
     let wasm_msg = WhitelistExecuteMsg::AddMembers(inner_msg);
     let res = router.execute_contract(creator.clone(), whitelist_addr, &wasm_msg, &[]);
@@ -486,7 +514,7 @@ fn mint_count_query() {
     setup_block_time(&mut router, START_TIME);
 
     // Mint succeeds
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -494,6 +522,7 @@ fn mint_count_query() {
         &coins(WHITELIST_AMOUNT, NATIVE_DENOM),
     );
     assert!(res.is_ok());
+    let token_id = minted_token_id(&res.unwrap());
 
     // Query count
     let res: MintCountResponse = router
@@ -509,7 +538,7 @@ fn mint_count_query() {
     assert_eq!(res.address, buyer.to_string());
 
     // Mint fails, over whitelist per address limit
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let err = router
         .execute_contract(
             buyer.clone(),
@@ -527,7 +556,7 @@ fn mint_count_query() {
     setup_block_time(&mut router, START_TIME + 20_000);
 
     // Public mint succeeds
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -552,7 +581,7 @@ fn mint_count_query() {
     // Buyer transfers NFT to creator
     let transfer_msg: Cw721ExecuteMsg<Empty> = Cw721ExecuteMsg::TransferNft {
         recipient: creator.to_string(),
-        token_id: "1".to_string(),
+        token_id,
     };
     let res = router.execute_contract(
         buyer.clone(),
@@ -563,7 +592,7 @@ fn mint_count_query() {
     assert!(res.is_ok());
 
     // Mint succeeds
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -586,7 +615,7 @@ fn mint_count_query() {
     assert_eq!(res.address, buyer.to_string());
 
     // Mint fails
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let err = router
         .execute_contract(
             buyer.clone(),
@@ -620,7 +649,7 @@ fn whitelist_already_started() {
     let (creator, _) = setup_accounts(&mut router);
     let num_tokens = 1;
     let (minter_addr, _) = setup_minter_contract(&mut router, &creator, num_tokens);
-    let whitelist_addr = setup_whitelist_contract(&mut router, &creator);
+    let whitelist_addr = setup_whitelist_contract(&mut router, &creator, &minter_addr);
 
     setup_block_time(&mut router, START_TIME + 101);
 
@@ -644,7 +673,7 @@ fn whitelist_can_update_before_start() {
     let (creator, _) = setup_accounts(&mut router);
     let num_tokens = 1;
     let (minter_addr, _) = setup_minter_contract(&mut router, &creator, num_tokens);
-    let whitelist_addr = setup_whitelist_contract(&mut router, &creator);
+    let whitelist_addr = setup_whitelist_contract(&mut router, &creator, &minter_addr);
 
     setup_block_time(&mut router, START_TIME - 1000);
 
@@ -672,6 +701,37 @@ fn whitelist_can_update_before_start() {
         .unwrap();
 }
 
+#[test]
+fn whitelist_requires_registered_minter() {
+    let mut router = custom_mock_app();
+    let (creator, _) = setup_accounts(&mut router);
+    let num_tokens = 1;
+    let (minter_addr, _) = setup_minter_contract(&mut router, &creator, num_tokens);
+    let whitelist_addr = setup_whitelist_contract(&mut router, &creator, &minter_addr);
+
+    setup_block_time(&mut router, START_TIME - 1000);
+
+    // Remove the minter from the whitelist minters
+    let wasm_msg = WhitelistExecuteMsg::UpdateMinters(vec![]);
+    let res = router.execute_contract(creator.clone(), whitelist_addr.clone(), &wasm_msg, &[]);
+    assert!(res.is_ok());
+
+    // set whitelist in minter contract fails
+    let set_whitelist_msg = ExecuteMsg::SetWhitelist {
+        whitelist: whitelist_addr.to_string(),
+    };
+    let err = router
+        .execute_contract(creator, minter_addr, &set_whitelist_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        ContractError::MinterNotRegistered {
+            whitelist: whitelist_addr.to_string()
+        }
+        .to_string()
+    );
+}
+
 #[test]
 fn whitelist_access_len_add_remove_expiration() {
     let mut router = custom_mock_app();
@@ -679,7 +739,7 @@ fn whitelist_access_len_add_remove_expiration() {
     let num_tokens = 1;
     let (minter_addr, config) = setup_minter_contract(&mut router, &creator, num_tokens);
     let cw721_addr = config.cw721_address;
-    let whitelist_addr = setup_whitelist_contract(&mut router, &creator);
+    let whitelist_addr = setup_whitelist_contract(&mut router, &creator, &minter_addr);
     const AFTER_GENESIS_TIME: Timestamp = Timestamp::from_nanos(START_TIME + 100);
 
     // Set to just before genesis mint start time
@@ -713,7 +773,7 @@ fn whitelist_access_len_add_remove_expiration() {
     assert!(res.is_ok());
 
     // Mint fails, buyer is not on whitelist
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -725,13 +785,16 @@ fn whitelist_access_len_add_remove_expiration() {
     // Add buyer to whitelist
     let inner_msg = AddMembersMsg {
         to_add: vec![buyer.to_string()],
+        allocations: None,
+        stage_id: None,
+        skip_invalid: None,
     };
     let wasm_msg = WhitelistExecuteMsg::AddMembers(inner_msg);
     let res = router.execute_contract(creator.clone(), whitelist_addr.clone(), &wasm_msg, &[]);
     assert!(res.is_ok());
 
     // Mint fails, not whitelist price
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     router
         .execute_contract(
             buyer.clone(),
@@ -762,7 +825,7 @@ fn whitelist_access_len_add_remove_expiration() {
     );
 
     // Mint succeeds with whitelist price
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -772,7 +835,7 @@ fn whitelist_access_len_add_remove_expiration() {
     assert!(res.is_ok());
 
     // Mint fails, over whitelist per address limit
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let err = router
         .execute_contract(
             buyer.clone(),
@@ -800,7 +863,7 @@ fn whitelist_access_len_add_remove_expiration() {
     assert!(res.is_ok());
 
     // Mint fails, buyer exceeded per address limit
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let err = router
         .execute_contract(
             buyer.clone(),
@@ -815,13 +878,18 @@ fn whitelist_access_len_add_remove_expiration() {
     );
 
     // Remove buyer from whitelist
-    let inner_msg = AddMembersMsg { to_add: vec![] };
+    let inner_msg = AddMembersMsg {
+        to_add: vec![],
+        allocations: None,
+        stage_id: None,
+        skip_invalid: None,
+    };
     let wasm_msg = WhitelistExecuteMsg::AddMembers(inner_msg);
     let res = router.execute_contract(creator.clone(), whitelist_addr, &wasm_msg, &[]);
     assert!(res.is_ok());
 
     // Mint fails
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer,
         minter_addr,
@@ -852,7 +920,7 @@ fn before_start_time() {
     assert!(res.is_err());
 
     // Buyer can't mint before start_time
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -875,7 +943,7 @@ fn before_start_time() {
     setup_block_time(&mut router, START_TIME + 10_000_000);
 
     // Mint succeeds
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer,
         minter_addr,
@@ -932,7 +1000,7 @@ fn check_per_address_limit() {
     assert!(res.is_ok());
 
     // First mint succeeds
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -943,7 +1011,7 @@ fn check_per_address_limit() {
     assert!(res.is_ok());
 
     // Second mint fails from exceeding per address limit
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer,
         minter_addr,
@@ -953,6 +1021,90 @@ fn check_per_address_limit() {
     assert!(res.is_err());
 }
 
+#[test]
+fn whitelist_allocation_over_per_address_limit() {
+    let mut router = custom_mock_app();
+    let (creator, buyer) = setup_accounts(&mut router);
+    let num_tokens = 5;
+    let (minter_addr, _config) = setup_minter_contract(&mut router, &creator, num_tokens);
+    let whitelist_addr = setup_whitelist_contract(&mut router, &creator, &minter_addr);
+
+    setup_block_time(&mut router, START_TIME - 10);
+
+    let per_address_limit_msg = ExecuteMsg::UpdatePerAddressLimit {
+        per_address_limit: 1,
+    };
+    let res = router.execute_contract(
+        creator.clone(),
+        minter_addr.clone(),
+        &per_address_limit_msg,
+        &[],
+    );
+    assert!(res.is_ok());
+
+    let set_whitelist_msg = ExecuteMsg::SetWhitelist {
+        whitelist: whitelist_addr.to_string(),
+    };
+    let res = router.execute_contract(
+        creator.clone(),
+        minter_addr.clone(),
+        &set_whitelist_msg,
+        &[],
+    );
+    assert!(res.is_ok());
+
+    // Buyer's allocation is larger than the public per address limit
+    let inner_msg = AddMembersMsg {
+        to_add: vec![],
+        allocations: Some(vec![(buyer.to_string(), 3)]),
+        stage_id: None,
+        skip_invalid: None,
+    };
+    let wasm_msg = WhitelistExecuteMsg::AddMembers(inner_msg);
+    let res = router.execute_contract(creator.clone(), whitelist_addr, &wasm_msg, &[]);
+    assert!(res.is_ok());
+
+    // Whitelist mints are only limited by the allocation
+    setup_block_time(&mut router, START_TIME + 100);
+    let mint_msg = ExecuteMsg::Mint { proof: None };
+    for _ in 0..3 {
+        let res = router.execute_contract(
+            buyer.clone(),
+            minter_addr.clone(),
+            &mint_msg,
+            &coins(WHITELIST_AMOUNT, NATIVE_DENOM),
+        );
+        assert!(res.is_ok());
+    }
+    let err = router
+        .execute_contract(
+            buyer.clone(),
+            minter_addr.clone(),
+            &mint_msg,
+            &coins(WHITELIST_AMOUNT, NATIVE_DENOM),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        ContractError::MaxPerAddressLimitExceeded {}.to_string()
+    );
+
+    // Public mints count the whitelist mints against the per address limit
+    setup_block_time(&mut router, START_TIME + 10000000);
+    let err = router
+        .execute_contract(
+            buyer,
+            minter_addr,
+            &mint_msg,
+            &coins(UNIT_PRICE, NATIVE_DENOM),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        ContractError::MaxPerAddressLimitExceeded {}.to_string()
+    );
+}
+
 #[test]
 fn mint_for_token_id_addr() {
     let mut router = custom_mock_app();
@@ -985,9 +1137,9 @@ fn mint_for_token_id_addr() {
     );
 
     // Test token id already sold
-    // 1. mint token_id 1
-    // 2. mint_for token_id 1
-    let mint_msg = ExecuteMsg::Mint {};
+    // 1. mint a random token_id
+    // 2. mint_for the same token_id
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -995,6 +1147,7 @@ fn mint_for_token_id_addr() {
         &coins(UNIT_PRICE, NATIVE_DENOM),
     );
     assert!(res.is_ok());
+    let sold_token_id: u32 = minted_token_id(&res.unwrap()).parse().unwrap();
 
     // Minter contract should have a balance
     let minter_balance = router
@@ -1027,7 +1180,7 @@ fn mint_for_token_id_addr() {
     );
 
     // Mint fails, token_id already sold
-    let token_id = 1;
+    let token_id = sold_token_id;
     let mint_for_msg = ExecuteMsg::MintFor {
         token_id,
         recipient: buyer.to_string(),
@@ -1054,8 +1207,8 @@ fn mint_for_token_id_addr() {
         .unwrap();
     assert_eq!(mintable_num_tokens_response.count, 3);
 
-    // Test mint_for token_id 2 then normal mint
-    let token_id = 2;
+    // Test mint_for an unsold token_id then normal mint
+    let token_id = if sold_token_id == 2 { 3 } else { 2 };
     let mint_for_msg = ExecuteMsg::MintFor {
         token_id,
         recipient: buyer.to_string(),
@@ -1285,7 +1438,7 @@ fn unhappy_path() {
     let (minter_addr, _config) = setup_minter_contract(&mut router, &creator, num_tokens);
 
     // Fails if too little funds are sent
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -1295,7 +1448,7 @@ fn unhappy_path() {
     assert!(res.is_err());
 
     // Fails if too many funds are sent
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer.clone(),
         minter_addr.clone(),
@@ -1305,7 +1458,7 @@ fn unhappy_path() {
     assert!(res.is_err());
 
     // Fails wrong denom is sent
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(buyer, minter_addr, &mint_msg, &coins(UNIT_PRICE, "uatom"));
     assert!(res.is_err());
 }
@@ -1332,7 +1485,7 @@ fn can_withdraw() {
         .unwrap_err();
 
     // do a mint
-    let mint_msg = ExecuteMsg::Mint {};
+    let mint_msg = ExecuteMsg::Mint { proof: None };
     let res = router.execute_contract(
         buyer,
         minter_addr.clone(),
@@ -1363,3 +1516,315 @@ fn can_withdraw() {
         coins(INITIAL_BALANCE + UNIT_PRICE, NATIVE_DENOM)
     );
 }
+
+#[test]
+fn shuffled_mint_with_seed_commitment() {
+    let mut router = custom_mock_app();
+    let (creator, buyer) = setup_accounts(&mut router);
+    let num_tokens = 5;
+    let (minter_addr, _config) = setup_minter_contract(&mut router, &creator, num_tokens);
+    let seed = "creator secret";
+    let commitment = hex::encode(Sha256::digest(seed.as_bytes()));
+
+    // Token ids are their own metadata ids without a commitment
+    let res: MetadataIdResponse = router
+        .wrap()
+        .query_wasm_smart(minter_addr.clone(), &QueryMsg::MetadataId { token_id: 2 })
+        .unwrap();
+    assert_eq!(res.metadata_id, Some(2));
+
+    // Commitment must be a sha256 hash
+    let commit_msg = ExecuteMsg::CommitSeed {
+        commitment: "not a hash".to_string(),
+        reveal_time: None,
+    };
+    let err = router
+        .execute_contract(creator.clone(), minter_addr.clone(), &commit_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        ContractError::InvalidSeedCommitment {}.to_string()
+    );
+
+    // Only the admin can commit a seed
+    let commit_msg = ExecuteMsg::CommitSeed {
+        commitment: commitment.clone(),
+        reveal_time: None,
+    };
+    router
+        .execute_contract(buyer.clone(), minter_addr.clone(), &commit_msg, &[])
+        .unwrap_err();
+    let res = router.execute_contract(creator.clone(), minter_addr.clone(), &commit_msg, &[]);
+    assert!(res.is_ok());
+
+    // Metadata ids are unknown until the seed is revealed
+    let res: MetadataIdResponse = router
+        .wrap()
+        .query_wasm_smart(minter_addr.clone(), &QueryMsg::MetadataId { token_id: 2 })
+        .unwrap();
+    assert_eq!(res.metadata_id, None);
+
+    // Can't reveal before the collection sells out
+    let reveal_msg = ExecuteMsg::RevealSeed {
+        seed: seed.to_string(),
+    };
+    let err = router
+        .execute_contract(creator.clone(), minter_addr.clone(), &reveal_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        ContractError::RevealTooEarly {}.to_string()
+    );
+
+    setup_block_time(&mut router, START_TIME + 1);
+
+    // Can't commit once minting started
+    let err = router
+        .execute_contract(creator.clone(), minter_addr.clone(), &commit_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        ContractError::AlreadyStarted {}.to_string()
+    );
+
+    // Every token id is minted exactly once
+    let mint_msg = ExecuteMsg::Mint { proof: None };
+    let mut token_ids: Vec<u32> = (0..num_tokens)
+        .map(|_| {
+            let res = router
+                .execute_contract(
+                    buyer.clone(),
+                    minter_addr.clone(),
+                    &mint_msg,
+                    &coins(UNIT_PRICE, NATIVE_DENOM),
+                )
+                .unwrap();
+            minted_token_id(&res).parse().unwrap()
+        })
+        .collect();
+    token_ids.sort_unstable();
+    assert_eq!(token_ids, vec![1, 2, 3, 4, 5]);
+
+    let err = router
+        .execute_contract(
+            buyer,
+            minter_addr.clone(),
+            &mint_msg,
+            &coins(UNIT_PRICE, NATIVE_DENOM),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        ContractError::SoldOut {}.to_string()
+    );
+
+    // Revealed seed has to match the commitment
+    let wrong_reveal_msg = ExecuteMsg::RevealSeed {
+        seed: "another secret".to_string(),
+    };
+    let err = router
+        .execute_contract(creator.clone(), minter_addr.clone(), &wrong_reveal_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        ContractError::InvalidSeed {}.to_string()
+    );
+    let res = router
+        .execute_contract(creator, minter_addr.clone(), &reveal_msg, &[])
+        .unwrap();
+    let offset: u32 = res
+        .events
+        .iter()
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == "metadata_offset")
+        .map(|attr| attr.value.parse().unwrap())
+        .unwrap();
+    assert!(offset < num_tokens);
+
+    // The revealed seed offsets every token id onto a distinct metadata id
+    let mut metadata_ids: Vec<u32> = (1..=num_tokens)
+        .map(|token_id| {
+            let res: MetadataIdResponse = router
+                .wrap()
+                .query_wasm_smart(minter_addr.clone(), &QueryMsg::MetadataId { token_id })
+                .unwrap();
+            assert_eq!(
+                res.metadata_id,
+                Some((token_id - 1 + offset) % num_tokens + 1)
+            );
+            res.metadata_id.unwrap()
+        })
+        .collect();
+    metadata_ids.sort_unstable();
+    assert_eq!(metadata_ids, vec![1, 2, 3, 4, 5]);
+
+    // A different seed over the same mints gives a different offset
+    let mint_seed: Vec<u8> = (0..32).collect();
+    assert_eq!(metadata_offset("creator secret", &mint_seed, 1000), 309);
+    assert_eq!(metadata_offset("another secret", &mint_seed, 1000), 42);
+}
+
+#[test]
+fn uppercase_seed_commitment() {
+    let mut router = custom_mock_app();
+    let (creator, buyer) = setup_accounts(&mut router);
+    let (minter_addr, _config) = setup_minter_contract(&mut router, &creator, 1);
+    let seed = "creator secret";
+
+    // An uppercase commitment is accepted and matches the revealed seed
+    let commit_msg = ExecuteMsg::CommitSeed {
+        commitment: hex::encode_upper(Sha256::digest(seed.as_bytes())),
+        reveal_time: None,
+    };
+    let res = router.execute_contract(creator.clone(), minter_addr.clone(), &commit_msg, &[]);
+    assert!(res.is_ok());
+
+    setup_block_time(&mut router, START_TIME + 1);
+    let mint_msg = ExecuteMsg::Mint { proof: None };
+    let res = router.execute_contract(
+        buyer,
+        minter_addr.clone(),
+        &mint_msg,
+        &coins(UNIT_PRICE, NATIVE_DENOM),
+    );
+    assert!(res.is_ok());
+
+    let reveal_msg = ExecuteMsg::RevealSeed {
+        seed: seed.to_string(),
+    };
+    let res = router.execute_contract(creator, minter_addr.clone(), &reveal_msg, &[]);
+    assert!(res.is_ok());
+
+    let res: MetadataIdResponse = router
+        .wrap()
+        .query_wasm_smart(minter_addr, &QueryMsg::MetadataId { token_id: 1 })
+        .unwrap();
+    assert_eq!(res.metadata_id, Some(1));
+}
+
+#[test]
+fn reveal_seed_after_reveal_time() {
+    let mut router = custom_mock_app();
+    let (creator, buyer) = setup_accounts(&mut router);
+    let num_tokens = 5;
+    let (minter_addr, _config) = setup_minter_contract(&mut router, &creator, num_tokens);
+    let seed = "creator secret";
+    let commitment = hex::encode(Sha256::digest(seed.as_bytes()));
+
+    // The reveal time must be after the start time
+    let commit_msg = ExecuteMsg::CommitSeed {
+        commitment: commitment.clone(),
+        reveal_time: Some(Timestamp::from_nanos(START_TIME)),
+    };
+    let err = router
+        .execute_contract(creator.clone(), minter_addr.clone(), &commit_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        ContractError::InvalidRevealTime(
+            Timestamp::from_nanos(START_TIME),
+            Timestamp::from_nanos(START_TIME)
+        )
+        .to_string()
+    );
+    let reveal_time = Timestamp::from_nanos(START_TIME + 1000);
+    let commit_msg = ExecuteMsg::CommitSeed {
+        commitment,
+        reveal_time: Some(reveal_time),
+    };
+    let res = router.execute_contract(creator.clone(), minter_addr.clone(), &commit_msg, &[]);
+    assert!(res.is_ok());
+
+    setup_block_time(&mut router, START_TIME + 1);
+    let mint_msg = ExecuteMsg::Mint { proof: None };
+    let res = router.execute_contract(
+        buyer,
+        minter_addr.clone(),
+        &mint_msg,
+        &coins(UNIT_PRICE, NATIVE_DENOM),
+    );
+    assert!(res.is_ok());
+
+    // Can't reveal before the collection sells out or the reveal time passes
+    let reveal_msg = ExecuteMsg::RevealSeed {
+        seed: seed.to_string(),
+    };
+    let err = router
+        .execute_contract(creator.clone(), minter_addr.clone(), &reveal_msg, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.source().unwrap().to_string(),
+        ContractError::RevealTooEarly {}.to_string()
+    );
+
+    setup_block_time(&mut router, reveal_time.nanos());
+    let res = router.execute_contract(creator, minter_addr.clone(), &reveal_msg, &[]);
+    assert!(res.is_ok());
+
+    let res: MetadataIdResponse = router
+        .wrap()
+        .query_wasm_smart(minter_addr, &QueryMsg::MetadataId { token_id: 1 })
+        .unwrap();
+    assert!(res.metadata_id.is_some());
+}
+
+#[test]
+fn lazy_token_ids_large_collection() {
+    let mut router = custom_mock_app();
+    let (creator, buyer) = setup_accounts(&mut router);
+    let num_tokens = 1_000_000;
+    let (minter_addr, _config) = setup_minter_contract(&mut router, &creator, num_tokens);
+
+    let res: MintableNumTokensResponse = router
+        .wrap()
+        .query_wasm_smart(minter_addr.clone(), &QueryMsg::MintableNumTokens {})
+        .unwrap();
+    assert_eq!(res.count, num_tokens);
+
+    setup_block_time(&mut router, START_TIME + 1);
+
+    // Mint the first and last token ids
+    for token_id in [1, num_tokens] {
+        let mint_for_msg = ExecuteMsg::MintFor {
+            token_id,
+            recipient: buyer.to_string(),
+        };
+        let res = router
+            .execute_contract(creator.clone(), minter_addr.clone(), &mint_for_msg, &[])
+            .unwrap();
+        assert_eq!(minted_token_id(&res), token_id.to_string());
+    }
+
+    // Random mints never hand out a sold token id
+    let mint_msg = ExecuteMsg::Mint { proof: None };
+    for _ in 0..3 {
+        let res = router
+            .execute_contract(
+                buyer.clone(),
+                minter_addr.clone(),
+                &mint_msg,
+                &coins(UNIT_PRICE, NATIVE_DENOM),
+            )
+            .unwrap();
+        let token_id: u32 = minted_token_id(&res).parse().unwrap();
+        assert!(token_id > 1 && token_id < num_tokens);
+
+        let mint_for_msg = ExecuteMsg::MintFor {
+            token_id,
+            recipient: buyer.to_string(),
+        };
+        let err = router
+            .execute_contract(creator.clone(), minter_addr.clone(), &mint_for_msg, &[])
+            .unwrap_err();
+        assert_eq!(
+            err.source().unwrap().to_string(),
+            ContractError::TokenIdAlreadySold { token_id }.to_string()
+        );
+    }
+
+    let res: MintableNumTokensResponse = router
+        .wrap()
+        .query_wasm_smart(minter_addr, &QueryMsg::MintableNumTokens {})
+        .unwrap();
+    assert_eq!(res.count, num_tokens - 5);
+}
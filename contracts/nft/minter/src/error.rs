@@ -44,6 +44,9 @@ pub enum ContractError {
     #[error("WhitelistAlreadyStarted")]
     WhitelistAlreadyStarted {},
 
+    #[error("Minter is not registered with whitelist {whitelist}")]
+    MinterNotRegistered { whitelist: String },
+
     #[error("InvalidStartTime {0} < {1}")]
     InvalidStartTime(Timestamp, Timestamp),
 
@@ -68,6 +71,18 @@ pub enum ContractError {
     #[error("Token id: {token_id} already sold")]
     TokenIdAlreadySold { token_id: u32 },
 
+    #[error("Invalid seed commitment (must be a hex encoded sha256 hash)")]
+    InvalidSeedCommitment {},
+
+    #[error("Seed does not match the commitment")]
+    InvalidSeed {},
+
+    #[error("Seed can only be revealed once sold out or past the reveal time")]
+    RevealTooEarly {},
+
+    #[error("InvalidRevealTime {0} < {1}")]
+    InvalidRevealTime(Timestamp, Timestamp),
+
     #[error("ZeroBalance")]
     ZeroBalance {},
 
@@ -19,13 +19,32 @@ pub struct InstantiateMsg {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    Mint {},
+    Mint {
+        /// Merkle proof of whitelist membership, when the whitelist uses a Merkle root
+        proof: Option<Vec<String>>,
+    },
+    /// The whitelist must already list this contract in its minters, as
+    /// whitelist mints are recorded there
     SetWhitelist { whitelist: String },
     UpdateStartTime(Timestamp),
     UpdatePerAddressLimit { per_address_limit: u32 },
     MintTo { recipient: String },
     MintFor { token_id: u32, recipient: String },
     Withdraw {},
+    /// Commit to a secret seed before minting starts. Token id selection only
+    /// depends on public data and can be simulated, the seed instead decides
+    /// which metadata each token id gets once revealed.
+    /// The seed can also be revealed after `reveal_time`, so a collection that
+    /// doesn't sell out still gets its metadata.
+    CommitSeed {
+        commitment: String,
+        reveal_time: Option<Timestamp>,
+    },
+    /// Reveal the committed seed, fixing the metadata offset. Allowed once the
+    /// collection is sold out or the committed reveal time has passed. An early
+    /// reveal hashes the mint seed at the time of the reveal, and the metadata
+    /// of the token ids still unminted becomes known.
+    RevealSeed { seed: String },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -36,6 +55,9 @@ pub enum QueryMsg {
     StartTime {},
     MintPrice {},
     MintCount { address: String },
+    /// Metadata id under `base_token_uri` for a token id, unknown until a
+    /// committed seed is revealed
+    MetadataId { token_id: u32 },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -73,3 +95,9 @@ pub struct MintCountResponse {
     pub address: String,
     pub count: u32,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MetadataIdResponse {
+    pub token_id: u32,
+    pub metadata_id: Option<u32>,
+}
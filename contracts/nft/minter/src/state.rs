@@ -18,6 +18,19 @@ pub struct Config {
 
 pub const CONFIG: Item<Config> = Item::new("config");
 pub const CW721_ADDRESS: Item<Addr> = Item::new("cw721_address");
-pub const MINTABLE_TOKEN_IDS: Map<u32, bool> = Map::new("mt");
+/// Unminted token ids packed into positions 0..MINTABLE_NUM_TOKENS, removed by swap-and-pop.
+/// Only swapped positions are stored, a missing position holds token id position + 1
+pub const MINTABLE_TOKEN_POSITIONS: Map<u32, u32> = Map::new("mp");
+/// Reverse lookup of MINTABLE_TOKEN_POSITIONS, a missing token id sits at position token id - 1
+pub const TOKEN_POSITIONS: Map<u32, u32> = Map::new("tp");
 pub const MINTABLE_NUM_TOKENS: Item<u32> = Item::new("mintable_num_tokens");
+/// Seed for token id selection, rolled forward on every mint
+pub const MINT_SEED: Item<Vec<u8>> = Item::new("mint_seed");
+/// Hex encoded sha256 of an admin seed, revealed once the collection sells out
+/// or the reveal time passes
+pub const SEED_COMMITMENT: Item<String> = Item::new("seed_commitment");
+/// Time after which the seed can be revealed before the collection sells out
+pub const REVEAL_TIME: Item<Timestamp> = Item::new("reveal_time");
+/// Offset from token ids to metadata ids, derived from the revealed seed
+pub const METADATA_OFFSET: Item<u32> = Item::new("metadata_offset");
 pub const MINTER_ADDRS: Map<Addr, u32> = Map::new("ma");
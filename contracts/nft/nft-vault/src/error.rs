@@ -1,7 +1,7 @@
 use cosmwasm_std::{StdError};
 use thiserror::Error;
 use crate::hooks::HookError;
-use cw_utils::PaymentError;
+use cw_utils::{ParseReplyError, PaymentError};
 
 #[derive(Error, Debug)]
 pub enum ContractError {
@@ -17,6 +17,12 @@ pub enum ContractError {
     #[error("{0}")]
     PaymentError(#[from] PaymentError),
 
+    #[error("{0}")]
+    ParseReplyError(#[from] ParseReplyError),
+
     #[error("InvalidStatus: {0}")]
     InvalidStatus(String),
+
+    #[error("InvalidInput: {0}")]
+    InvalidInput(String),
 }
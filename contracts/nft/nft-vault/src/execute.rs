@@ -1,11 +1,40 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, Addr, Event, WasmMsg, SubMsg, Reply};
-use cw_utils::{nonpayable};
+use cosmwasm_std::{
+    from_binary, DepsMut, Env, MessageInfo, Response, Addr, Event, WasmMsg, SubMsg, Reply,
+    StdResult, Storage, Timestamp, Uint128, Order, BankMsg
+};
+use cw20::{Cw20ReceiveMsg, Denom};
+use cw721::Cw721ReceiveMsg;
+use cw_storage_plus::Bound;
+use cw_utils::{must_pay, nonpayable, parse_reply_instantiate_data};
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, HookMsg, HookAction};
-use crate::state::{CONFIG, VaultToken, VaultTokenStatus, vault_tokens, STAKE_HOOKS, UNSTAKE_HOOKS, WITHDRAW_HOOKS};
-use crate::helpers::{map_validate, only_operator, transfer_nft, only_owner};
+use crate::msg::{ExecuteMsg, HookMsg, HookAction, ReceiveMsg, ReceiveNftMsg, hook_msgs_into_binary};
+use crate::hooks::Hooks;
+use crate::state::{
+    Config, CONFIG, VaultToken, VaultTokenStatus, vault_tokens, STAKE_HOOKS, UNSTAKE_HOOKS,
+    WITHDRAW_HOOKS, RewardConfig, REWARD_CONFIG, REWARD_STATE, TOKEN_REWARDS, VaultTokenKey,
+    vault_token_key, HookDelivery, HOOK_DELIVERY_ID, PENDING_HOOKS, FAILED_HOOKS, LockTier,
+    EarlyUnstakePenalty, RECEIPT_COLLECTION, STAKED_BALANCES, TOTAL_STAKED, ForceAction,
+    NUM_STAKERS, UNSTAKED_BALANCES, TOTAL_UNSTAKED, OWNER_UNSTAKES, UNSTAKES,
+    ScheduledForceAction, FORCE_ACTIONS
+};
+use crate::helpers::{
+    map_validate, only_operator, transfer_nft, only_owner, accrue_rewards, settle_token_reward,
+    transfer_reward, only_approved_collection, select_lock_tier, validate_lock_config, burn_nft,
+    mint_receipt, position_owner, burn_receipt, void_receipt, receipt_position, return_receipt
+};
+
+// Reply id of the receipt collection instantiation, hook delivery ids start at 1
+pub const INSTANTIATE_RECEIPT_REPLY_ID: u64 = 0;
+
+// Withdraw limits
+const DEFAULT_WITHDRAW_LIMIT: u32 = 10;
+const MAX_WITHDRAW_LIMIT: u32 = 30;
+
+// Retry limits
+const DEFAULT_RETRY_LIMIT: u32 = 10;
+const MAX_RETRY_LIMIT: u32 = 30;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
@@ -18,6 +47,7 @@ pub fn execute(
 
     match msg {
         ExecuteMsg::UpdateConfig {
+            collections,
             operators,
             label,
             unstake_period,
@@ -25,56 +55,160 @@ pub fn execute(
             deps,
             env,
             info,
+            collections,
             operators,
             label,
             unstake_period
         ),
-        ExecuteMsg::AddStakeHook { hook } => execute_add_stake_hook(
+        ExecuteMsg::AddStakeHook { hook, required } => execute_add_stake_hook(
             deps,
             info,
-            api.addr_validate(&hook)?
+            api.addr_validate(&hook)?,
+            required.unwrap_or(false)
         ),
         ExecuteMsg::RemoveStakeHook { hook } => execute_remove_stake_hook(
             deps,
             info,
             api.addr_validate(&hook)?
         ),
-        ExecuteMsg::AddUnstakeHook { hook } => execute_add_unstake_hook(
+        ExecuteMsg::AddUnstakeHook { hook, required } => execute_add_unstake_hook(
             deps,
             info,
-            api.addr_validate(&hook)?
+            api.addr_validate(&hook)?,
+            required.unwrap_or(false)
         ),
         ExecuteMsg::RemoveUnstakeHook { hook } => execute_remove_unstake_hook(
             deps,
             info,
             api.addr_validate(&hook)?
         ),
-        ExecuteMsg::AddWithdrawHook { hook } => execute_add_withdraw_hook(
+        ExecuteMsg::AddWithdrawHook { hook, required } => execute_add_withdraw_hook(
             deps,
             info,
-            api.addr_validate(&hook)?
+            api.addr_validate(&hook)?,
+            required.unwrap_or(false)
         ),
         ExecuteMsg::RemoveWithdrawHook { hook } => execute_remove_withdraw_hook(
             deps,
             info,
             api.addr_validate(&hook)?
         ),
-        ExecuteMsg::Stake { token_id } => execute_stake(
+        ExecuteMsg::UpdateLockConfig {
+            lock_tiers,
+            early_unstake_penalty,
+        } => execute_update_lock_config(
+            deps,
+            info,
+            lock_tiers,
+            early_unstake_penalty
+        ),
+        ExecuteMsg::Stake { collection, token_id, lock_duration } => execute_stake(
+            deps,
+            env,
+            info,
+            api.addr_validate(&collection)?,
+            token_id,
+            lock_duration
+        ),
+        ExecuteMsg::ReceiveNft(receive_msg) => execute_receive_nft(
+            deps,
+            env,
+            info,
+            receive_msg
+        ),
+        ExecuteMsg::StakeMany { collection, token_ids, lock_duration } => execute_stake_many(
+            deps,
+            env,
+            info,
+            api.addr_validate(&collection)?,
+            token_ids,
+            lock_duration
+        ),
+        ExecuteMsg::Unstake { collection, token_id } => execute_unstake(
             deps,
             env,
             info,
+            api.addr_validate(&collection)?,
             token_id
         ),
-        ExecuteMsg::Unstake { token_id } => execute_unstake(
+        ExecuteMsg::Withdraw { collection, token_id } => execute_withdraw(
             deps,
             env,
             info,
+            api.addr_validate(&collection)?,
             token_id
         ),
-        ExecuteMsg::Withdraw { token_id } => execute_withdraw(
+        ExecuteMsg::UnstakeMany { collection, token_ids } => execute_unstake_many(
+            deps,
+            env,
+            info,
+            api.addr_validate(&collection)?,
+            token_ids
+        ),
+        ExecuteMsg::WithdrawMany { collection, token_ids } => execute_withdraw_many(
+            deps,
+            env,
+            info,
+            api.addr_validate(&collection)?,
+            token_ids
+        ),
+        ExecuteMsg::WithdrawAllTransferrable { limit } => execute_withdraw_all_transferrable(
+            deps,
+            env,
+            info,
+            limit
+        ),
+        ExecuteMsg::ConfigureRewards { denom, emission_rate } => execute_configure_rewards(
+            deps,
+            env,
+            info,
+            denom,
+            emission_rate
+        ),
+        ExecuteMsg::FundRewards {} => execute_fund_rewards(
+            deps,
+            env,
+            info
+        ),
+        ExecuteMsg::Receive(receive_msg) => execute_receive(
+            deps,
+            env,
+            info,
+            receive_msg
+        ),
+        ExecuteMsg::ClaimRewards { collection, token_ids } => execute_claim_rewards(
+            deps,
+            env,
+            info,
+            api.addr_validate(&collection)?,
+            token_ids
+        ),
+        ExecuteMsg::RetryHooks { limit } => execute_retry_hooks(
+            deps,
+            info,
+            limit
+        ),
+        ExecuteMsg::ForceUnstake { collection, token_id, reason } => execute_force_unstake(
             deps,
             env,
             info,
+            api.addr_validate(&collection)?,
+            token_id,
+            reason
+        ),
+        ExecuteMsg::ForceWithdraw { collection, token_id, recipient, reason } => execute_force_withdraw(
+            deps,
+            env,
+            info,
+            api.addr_validate(&collection)?,
+            token_id,
+            api.addr_validate(&recipient)?,
+            reason
+        ),
+        ExecuteMsg::CancelForceAction { collection, token_id } => execute_cancel_force_action(
+            deps,
+            info,
+            api.addr_validate(&collection)?,
             token_id
         ),
     }
@@ -84,6 +218,7 @@ pub fn execute_update_config(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
+    collections: Option<Vec<String>>,
     operators: Option<Vec<String>>,
     label: Option<String>,
     unstake_period: Option<u64>,
@@ -91,6 +226,13 @@ pub fn execute_update_config(
     let mut config = CONFIG.load(deps.storage)?;
     only_operator(&info, &config)?;
 
+    // Tokens of removed collections can still be unstaked and withdrawn
+    if let Some(_collections) = collections {
+        if _collections.is_empty() {
+            return Err(ContractError::InvalidInput("collections must not be empty".to_string()));
+        }
+        config.collections = map_validate(deps.api, &_collections)?;
+    }
     if let Some(_operators) = operators {
         config.operators = map_validate(deps.api, &_operators)?;
     }
@@ -105,21 +247,22 @@ pub fn execute_update_config(
     Ok(Response::new()
         .add_attribute("action", "update_config")
         .add_attribute("operators", config.operators.iter().map(|addr| addr.to_string()).collect::<Vec<String>>().join(","))
-        .add_attribute("cw721_address", config.cw721_address)
+        .add_attribute("collections", config.collections.iter().map(|addr| addr.to_string()).collect::<Vec<String>>().join(","))
         .add_attribute("label", config.label)
         .add_attribute("unstake_period", config.unstake_period.to_string())
     )
 }
 
-pub fn execute_add_stake_hook(deps: DepsMut, info: MessageInfo, hook: Addr) -> Result<Response, ContractError> {
+pub fn execute_add_stake_hook(deps: DepsMut, info: MessageInfo, hook: Addr, required: bool) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     only_operator(&info, &config)?;
 
-    STAKE_HOOKS.add_hook(deps.storage, hook.clone())?;
+    STAKE_HOOKS.add_hook(deps.storage, hook.clone(), required)?;
 
     let res = Response::new()
         .add_attribute("action", "add_stake_hook")
-        .add_attribute("hook", hook);
+        .add_attribute("hook", hook)
+        .add_attribute("required", required.to_string());
     Ok(res)
 }
 
@@ -135,15 +278,16 @@ pub fn execute_remove_stake_hook(deps: DepsMut, info: MessageInfo, hook: Addr) -
     Ok(res)
 }
 
-pub fn execute_add_unstake_hook(deps: DepsMut, info: MessageInfo, hook: Addr) -> Result<Response, ContractError> {
+pub fn execute_add_unstake_hook(deps: DepsMut, info: MessageInfo, hook: Addr, required: bool) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     only_operator(&info, &config)?;
 
-    UNSTAKE_HOOKS.add_hook(deps.storage, hook.clone())?;
+    UNSTAKE_HOOKS.add_hook(deps.storage, hook.clone(), required)?;
 
     let res = Response::new()
         .add_attribute("action", "add_unstake_hook")
-        .add_attribute("hook", hook);
+        .add_attribute("hook", hook)
+        .add_attribute("required", required.to_string());
     Ok(res)
 }
 
@@ -160,15 +304,16 @@ pub fn execute_remove_unstake_hook(deps: DepsMut, info: MessageInfo, hook: Addr)
 }
 
 
-pub fn execute_add_withdraw_hook(deps: DepsMut, info: MessageInfo, hook: Addr) -> Result<Response, ContractError> {
+pub fn execute_add_withdraw_hook(deps: DepsMut, info: MessageInfo, hook: Addr, required: bool) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     only_operator(&info, &config)?;
 
-    WITHDRAW_HOOKS.add_hook(deps.storage, hook.clone())?;
+    WITHDRAW_HOOKS.add_hook(deps.storage, hook.clone(), required)?;
 
     let res = Response::new()
         .add_attribute("action", "add_withdraw_hook")
-        .add_attribute("hook", hook);
+        .add_attribute("hook", hook)
+        .add_attribute("required", required.to_string());
     Ok(res)
 }
 
@@ -188,18 +333,113 @@ pub fn execute_stake(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    collection: Addr,
     token_id: String,
+    lock_duration: Option<u64>,
 ) -> Result<Response, ContractError> {
     nonpayable(&info)?;
-    
+
     let mut response = Response::new();
 
     let config = CONFIG.load(deps.storage)?;
-    let mut vault_token_option = vault_tokens().may_load(deps.storage, token_id.clone())?;
+    stake_token(deps, &env, &config, &info.sender, &collection, token_id, lock_duration, false, &mut response)?;
+
+    Ok(response)
+}
+
+/// Stakes an NFT that was transferred to the vault with `SendNft`,
+/// or unstakes, withdraws or transfers the position of a receipt sent back to the vault
+pub fn execute_receive_nft(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    receive_msg: Cw721ReceiveMsg,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    // The sender is the collection of the received NFT
+    let config = CONFIG.load(deps.storage)?;
+    let collection = info.sender.clone();
+    let holder = deps.api.addr_validate(&receive_msg.sender)?;
+    let is_receipt = RECEIPT_COLLECTION.may_load(deps.storage)?.as_ref() == Some(&collection);
+
+    let mut response = Response::new();
+
+    match from_binary(&receive_msg.msg)? {
+        ReceiveNftMsg::Stake { lock_duration } => {
+            stake_token(deps, &env, &config, &holder, &collection, receive_msg.token_id, lock_duration, true, &mut response)?;
+        }
+        ReceiveNftMsg::Unstake {} if is_receipt => {
+            let (collection, token_id) = receipt_position(deps.storage, &receive_msg.token_id)?;
+            unstake_position(deps, &env, &info, &config, &collection, &token_id, &holder, true, &mut response)?;
+        }
+        ReceiveNftMsg::Withdraw {} if is_receipt => {
+            let (collection, token_id) = receipt_position(deps.storage, &receive_msg.token_id)?;
+            let vault_token = withdraw_token(deps.branch(), &env, &config, &holder, &collection, &token_id, &mut response)?;
+
+            let msg = HookMsg::new(&vault_token, &env.block.time, config.unstake_period);
+            let submsgs = prepare_hooks(deps.storage, &WITHDRAW_HOOKS, HookAction::Withdraw, &[msg])?;
+            response.messages.extend(submsgs);
+        }
+        ReceiveNftMsg::TransferPosition { recipient } if is_receipt => {
+            let recipient = deps.api.addr_validate(&recipient)?;
+            let key = receipt_position(deps.storage, &receive_msg.token_id)?;
+            transfer_position(deps.storage, &env, &config, key.clone(), &recipient, &mut response)?;
+            return_receipt(deps.storage, &key.0, &key.1, &recipient, &mut response)?;
+        }
+        _ => {
+            return Err(ContractError::InvalidInput("only receipts can be sent to unstake, withdraw or transfer".to_string()));
+        }
+    }
+
+    Ok(response)
+}
+
+pub fn execute_stake_many(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collection: Addr,
+    token_ids: Vec<String>,
+    lock_duration: Option<u64>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    if token_ids.is_empty() {
+        return Err(ContractError::InvalidInput("token_ids must not be empty".to_string()));
+    }
+
+    let mut response = Response::new();
+
+    let config = CONFIG.load(deps.storage)?;
+    for token_id in token_ids {
+        stake_token(deps.branch(), &env, &config, &info.sender, &collection, token_id, lock_duration, false, &mut response)?;
+    }
+
+    Ok(response)
+}
+
+/// Stakes or re-stakes an NFT for its owner, pulling the NFT into the vault unless it was received
+fn stake_token(
+    deps: DepsMut,
+    env: &Env,
+    config: &Config,
+    owner: &Addr,
+    collection: &Addr,
+    token_id: String,
+    lock_duration: Option<u64>,
+    received: bool,
+    response: &mut Response,
+) -> Result<(), ContractError> {
+    only_approved_collection(collection, config)?;
+    let (lock_end_timestamp, reward_weight) = select_lock_tier(config, lock_duration, &env.block.time)?;
+
+    let key = vault_token_key(collection, &token_id);
+    let mut vault_token_option = vault_tokens().may_load(deps.storage, key.clone())?;
 
     if let Some(_vault_token) = &mut vault_token_option {
-        // Only the original owner can re-stake
-        if _vault_token.owner != info.sender {
+        // Only the position owner can re-stake
+        if &position_owner(deps.as_ref(), _vault_token)? != owner {
             return Err(ContractError::Unauthorized("Only owner can restake".to_string()));
         }
         // Allow users to re-stake tokens that are either unstaking or transferrable
@@ -207,184 +447,1044 @@ pub fn execute_stake(
         if status == VaultTokenStatus::Staked {
             return Err(ContractError::InvalidStatus(status.to_string()));
         }
+        update_unstaked_balance(deps.storage, &_vault_token.owner, &_vault_token.unstake_timestamp.unwrap(), false)?;
+        _vault_token.owner = owner.clone();
         _vault_token.stake_timestamp = env.block.time;
         _vault_token.unstake_timestamp = None;
+        _vault_token.lock_end_timestamp = lock_end_timestamp;
     } else {
-        only_owner(deps.as_ref(), &info, &config.cw721_address, &token_id)?;
+        // Received NFTs are already held by the vault
+        if !received {
+            only_owner(deps.as_ref(), owner, collection, &token_id)?;
 
-        // Allow users to stake tokens
-        transfer_nft(&token_id, &env.contract.address, &config.cw721_address, &mut response)?;
+            // Allow users to stake tokens
+            transfer_nft(&token_id, &env.contract.address, collection, response)?;
+        }
+        mint_receipt(deps.storage, collection, &token_id, owner, response)?;
 
         vault_token_option = Some(VaultToken {
+            collection: collection.clone(),
             token_id: token_id.clone(),
-            owner: info.sender.clone(),
+            owner: owner.clone(),
             stake_timestamp: env.block.time,
-            unstake_timestamp: None
+            unstake_timestamp: None,
+            lock_end_timestamp,
         });
     }
 
     let vault_token = vault_token_option.unwrap();
-    vault_tokens().save(deps.storage, token_id.clone(), &vault_token)?;
-
-    let submsgs = STAKE_HOOKS.prepare_hooks(deps.storage, |h| {
-        let msg = HookMsg::new(
-            &config.cw721_address,
-            &vault_token,
-            &env.block.time,
-            config.unstake_period,
-        );
-        let execute = WasmMsg::Execute {
-            contract_addr: h.to_string(),
-            msg: msg.into_binary(HookAction::Stake)?,
-            funds: vec![],
-        };
-        Ok(SubMsg::reply_on_error(execute, HookReply::Stake as u64))
-    })?;
+    vault_tokens().save(deps.storage, key.clone(), &vault_token)?;
+    update_token_rewards(deps.storage, &env.block.time, key, Some(reward_weight))?;
+    update_staked_balance(deps.storage, env.block.height, owner, true)?;
+
+    let msg = HookMsg::new(&vault_token, &env.block.time, config.unstake_period);
+    let submsgs = prepare_hooks(deps.storage, &STAKE_HOOKS, HookAction::Stake, &[msg])?;
 
     let event = Event::new("stake-token")
-        .add_attribute("cw721_address", config.cw721_address.to_string())
+        .add_attribute("cw721_address", vault_token.collection.to_string())
         .add_attribute("token_id", &token_id.to_string());
 
-    Ok(response.add_submessages(submsgs).add_event(event))
+    response.messages.extend(submsgs);
+    response.events.push(event);
+
+    Ok(())
 }
 
 pub fn execute_unstake(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    collection: Addr,
     token_id: String,
 ) -> Result<Response, ContractError> {
-    nonpayable(&info)?;
-
-    let vault_token = vault_tokens().load(deps.storage, token_id.clone())?;
+    let vault_token = vault_tokens().load(deps.storage, vault_token_key(&collection, &token_id))?;
 
-    // Only original owner can unstake
-    if vault_token.owner != info.sender {
+    // Only the position owner can unstake
+    if position_owner(deps.as_ref(), &vault_token)? != info.sender {
         return Err(ContractError::Unauthorized("Only owner can unstake".to_string()));
     }
 
     let config = CONFIG.load(deps.storage)?;
-    let vault_token = vault_tokens().update(
-        deps.storage,
-        token_id.clone(),
-        |vault_token| -> Result<VaultToken, ContractError> {
-            let mut updated_vault_token = vault_token.unwrap();
-            let status = updated_vault_token.get_status(&env.block.time, config.unstake_period);
-            if status != VaultTokenStatus::Staked {
-                return Err(ContractError::InvalidStatus(status.to_string()));
+    let mut response = Response::new();
+    unstake_position(deps, &env, &info, &config, &collection, &token_id, &info.sender, false, &mut response)?;
+
+    Ok(response)
+}
+
+/// Unstakes a position for its owner, who sent its receipt to the vault when `received`.
+/// The vault burns the receipt of a token burned by the early unstake penalty and returns it otherwise
+fn unstake_position(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    config: &Config,
+    collection: &Addr,
+    token_id: &str,
+    owner: &Addr,
+    received: bool,
+    response: &mut Response,
+) -> Result<(), ContractError> {
+    let key = vault_token_key(collection, token_id);
+    let vault_token = vault_tokens().load(deps.storage, key.clone())?;
+    let is_liquid = RECEIPT_COLLECTION.may_load(deps.storage)?.is_some();
+
+    // Locked tokens may only be unstaked early by paying the penalty
+    let is_locked = vault_token.lock_end_timestamp.map_or(false, |ts| env.block.time < ts);
+    let penalty = match (is_locked, &config.early_unstake_penalty) {
+        (false, _) => None,
+        (true, None) => {
+            return Err(ContractError::InvalidStatus(format!(
+                "Locked until {}",
+                vault_token.lock_end_timestamp.unwrap().seconds()
+            )));
+        }
+        (true, Some(_penalty)) => Some(_penalty.clone()),
+    };
+    match &penalty {
+        Some(EarlyUnstakePenalty::Fee { fee, recipient }) => {
+            let paid = must_pay(info, &fee.denom)?;
+            if paid != fee.amount {
+                return Err(ContractError::InvalidInput(format!("early unstake fee must be {}", fee)));
             }
-            updated_vault_token.unstake_timestamp = Some(env.block.time);
-            Ok(updated_vault_token)
-        }
-    )?;
-
-    let submsgs = UNSTAKE_HOOKS.prepare_hooks(deps.storage, |h| {
-        let msg = HookMsg::new(
-            &config.cw721_address,
-            &vault_token,
-            &env.block.time,
-            config.unstake_period,
-        );
-        let execute = WasmMsg::Execute {
-            contract_addr: h.to_string(),
-            msg: msg.into_binary(HookAction::Unstake)?,
-            funds: vec![],
-        };
-        Ok(SubMsg::reply_on_error(execute, HookReply::Unstake as u64))
-    })?;
+            response.messages.push(SubMsg::new(BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: vec![fee.clone()],
+            }));
+        }
+        Some(EarlyUnstakePenalty::Burn) if is_liquid && !received => {
+            return Err(ContractError::InvalidInput("send the receipt to the vault to unstake".to_string()));
+        }
+        _ => nonpayable(info)?,
+    }
+
+    let vault_token = unstake_token(deps.storage, env, config, key.clone(), owner)?;
+
+    match &penalty {
+        Some(EarlyUnstakePenalty::Burn) => {
+            // A burned NFT leaves the vault immediately, with its unclaimed rewards
+            burn_nft(&token_id.to_string(), collection, response)?;
+            burn_receipt(deps.storage, collection, token_id, response)?;
+            vault_tokens().remove(deps.storage, key.clone())?;
+            FORCE_ACTIONS.remove(deps.storage, key.clone());
+            update_unstaked_balance(deps.storage, &vault_token.owner, &env.block.time, false)?;
+            payout_token_rewards(deps.storage, key, &vault_token.owner, response)?;
+        }
+        Some(EarlyUnstakePenalty::ForfeitRewards) => {
+            forfeit_token_rewards(deps.storage, key, response)?;
+        }
+        _ => {}
+    }
+    if received && penalty != Some(EarlyUnstakePenalty::Burn) {
+        return_receipt(deps.storage, collection, token_id, owner, response)?;
+    }
+
+    let msg = HookMsg::new(&vault_token, &env.block.time, config.unstake_period);
+    let mut submsgs = prepare_hooks(deps.storage, &UNSTAKE_HOOKS, HookAction::Unstake, &[msg.clone()])?;
+    if penalty == Some(EarlyUnstakePenalty::Burn) {
+        submsgs.extend(prepare_hooks(deps.storage, &WITHDRAW_HOOKS, HookAction::Withdraw, &[msg])?);
+    }
 
     let event = Event::new("unstake-token")
-        .add_attribute("cw721_address", config.cw721_address.to_string())
-        .add_attribute("token_id", &token_id.to_string());
+        .add_attribute("cw721_address", vault_token.collection.to_string())
+        .add_attribute("token_id", &token_id.to_string())
+        .add_attribute("early", is_locked.to_string());
 
-    let response = Response::new();
-    Ok(response.add_submessages(submsgs).add_event(event))
+    response.messages.extend(submsgs);
+    response.events.push(event);
+
+    Ok(())
+}
+
+/// Records a new owner of a position, moving its voting power or unstaked balance to them
+fn transfer_position(
+    storage: &mut dyn Storage,
+    env: &Env,
+    config: &Config,
+    key: VaultTokenKey,
+    recipient: &Addr,
+    response: &mut Response,
+) -> Result<(), ContractError> {
+    let mut vault_token = vault_tokens().load(storage, key.clone())?;
+    let prev_owner = vault_token.owner.clone();
+
+    match vault_token.unstake_timestamp {
+        None => {
+            update_staked_balance(storage, env.block.height, &prev_owner, false)?;
+            update_staked_balance(storage, env.block.height, recipient, true)?;
+        }
+        Some(_unstake_timestamp) => {
+            update_unstaked_balance(storage, &prev_owner, &_unstake_timestamp, false)?;
+            update_unstaked_balance(storage, recipient, &_unstake_timestamp, true)?;
+        }
+    }
+    vault_token.owner = recipient.clone();
+    vault_tokens().save(storage, key, &vault_token)?;
+
+    let event = Event::new("transfer-position")
+        .add_attribute("cw721_address", vault_token.collection.to_string())
+        .add_attribute("token_id", vault_token.token_id.to_string())
+        .add_attribute("from", prev_owner.to_string())
+        .add_attribute("to", recipient.to_string())
+        .add_attribute("status", vault_token.get_status(&env.block.time, config.unstake_period).to_string());
+    response.events.push(event);
+
+    Ok(())
+}
+
+/// Moves a staked token to the Unstaking status, recording the owner of the position
+fn unstake_token(
+    storage: &mut dyn Storage,
+    env: &Env,
+    config: &Config,
+    key: VaultTokenKey,
+    owner: &Addr,
+) -> Result<VaultToken, ContractError> {
+    let mut vault_token = vault_tokens().load(storage, key.clone())?;
+    let status = vault_token.get_status(&env.block.time, config.unstake_period);
+    if status != VaultTokenStatus::Staked {
+        return Err(ContractError::InvalidStatus(status.to_string()));
+    }
+
+    let staker = vault_token.owner.clone();
+    vault_token.owner = owner.clone();
+    vault_token.unstake_timestamp = Some(env.block.time);
+    vault_tokens().save(storage, key.clone(), &vault_token)?;
+
+    update_token_rewards(storage, &env.block.time, key, None)?;
+    update_staked_balance(storage, env.block.height, &staker, false)?;
+    update_unstaked_balance(storage, owner, &env.block.time, true)?;
+
+    Ok(vault_token)
 }
 
 pub fn execute_withdraw(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    collection: Addr,
     token_id: String,
 ) -> Result<Response, ContractError> {
     nonpayable(&info)?;
 
-    let vault_token = vault_tokens().load(deps.storage, token_id.clone())?;
+    if RECEIPT_COLLECTION.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::InvalidInput("send the receipt to the vault to withdraw".to_string()));
+    }
 
-    // Only original owner can withdraw
+    // Only the owner can withdraw
+    let vault_token = vault_tokens().load(deps.storage, vault_token_key(&collection, &token_id))?;
     if vault_token.owner != info.sender {
         return Err(ContractError::Unauthorized("Only owner can withdraw".to_string()));
     }
 
     let config = CONFIG.load(deps.storage)?;
+    let mut response = Response::new();
+    let vault_token = withdraw_token(deps.branch(), &env, &config, &info.sender, &collection, &token_id, &mut response)?;
+
+    let msg = HookMsg::new(&vault_token, &env.block.time, config.unstake_period);
+    let submsgs = prepare_hooks(deps.storage, &WITHDRAW_HOOKS, HookAction::Withdraw, &[msg])?;
+
+    Ok(response.add_submessages(submsgs))
+}
+
+pub fn execute_unstake_many(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collection: Addr,
+    token_ids: Vec<String>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    if token_ids.is_empty() {
+        return Err(ContractError::InvalidInput("token_ids must not be empty".to_string()));
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let mut response = Response::new();
+    let mut msgs = vec![];
+    for token_id in token_ids {
+        let key = vault_token_key(&collection, &token_id);
+        let vault_token = vault_tokens().load(deps.storage, key.clone())?;
+
+        // Only the position owner can unstake
+        if position_owner(deps.as_ref(), &vault_token)? != info.sender {
+            return Err(ContractError::Unauthorized("Only owner can unstake".to_string()));
+        }
+        // Early unstake penalties only apply to single unstakes
+        if let Some(_lock_end_timestamp) = vault_token.lock_end_timestamp {
+            if env.block.time < _lock_end_timestamp {
+                return Err(ContractError::InvalidStatus(format!("Locked until {}", _lock_end_timestamp.seconds())));
+            }
+        }
+
+        let vault_token = unstake_token(deps.storage, &env, &config, key, &info.sender)?;
+        msgs.push(HookMsg::new(&vault_token, &env.block.time, config.unstake_period));
+
+        let event = Event::new("unstake-token")
+            .add_attribute("cw721_address", vault_token.collection.to_string())
+            .add_attribute("token_id", &token_id.to_string())
+            .add_attribute("early", false.to_string());
+        response.events.push(event);
+    }
+
+    let submsgs = prepare_hooks(deps.storage, &UNSTAKE_HOOKS, HookAction::Unstake, &msgs)?;
+    Ok(response.add_submessages(submsgs))
+}
+
+pub fn execute_withdraw_many(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collection: Addr,
+    token_ids: Vec<String>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    if token_ids.is_empty() {
+        return Err(ContractError::InvalidInput("token_ids must not be empty".to_string()));
+    }
+    if RECEIPT_COLLECTION.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::InvalidInput("send the receipt to the vault to withdraw".to_string()));
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let mut response = Response::new();
+    let mut msgs = vec![];
+    for token_id in token_ids {
+        // Only the owner can withdraw
+        let vault_token = vault_tokens().load(deps.storage, vault_token_key(&collection, &token_id))?;
+        if vault_token.owner != info.sender {
+            return Err(ContractError::Unauthorized("Only owner can withdraw".to_string()));
+        }
+
+        let vault_token = withdraw_token(deps.branch(), &env, &config, &info.sender, &collection, &token_id, &mut response)?;
+        msgs.push(HookMsg::new(&vault_token, &env.block.time, config.unstake_period));
+    }
+
+    let submsgs = prepare_hooks(deps.storage, &WITHDRAW_HOOKS, HookAction::Withdraw, &msgs)?;
+    Ok(response.add_submessages(submsgs))
+}
+
+/// Anyone can withdraw Transferrable tokens to their owners in order of unstake time.
+/// Positions of liquid staking vaults are withdrawn by sending their receipt, so those vaults are excluded
+pub fn execute_withdraw_all_transferrable(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    if RECEIPT_COLLECTION.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::InvalidInput("receipt holders must withdraw their own tokens".to_string()));
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let limit = limit.unwrap_or(DEFAULT_WITHDRAW_LIMIT).min(MAX_WITHDRAW_LIMIT) as usize;
+
+    // Staked tokens are indexed with an unstake time of 0, and tokens become
+    // Transferrable in order of unstake time, so stop at the first one that is not
+    let start = Some(Bound::inclusive((1u64, vault_token_key(&Addr::unchecked(""), ""))));
+    let transferrable_tokens = vault_tokens()
+        .idx
+        .unstake_timestamp
+        .range(deps.storage, start, None, Order::Ascending)
+        .take_while(|item| match item {
+            Ok((_, vault_token)) => {
+                vault_token.get_status(&env.block.time, config.unstake_period) == VaultTokenStatus::Transferrable
+            }
+            Err(_) => true,
+        })
+        .take(limit)
+        .map(|res| res.map(|item| item.1))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut response = Response::new();
+    let mut msgs = vec![];
+    for vault_token in transferrable_tokens {
+        let vault_token = withdraw_token(
+            deps.branch(),
+            &env,
+            &config,
+            &vault_token.owner,
+            &vault_token.collection,
+            &vault_token.token_id,
+            &mut response,
+        )?;
+        msgs.push(HookMsg::new(&vault_token, &env.block.time, config.unstake_period));
+    }
+
+    let event = Event::new("withdraw-all-transferrable")
+        .add_attribute("caller", info.sender.to_string())
+        .add_attribute("num_withdrawn", msgs.len().to_string());
+    response.events.push(event);
+
+    if msgs.is_empty() {
+        return Ok(response);
+    }
+    let submsgs = prepare_hooks(deps.storage, &WITHDRAW_HOOKS, HookAction::Withdraw, &msgs)?;
+    Ok(response.add_submessages(submsgs))
+}
+
+/// Withdraws a Transferrable token to the owner of its position, callers check the owner
+fn withdraw_token(
+    deps: DepsMut,
+    env: &Env,
+    config: &Config,
+    owner: &Addr,
+    collection: &Addr,
+    token_id: &str,
+    response: &mut Response,
+) -> Result<VaultToken, ContractError> {
+    let key = vault_token_key(collection, token_id);
+    let mut vault_token = vault_tokens().load(deps.storage, key.clone())?;
+
     let status = vault_token.get_status(&env.block.time, config.unstake_period);
     if status != VaultTokenStatus::Transferrable {
         return Err(ContractError::InvalidStatus(status.to_string()));
     }
+    update_unstaked_balance(deps.storage, &vault_token.owner, &vault_token.unstake_timestamp.unwrap(), false)?;
+    vault_token.owner = owner.clone();
 
-    let mut response = Response::new();
-    transfer_nft(&token_id, &vault_token.owner, &config.cw721_address, &mut response)?;
-    vault_tokens().remove(deps.storage, token_id.clone())?;
-
-    let submsgs = WITHDRAW_HOOKS.prepare_hooks(deps.storage, |h| {
-        let msg = HookMsg::new(
-            &config.cw721_address,
-            &vault_token,
-            &env.block.time,
-            config.unstake_period,
-        );
-        let execute = WasmMsg::Execute {
-            contract_addr: h.to_string(),
-            msg: msg.into_binary(HookAction::Withdraw)?,
-            funds: vec![],
-        };
-        Ok(SubMsg::reply_on_error(execute, HookReply::Withdraw as u64))
-    })?;
+    transfer_nft(&token_id.to_string(), &vault_token.owner, collection, response)?;
+    burn_receipt(deps.storage, collection, token_id, response)?;
+    vault_tokens().remove(deps.storage, key.clone())?;
+    FORCE_ACTIONS.remove(deps.storage, key.clone());
+
+    // Unclaimed rewards are paid out with the NFT
+    payout_token_rewards(deps.storage, key, &vault_token.owner, response)?;
 
     let event = Event::new("withdraw-token")
-        .add_attribute("cw721_address", config.cw721_address.to_string())
-        .add_attribute("token_id", &token_id.to_string());
+        .add_attribute("cw721_address", vault_token.collection.to_string())
+        .add_attribute("token_id", token_id.to_string());
+    response.events.push(event);
+
+    Ok(vault_token)
+}
+
+/// An operator may unstake a token on behalf of its owner, ignoring its lock
+pub fn execute_force_unstake(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collection: Addr,
+    token_id: String,
+    reason: String,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    only_operator(&info, &config)?;
+
+    let key = vault_token_key(&collection, &token_id);
+    let vault_token = vault_tokens().load(deps.storage, key.clone())?;
+    let status = vault_token.get_status(&env.block.time, config.unstake_period);
+    if status != VaultTokenStatus::Staked {
+        return Err(ContractError::InvalidStatus(status.to_string()));
+    }
+
+    let mut response = Response::new();
+    if !check_force_timelock(deps.storage, &env, &config, key.clone(), ForceAction::Unstake, &reason, &mut response)? {
+        return Ok(response);
+    }
+
+    let owner = position_owner(deps.as_ref(), &vault_token)?;
+    let vault_token = unstake_token(deps.storage, &env, &config, key, &owner)?;
+
+    let msg = HookMsg::new(&vault_token, &env.block.time, config.unstake_period);
+    let submsgs = prepare_hooks(deps.storage, &UNSTAKE_HOOKS, HookAction::Unstake, &[msg])?;
+
+    let event = Event::new("force-unstake-token")
+        .add_attribute("cw721_address", vault_token.collection.to_string())
+        .add_attribute("token_id", &token_id.to_string())
+        .add_attribute("operator", info.sender.to_string())
+        .add_attribute("reason", reason);
 
     Ok(response.add_submessages(submsgs).add_event(event))
 }
 
-enum HookReply {
-    Stake = 1,
-    Unstake,
-    Withdraw,
+/// An operator may withdraw a token to a recipient, slashing its unclaimed rewards
+pub fn execute_force_withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collection: Addr,
+    token_id: String,
+    recipient: Addr,
+    reason: String,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    only_operator(&info, &config)?;
+
+    let key = vault_token_key(&collection, &token_id);
+    let mut vault_token = vault_tokens().load(deps.storage, key.clone())?;
+
+    let mut response = Response::new();
+    let action = ForceAction::Withdraw { recipient: recipient.clone() };
+    if !check_force_timelock(deps.storage, &env, &config, key.clone(), action, &reason, &mut response)? {
+        return Ok(response);
+    }
+
+    // A staked token is unstaked first so that its rewards and voting power are settled
+    let status = vault_token.get_status(&env.block.time, config.unstake_period);
+    if status == VaultTokenStatus::Staked {
+        let owner = position_owner(deps.as_ref(), &vault_token)?;
+        vault_token = unstake_token(deps.storage, &env, &config, key.clone(), &owner)?;
+
+        let msg = HookMsg::new(&vault_token, &env.block.time, config.unstake_period);
+        let submsgs = prepare_hooks(deps.storage, &UNSTAKE_HOOKS, HookAction::Unstake, &[msg])?;
+        response.messages.extend(submsgs);
+    }
+
+    transfer_nft(&token_id, &recipient, &collection, &mut response)?;
+    void_receipt(deps.storage, &collection, &token_id, &mut response)?;
+    vault_tokens().remove(deps.storage, key.clone())?;
+    update_unstaked_balance(deps.storage, &vault_token.owner, &vault_token.unstake_timestamp.unwrap(), false)?;
+    forfeit_token_rewards(deps.storage, key.clone(), &mut response)?;
+    TOKEN_REWARDS.remove(deps.storage, key);
+
+    let msg = HookMsg::new(&vault_token, &env.block.time, config.unstake_period);
+    let submsgs = prepare_hooks(deps.storage, &WITHDRAW_HOOKS, HookAction::Withdraw, &[msg])?;
+
+    let event = Event::new("force-withdraw-token")
+        .add_attribute("cw721_address", vault_token.collection.to_string())
+        .add_attribute("token_id", &token_id.to_string())
+        .add_attribute("recipient", recipient.to_string())
+        .add_attribute("operator", info.sender.to_string())
+        .add_attribute("reason", reason);
+
+    Ok(response.add_submessages(submsgs).add_event(event))
 }
 
-impl From<u64> for HookReply {
-    fn from(item: u64) -> Self {
-        match item {
-            1 => HookReply::Stake,
-            2 => HookReply::Unstake,
-            3 => HookReply::Withdraw,
-            _ => panic!("invalid reply type"),
+/// An operator may cancel a force action awaiting the force timelock
+pub fn execute_cancel_force_action(
+    deps: DepsMut,
+    info: MessageInfo,
+    collection: Addr,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    only_operator(&info, &config)?;
+
+    let key = vault_token_key(&collection, &token_id);
+    if !FORCE_ACTIONS.has(deps.storage, key.clone()) {
+        return Err(ContractError::InvalidInput("no force action is scheduled".to_string()));
+    }
+    FORCE_ACTIONS.remove(deps.storage, key);
+
+    Ok(Response::new()
+        .add_attribute("action", "cancel_force_action")
+        .add_attribute("cw721_address", collection.to_string())
+        .add_attribute("token_id", token_id)
+    )
+}
+
+/// Returns whether a force action can be executed now. When the vault has a force timelock,
+/// the action is scheduled on the first call and executed by a call once the timelock has passed
+fn check_force_timelock(
+    storage: &mut dyn Storage,
+    env: &Env,
+    config: &Config,
+    key: VaultTokenKey,
+    action: ForceAction,
+    reason: &str,
+    response: &mut Response,
+) -> Result<bool, ContractError> {
+    if reason.trim().is_empty() {
+        return Err(ContractError::InvalidInput("reason must not be empty".to_string()));
+    }
+    let force_timelock = match config.force_timelock {
+        Some(_force_timelock) => _force_timelock,
+        None => return Ok(true),
+    };
+
+    match FORCE_ACTIONS.may_load(storage, key.clone())? {
+        Some(_scheduled) if _scheduled.action == action => {
+            if env.block.time < _scheduled.executable_at {
+                return Err(ContractError::InvalidStatus(format!(
+                    "Force action timelocked until {}",
+                    _scheduled.executable_at.seconds()
+                )));
+            }
+            FORCE_ACTIONS.remove(storage, key);
+            Ok(true)
+        }
+        _ => {
+            let scheduled = ScheduledForceAction {
+                action,
+                reason: reason.to_string(),
+                executable_at: env.block.time.plus_seconds(force_timelock),
+            };
+            FORCE_ACTIONS.save(storage, key.clone(), &scheduled)?;
+
+            let event = Event::new("schedule-force-action")
+                .add_attribute("cw721_address", key.0.to_string())
+                .add_attribute("token_id", key.1)
+                .add_attribute("reason", reason.to_string())
+                .add_attribute("executable_at", scheduled.executable_at.seconds().to_string());
+            response.events.push(event);
+            Ok(false)
         }
     }
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
-    match HookReply::from(msg.id) {
-        HookReply::Stake => {
-            let res = Response::new()
-                .add_attribute("action", "stake-hook-failed")
-                .add_attribute("error", msg.result.unwrap_err());
-            Ok(res)
+/// Settles the rewards of a token entering the Staked status with a reward weight,
+/// or leaving the Staked status
+fn update_token_rewards(
+    storage: &mut dyn Storage,
+    now: &Timestamp,
+    key: VaultTokenKey,
+    stake_weight: Option<u64>,
+) -> StdResult<()> {
+    let reward_config = REWARD_CONFIG.may_load(storage)?;
+    let mut reward_state = REWARD_STATE.load(storage)?;
+    accrue_rewards(&mut reward_state, reward_config.as_ref(), now)?;
+
+    let was_staked = stake_weight.is_none();
+    let mut token_reward = settle_token_reward(storage, &reward_state, key.clone(), was_staked)?;
+
+    match stake_weight {
+        Some(weight) => {
+            token_reward.weight = weight;
+            reward_state.num_staked += 1;
+            reward_state.staked_weight += weight;
+        }
+        None => {
+            reward_state.num_staked -= 1;
+            reward_state.staked_weight -= token_reward.weight;
+        }
+    }
+    TOKEN_REWARDS.save(storage, key, &token_reward)?;
+    REWARD_STATE.save(storage, &reward_state)
+}
+
+/// Updates the voting power snapshots of a staker's token entering or leaving the Staked status
+fn update_staked_balance(
+    storage: &mut dyn Storage,
+    height: u64,
+    staker: &Addr,
+    is_staked: bool,
+) -> StdResult<()> {
+    let balance = STAKED_BALANCES.may_load(storage, staker)?.unwrap_or_default();
+    let total = TOTAL_STAKED.may_load(storage)?.unwrap_or_default();
+    let num_stakers = NUM_STAKERS.may_load(storage)?.unwrap_or_default();
+    if is_staked {
+        if balance == 0 {
+            NUM_STAKERS.save(storage, &(num_stakers + 1))?;
+        }
+        STAKED_BALANCES.save(storage, staker, &(balance + 1), height)?;
+        TOTAL_STAKED.save(storage, &(total + 1), height)
+    } else {
+        if balance == 1 {
+            NUM_STAKERS.save(storage, &(num_stakers - 1))?;
+        }
+        STAKED_BALANCES.save(storage, staker, &(balance - 1), height)?;
+        TOTAL_STAKED.save(storage, &(total - 1), height)
+    }
+}
+
+/// Updates the counters of an owner's token entering or leaving the vault's unstaked tokens
+fn update_unstaked_balance(
+    storage: &mut dyn Storage,
+    owner: &Addr,
+    unstake_timestamp: &Timestamp,
+    is_unstaked: bool,
+) -> StdResult<()> {
+    let unstake_time = unstake_timestamp.seconds();
+    let balance = UNSTAKED_BALANCES.may_load(storage, owner)?.unwrap_or_default();
+    let total = TOTAL_UNSTAKED.may_load(storage)?.unwrap_or_default();
+    let owner_unstakes = OWNER_UNSTAKES.may_load(storage, (owner, unstake_time))?.unwrap_or_default();
+    let unstakes = UNSTAKES.may_load(storage, unstake_time)?.unwrap_or_default();
+    if is_unstaked {
+        UNSTAKED_BALANCES.save(storage, owner, &(balance + 1))?;
+        TOTAL_UNSTAKED.save(storage, &(total + 1))?;
+        OWNER_UNSTAKES.save(storage, (owner, unstake_time), &(owner_unstakes + 1))?;
+        UNSTAKES.save(storage, unstake_time, &(unstakes + 1))
+    } else {
+        UNSTAKED_BALANCES.save(storage, owner, &(balance - 1))?;
+        TOTAL_UNSTAKED.save(storage, &(total - 1))?;
+        if owner_unstakes == 1 {
+            OWNER_UNSTAKES.remove(storage, (owner, unstake_time));
+        } else {
+            OWNER_UNSTAKES.save(storage, (owner, unstake_time), &(owner_unstakes - 1))?;
+        }
+        if unstakes == 1 {
+            UNSTAKES.remove(storage, unstake_time);
+        } else {
+            UNSTAKES.save(storage, unstake_time, &(unstakes - 1))?;
         }
-        HookReply::Unstake => {
+        Ok(())
+    }
+}
+
+/// Returns the unclaimed rewards of a token to the reward pool
+fn forfeit_token_rewards(
+    storage: &mut dyn Storage,
+    key: VaultTokenKey,
+    response: &mut Response,
+) -> StdResult<()> {
+    let mut token_reward = TOKEN_REWARDS.load(storage, key.clone())?;
+    let mut reward_state = REWARD_STATE.load(storage)?;
+    reward_state.unallocated += token_reward.accrued;
+    REWARD_STATE.save(storage, &reward_state)?;
+
+    let event = Event::new("forfeit-rewards")
+        .add_attribute("cw721_address", key.0.to_string())
+        .add_attribute("token_id", key.1.to_string())
+        .add_attribute("amount", token_reward.accrued.to_string());
+    response.events.push(event);
+
+    token_reward.accrued = Uint128::zero();
+    TOKEN_REWARDS.save(storage, key, &token_reward)
+}
+
+/// Pays out the unclaimed rewards of a token leaving the vault
+fn payout_token_rewards(
+    storage: &mut dyn Storage,
+    key: VaultTokenKey,
+    owner: &Addr,
+    response: &mut Response,
+) -> StdResult<()> {
+    if let Some(token_reward) = TOKEN_REWARDS.may_load(storage, key.clone())? {
+        if !token_reward.accrued.is_zero() {
+            let reward_config = REWARD_CONFIG.load(storage)?;
+            transfer_reward(&reward_config.denom, token_reward.accrued, owner, response)?;
+        }
+        TOKEN_REWARDS.remove(storage, key);
+    }
+    Ok(())
+}
+
+/// An operator may update the lock tiers and early unstake penalty,
+/// tokens that are already locked keep their lock end
+pub fn execute_update_lock_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    lock_tiers: Vec<LockTier>,
+    early_unstake_penalty: Option<EarlyUnstakePenalty>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let mut config = CONFIG.load(deps.storage)?;
+    only_operator(&info, &config)?;
+
+    let early_unstake_penalty = match early_unstake_penalty {
+        Some(EarlyUnstakePenalty::Fee { fee, recipient }) => Some(EarlyUnstakePenalty::Fee {
+            fee,
+            recipient: deps.api.addr_validate(recipient.as_str())?,
+        }),
+        _penalty => _penalty,
+    };
+    validate_lock_config(&lock_tiers, &early_unstake_penalty)?;
+
+    config.lock_tiers = lock_tiers;
+    config.early_unstake_penalty = early_unstake_penalty;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_lock_config")
+        .add_attribute("lock_tiers", config.lock_tiers.iter().map(|t| t.duration.to_string()).collect::<Vec<String>>().join(","))
+    )
+}
+
+/// An operator may set the reward token and emission rate
+pub fn execute_configure_rewards(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denom: Denom,
+    emission_rate: Uint128,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    only_operator(&info, &config)?;
+
+    let denom = match denom {
+        Denom::Cw20(_address) => Denom::Cw20(deps.api.addr_validate(_address.as_str())?),
+        _denom => _denom,
+    };
+
+    let prev_reward_config = REWARD_CONFIG.may_load(deps.storage)?;
+    if let Some(_prev_reward_config) = &prev_reward_config {
+        if _prev_reward_config.denom != denom {
+            return Err(ContractError::InvalidInput("reward denom cannot be changed".to_string()));
+        }
+    }
+
+    // Rewards earned at the previous emission rate are settled first
+    let mut reward_state = REWARD_STATE.load(deps.storage)?;
+    accrue_rewards(&mut reward_state, prev_reward_config.as_ref(), &env.block.time)?;
+    REWARD_STATE.save(deps.storage, &reward_state)?;
+
+    let reward_config = RewardConfig { denom, emission_rate };
+    REWARD_CONFIG.save(deps.storage, &reward_config)?;
+
+    let denom_attr = match &reward_config.denom {
+        Denom::Native(_denom) => _denom.to_string(),
+        Denom::Cw20(_address) => _address.to_string(),
+    };
+    Ok(Response::new()
+        .add_attribute("action", "configure_rewards")
+        .add_attribute("denom", denom_attr)
+        .add_attribute("emission_rate", reward_config.emission_rate.to_string())
+    )
+}
+
+/// An operator may fund the rewards with the native reward token
+pub fn execute_fund_rewards(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    only_operator(&info, &config)?;
+
+    let reward_config = load_reward_config(deps.storage)?;
+    let amount = match &reward_config.denom {
+        Denom::Native(_denom) => must_pay(&info, _denom)?,
+        Denom::Cw20(_) => {
+            return Err(ContractError::InvalidInput("cw20 rewards are funded with Send".to_string()));
+        }
+    };
+
+    fund_rewards(deps.storage, &env.block.time, &reward_config, amount)
+}
+
+/// An operator may fund the rewards by sending the cw20 reward token
+pub fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    receive_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let reward_config = load_reward_config(deps.storage)?;
+    if reward_config.denom != Denom::Cw20(info.sender.clone()) {
+        return Err(ContractError::Unauthorized("Only the reward token can be received".to_string()));
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let sender = deps.api.addr_validate(&receive_msg.sender)?;
+    if !config.operators.iter().any(|a| a == &sender) {
+        return Err(ContractError::Unauthorized(String::from("only an operator can call this function")));
+    }
+
+    match from_binary(&receive_msg.msg)? {
+        ReceiveMsg::FundRewards {} => {
+            fund_rewards(deps.storage, &env.block.time, &reward_config, receive_msg.amount)
+        }
+    }
+}
+
+fn load_reward_config(storage: &dyn Storage) -> Result<RewardConfig, ContractError> {
+    REWARD_CONFIG
+        .may_load(storage)?
+        .ok_or_else(|| ContractError::InvalidInput("rewards are not configured".to_string()))
+}
+
+fn fund_rewards(
+    storage: &mut dyn Storage,
+    now: &Timestamp,
+    reward_config: &RewardConfig,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    // Funds only pay for rewards earned from now on
+    let mut reward_state = REWARD_STATE.load(storage)?;
+    accrue_rewards(&mut reward_state, Some(reward_config), now)?;
+    reward_state.unallocated += amount;
+    REWARD_STATE.save(storage, &reward_state)?;
+
+    let event = Event::new("fund-rewards")
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("unallocated", reward_state.unallocated.to_string());
+
+    Ok(Response::new().add_event(event))
+}
+
+pub fn execute_claim_rewards(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    collection: Addr,
+    token_ids: Vec<String>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    if token_ids.is_empty() {
+        return Err(ContractError::InvalidInput("token_ids must not be empty".to_string()));
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let reward_config = load_reward_config(deps.storage)?;
+    let mut reward_state = REWARD_STATE.load(deps.storage)?;
+    accrue_rewards(&mut reward_state, Some(&reward_config), &env.block.time)?;
+    REWARD_STATE.save(deps.storage, &reward_state)?;
+
+    let mut claimed = Uint128::zero();
+    for token_id in &token_ids {
+        let key = vault_token_key(&collection, token_id);
+        let vault_token = vault_tokens().load(deps.storage, key.clone())?;
+
+        // Only the position owner can claim rewards
+        if position_owner(deps.as_ref(), &vault_token)? != info.sender {
+            return Err(ContractError::Unauthorized("Only owner can claim rewards".to_string()));
+        }
+
+        let is_staked = vault_token.get_status(&env.block.time, config.unstake_period) == VaultTokenStatus::Staked;
+        let mut token_reward = settle_token_reward(deps.storage, &reward_state, key.clone(), is_staked)?;
+        claimed += token_reward.accrued;
+        token_reward.accrued = Uint128::zero();
+        TOKEN_REWARDS.save(deps.storage, key, &token_reward)?;
+    }
+
+    let mut response = Response::new();
+    if !claimed.is_zero() {
+        transfer_reward(&reward_config.denom, claimed, &info.sender, &mut response)?;
+    }
+
+    let event = Event::new("claim-rewards")
+        .add_attribute("owner", info.sender.to_string())
+        .add_attribute("collection", collection.to_string())
+        .add_attribute("token_ids", token_ids.join(","))
+        .add_attribute("amount", claimed.to_string());
+
+    Ok(response.add_event(event))
+}
+
+/// Prepares the messages for an action's hooks. Optional hooks reply so that
+/// failed deliveries are kept for retries, required hooks revert the action on failure
+fn prepare_hooks(
+    storage: &mut dyn Storage,
+    hooks: &Hooks,
+    action: HookAction,
+    msgs: &[HookMsg],
+) -> StdResult<Vec<SubMsg>> {
+    let mut submsgs = vec![];
+    for (hook, required) in hooks.list_hooks(storage)? {
+        let execute = WasmMsg::Execute {
+            contract_addr: hook.to_string(),
+            msg: hook_msgs_into_binary(msgs.to_vec(), action.clone())?,
+            funds: vec![],
+        };
+        if required {
+            submsgs.push(SubMsg::new(execute));
+            continue;
+        }
+        let delivery = HookDelivery {
+            hook,
+            action: action.clone(),
+            msgs: msgs.to_vec(),
+            attempts: 1,
+            error: None,
+        };
+        let delivery_id = save_pending_hook(storage, &delivery)?;
+        submsgs.push(SubMsg::reply_always(execute, delivery_id));
+    }
+    Ok(submsgs)
+}
+
+fn save_pending_hook(storage: &mut dyn Storage, delivery: &HookDelivery) -> StdResult<u64> {
+    let delivery_id = HOOK_DELIVERY_ID.may_load(storage)?.unwrap_or(1);
+    HOOK_DELIVERY_ID.save(storage, &(delivery_id + 1))?;
+    PENDING_HOOKS.save(storage, delivery_id, delivery)?;
+    Ok(delivery_id)
+}
+
+/// An operator may resend the oldest failed hook messages,
+/// deliveries to hooks that were since removed are dropped
+pub fn execute_retry_hooks(
+    deps: DepsMut,
+    info: MessageInfo,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    only_operator(&info, &config)?;
+
+    let limit = limit.unwrap_or(DEFAULT_RETRY_LIMIT).min(MAX_RETRY_LIMIT) as usize;
+    let failed_hooks = FAILED_HOOKS
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut response = Response::new();
+    let mut num_retried = 0u32;
+    let mut num_dropped = 0u32;
+    for (delivery_id, mut delivery) in failed_hooks {
+        FAILED_HOOKS.remove(deps.storage, delivery_id);
+
+        let hooks = match delivery.action {
+            HookAction::Stake => &STAKE_HOOKS,
+            HookAction::Unstake => &UNSTAKE_HOOKS,
+            HookAction::Withdraw => &WITHDRAW_HOOKS,
+        };
+        if !hooks.list_hooks(deps.storage)?.iter().any(|(h, _)| h == &delivery.hook) {
+            num_dropped += 1;
+            continue;
+        }
+
+        let execute = WasmMsg::Execute {
+            contract_addr: delivery.hook.to_string(),
+            msg: hook_msgs_into_binary(delivery.msgs.clone(), delivery.action.clone())?,
+            funds: vec![],
+        };
+        delivery.attempts += 1;
+        PENDING_HOOKS.save(deps.storage, delivery_id, &delivery)?;
+        response.messages.push(SubMsg::reply_always(execute, delivery_id));
+        num_retried += 1;
+    }
+
+    let event = Event::new("retry-hooks")
+        .add_attribute("num_retried", num_retried.to_string())
+        .add_attribute("num_dropped", num_dropped.to_string());
+
+    Ok(response.add_event(event))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    if msg.id == INSTANTIATE_RECEIPT_REPLY_ID {
+        let res = parse_reply_instantiate_data(msg)?;
+        let receipt_collection = deps.api.addr_validate(&res.contract_address)?;
+        RECEIPT_COLLECTION.save(deps.storage, &receipt_collection)?;
+        return Ok(Response::new()
+            .add_attribute("action", "instantiate_receipt_reply")
+            .add_attribute("receipt_collection", receipt_collection.to_string()));
+    }
+
+    let mut delivery = PENDING_HOOKS.load(deps.storage, msg.id)?;
+    PENDING_HOOKS.remove(deps.storage, msg.id);
+
+    match msg.result.into_result() {
+        Ok(_) => {
             let res = Response::new()
-                .add_attribute("action", "unstake-hook-failed")
-                .add_attribute("error", msg.result.unwrap_err());
+                .add_attribute("action", "hook-delivered")
+                .add_attribute("hook", delivery.hook.to_string())
+                .add_attribute("delivery_id", msg.id.to_string());
             Ok(res)
         }
-        HookReply::Withdraw => {
+        Err(err) => {
+            let action = match delivery.action {
+                HookAction::Stake => "stake-hook-failed",
+                HookAction::Unstake => "unstake-hook-failed",
+                HookAction::Withdraw => "withdraw-hook-failed",
+            };
+            delivery.error = Some(err.clone());
+            FAILED_HOOKS.save(deps.storage, msg.id, &delivery)?;
+
             let res = Response::new()
-                .add_attribute("action", "withdraw-hook-failed")
-                .add_attribute("error", msg.result.unwrap_err());
+                .add_attribute("action", action)
+                .add_attribute("hook", delivery.hook.to_string())
+                .add_attribute("delivery_id", msg.id.to_string())
+                .add_attribute("error", err);
             Ok(res)
         }
     }
-}
\ No newline at end of file
+}
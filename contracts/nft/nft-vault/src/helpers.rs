@@ -1,11 +1,19 @@
 use cosmwasm_std::{
     Addr, Api, StdResult, MessageInfo, SubMsg, Response, WasmMsg, Event, to_binary,
-    Order, Deps
+    Order, Deps, BankMsg, Storage, Timestamp, Uint128, coin, Empty
 };
-use cw721::{Cw721ExecuteMsg};
+use cw20::{Cw20ExecuteMsg, Denom};
+use cw721::Cw721ExecuteMsg;
 use cw721_base::helpers::Cw721Contract;
+use cw721_base::msg::MintMsg;
+use pg721::msg::ExecuteMsg as Pg721ExecuteMsg;
+use std::cmp::min;
 use crate::error::ContractError;
-use crate::state::{Config};
+use crate::state::{
+    Config, RewardConfig, RewardState, TokenReward, TOKEN_REWARDS, VaultTokenKey, LockTier,
+    EarlyUnstakePenalty, VaultToken, RECEIPT_COLLECTION, RECEIPT_TOKENS, VOIDED_RECEIPTS,
+    vault_token_key
+};
 
 pub fn map_validate(api: &dyn Api, addresses: &[String]) -> StdResult<Vec<Addr>> {
     addresses
@@ -27,6 +35,54 @@ pub fn only_operator(info: &MessageInfo, config: &Config) -> Result<Addr, Contra
     Ok(info.sender.clone())
 }
 
+/// Returns the lock end and reward weight of the chosen lock tier
+pub fn select_lock_tier(
+    config: &Config,
+    lock_duration: Option<u64>,
+    now: &Timestamp,
+) -> Result<(Option<Timestamp>, u64), ContractError> {
+    match lock_duration {
+        Some(_lock_duration) => {
+            let lock_tier = config
+                .lock_tiers
+                .iter()
+                .find(|t| t.duration == _lock_duration)
+                .ok_or_else(|| ContractError::InvalidInput(format!("no lock tier of {} seconds", _lock_duration)))?;
+            Ok((Some(now.plus_seconds(lock_tier.duration)), lock_tier.reward_weight))
+        }
+        None => Ok((None, 1)),
+    }
+}
+
+/// Checks the lock tiers and early unstake penalty are valid
+pub fn validate_lock_config(
+    lock_tiers: &[LockTier],
+    early_unstake_penalty: &Option<EarlyUnstakePenalty>,
+) -> Result<(), ContractError> {
+    for (idx, lock_tier) in lock_tiers.iter().enumerate() {
+        if lock_tier.duration == 0 || lock_tier.reward_weight == 0 {
+            return Err(ContractError::InvalidInput("lock tier duration and reward_weight must be positive".to_string()));
+        }
+        if lock_tiers[..idx].iter().any(|t| t.duration == lock_tier.duration) {
+            return Err(ContractError::InvalidInput(format!("duplicate lock tier of {} seconds", lock_tier.duration)));
+        }
+    }
+    if let Some(EarlyUnstakePenalty::Fee { fee, .. }) = early_unstake_penalty {
+        if fee.amount.is_zero() {
+            return Err(ContractError::InvalidInput("early unstake fee must be positive".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the NFT contract is accepted for staking
+pub fn only_approved_collection(collection: &Addr, config: &Config) -> Result<(), ContractError> {
+    if !config.collections.iter().any(|c| c == collection) {
+        return Err(ContractError::Unauthorized(format!("collection {} is not accepted for staking", collection)));
+    }
+    Ok(())
+}
+
 pub type TokenId = String;
 
 pub fn transfer_nft(token_id: &TokenId, recipient: &Addr, collection: &Addr, response: &mut Response,) -> StdResult<()> {
@@ -51,6 +107,147 @@ pub fn transfer_nft(token_id: &TokenId, recipient: &Addr, collection: &Addr, res
     Ok(())
 }
 
+pub fn burn_nft(token_id: &TokenId, collection: &Addr, response: &mut Response) -> StdResult<()> {
+    let cw721_burn_msg = Cw721ExecuteMsg::Burn {
+        token_id: token_id.to_string(),
+    };
+
+    let exec_cw721_burn = SubMsg::new(WasmMsg::Execute {
+        contract_addr: collection.to_string(),
+        msg: to_binary(&cw721_burn_msg)?,
+        funds: vec![],
+    });
+    response.messages.push(exec_cw721_burn);
+
+    let event = Event::new("burn-nft")
+        .add_attribute("collection", collection.to_string())
+        .add_attribute("token_id", token_id.to_string());
+    response.events.push(event);
+
+    Ok(())
+}
+
+/// The receipt token id of a staked token, unique across the vault's collections.
+/// Ids of receipts minted after a voided receipt are prefixed with the number voided
+pub fn receipt_token_id(storage: &dyn Storage, collection: &Addr, token_id: &str) -> StdResult<TokenId> {
+    let num_voided = VOIDED_RECEIPTS.may_load(storage, vault_token_key(collection, token_id))?.unwrap_or_default();
+    if num_voided == 0 {
+        return Ok(format!("{}/{}", collection, token_id));
+    }
+    Ok(format!("{}/{}/{}", num_voided, collection, token_id))
+}
+
+/// Mints a receipt for a token entering the vault when liquid staking is enabled
+pub fn mint_receipt(
+    storage: &mut dyn Storage,
+    collection: &Addr,
+    token_id: &str,
+    owner: &Addr,
+    response: &mut Response,
+) -> StdResult<()> {
+    let receipt_collection = match RECEIPT_COLLECTION.may_load(storage)? {
+        Some(_receipt_collection) => _receipt_collection,
+        None => return Ok(()),
+    };
+    let receipt_token_id = receipt_token_id(storage, collection, token_id)?;
+    RECEIPT_TOKENS.save(storage, &receipt_token_id, &vault_token_key(collection, token_id))?;
+
+    let pg721_mint_msg = Pg721ExecuteMsg::Mint(MintMsg::<Empty> {
+        token_id: receipt_token_id.clone(),
+        owner: owner.to_string(),
+        token_uri: None,
+        extension: Empty {},
+    });
+
+    let exec_pg721_mint = SubMsg::new(WasmMsg::Execute {
+        contract_addr: receipt_collection.to_string(),
+        msg: to_binary(&pg721_mint_msg)?,
+        funds: vec![],
+    });
+    response.messages.push(exec_pg721_mint);
+
+    let event = Event::new("mint-receipt")
+        .add_attribute("receipt_collection", receipt_collection.to_string())
+        .add_attribute("receipt_token_id", receipt_token_id)
+        .add_attribute("owner", owner.to_string());
+    response.events.push(event);
+
+    Ok(())
+}
+
+/// Returns the owner of a staking position, which is the receipt holder when liquid staking is enabled
+pub fn position_owner(deps: Deps, vault_token: &VaultToken) -> StdResult<Addr> {
+    match RECEIPT_COLLECTION.may_load(deps.storage)? {
+        Some(_receipt_collection) => {
+            let receipt_token_id = receipt_token_id(deps.storage, &vault_token.collection, &vault_token.token_id)?;
+            let res = Cw721Contract(_receipt_collection).owner_of(&deps.querier, receipt_token_id, false)?;
+            deps.api.addr_validate(&res.owner)
+        }
+        None => Ok(vault_token.owner.clone()),
+    }
+}
+
+/// The staked token of a receipt sent to the vault, voided receipts no longer represent a position
+pub fn receipt_position(storage: &dyn Storage, receipt_token_id: &str) -> Result<VaultTokenKey, ContractError> {
+    RECEIPT_TOKENS
+        .may_load(storage, receipt_token_id)?
+        .ok_or_else(|| ContractError::InvalidInput("receipt does not represent a position".to_string()))
+}
+
+/// Returns a receipt sent to the vault to its holder
+pub fn return_receipt(
+    storage: &dyn Storage,
+    collection: &Addr,
+    token_id: &str,
+    holder: &Addr,
+    response: &mut Response,
+) -> StdResult<()> {
+    if let Some(_receipt_collection) = RECEIPT_COLLECTION.may_load(storage)? {
+        let receipt_token_id = receipt_token_id(storage, collection, token_id)?;
+        transfer_nft(&receipt_token_id, holder, &_receipt_collection, response)?;
+    }
+    Ok(())
+}
+
+/// Burns the receipt of a token leaving the vault. The holder sends the receipt
+/// to the vault to withdraw, so the vault owns the receipt it burns
+pub fn burn_receipt(
+    storage: &mut dyn Storage,
+    collection: &Addr,
+    token_id: &str,
+    response: &mut Response,
+) -> StdResult<()> {
+    if let Some(_receipt_collection) = RECEIPT_COLLECTION.may_load(storage)? {
+        let receipt_token_id = receipt_token_id(storage, collection, token_id)?;
+        RECEIPT_TOKENS.remove(storage, &receipt_token_id);
+        burn_nft(&receipt_token_id, &_receipt_collection, response)?;
+    }
+    Ok(())
+}
+
+/// Voids the receipt of a token forced out of the vault, the receipt no longer represents
+/// a position and a re-staked token is minted a receipt with a new id
+pub fn void_receipt(
+    storage: &mut dyn Storage,
+    collection: &Addr,
+    token_id: &str,
+    response: &mut Response,
+) -> StdResult<()> {
+    if let Some(_receipt_collection) = RECEIPT_COLLECTION.may_load(storage)? {
+        let receipt_token_id = receipt_token_id(storage, collection, token_id)?;
+        RECEIPT_TOKENS.remove(storage, &receipt_token_id);
+        VOIDED_RECEIPTS.update(storage, vault_token_key(collection, token_id), |num_voided| -> StdResult<_> {
+            Ok(num_voided.unwrap_or_default() + 1)
+        })?;
+
+        let event = Event::new("void-receipt")
+            .add_attribute("receipt_collection", _receipt_collection.to_string())
+            .add_attribute("receipt_token_id", receipt_token_id);
+        response.events.push(event);
+    }
+    Ok(())
+}
+
 pub fn option_bool_to_order(descending: Option<bool>) -> Order {
     match descending {
        Some(_descending) => if _descending { Order::Descending } else { Order::Ascending },
@@ -61,13 +258,84 @@ pub fn option_bool_to_order(descending: Option<bool>) -> Order {
 /// Checks to enforce only NFT owner can call
 pub fn only_owner(
     deps: Deps,
-    info: &MessageInfo,
+    sender: &Addr,
     collection: &Addr,
     token_id: &str,
 ) -> Result<(), ContractError> {
     let res = Cw721Contract(collection.clone()).owner_of(&deps.querier, token_id, false)?;
-    if res.owner != info.sender {
+    if &res.owner != sender {
         return Err(ContractError::Unauthorized(String::from("only the owner can call this function")));
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Advances the reward index to now, rewards stop accruing once the funded rewards are exhausted
+pub fn accrue_rewards(
+    reward_state: &mut RewardState,
+    reward_config: Option<&RewardConfig>,
+    now: &Timestamp,
+) -> StdResult<()> {
+    if now <= &reward_state.last_update {
+        return Ok(());
+    }
+    if let Some(_reward_config) = reward_config {
+        if reward_state.staked_weight > 0 {
+            let elapsed = Uint128::from(now.seconds() - reward_state.last_update.seconds());
+            let staked_weight = Uint128::from(reward_state.staked_weight);
+            let earned = min(
+                _reward_config.emission_rate.checked_mul(elapsed)?,
+                reward_state.unallocated / staked_weight,
+            );
+            reward_state.reward_index += earned;
+            reward_state.unallocated -= earned * staked_weight;
+        }
+    }
+    reward_state.last_update = *now;
+    Ok(())
+}
+
+/// Moves the rewards earned by a token since it was last settled into its accrued rewards
+pub fn settle_token_reward(
+    storage: &dyn Storage,
+    reward_state: &RewardState,
+    vault_token_key: VaultTokenKey,
+    is_staked: bool,
+) -> StdResult<TokenReward> {
+    let mut token_reward = TOKEN_REWARDS.may_load(storage, vault_token_key)?.unwrap_or_default();
+    if is_staked {
+        let earned = reward_state.reward_index - token_reward.reward_index;
+        token_reward.accrued += earned.checked_mul(Uint128::from(token_reward.weight))?;
+    }
+    token_reward.reward_index = reward_state.reward_index;
+    Ok(token_reward)
+}
+
+pub fn transfer_reward(
+    denom: &Denom,
+    amount: Uint128,
+    recipient: &Addr,
+    response: &mut Response,
+) -> StdResult<()> {
+    let msg = match denom {
+        Denom::Native(_denom) => SubMsg::new(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![coin(amount.u128(), _denom)],
+        }),
+        Denom::Cw20(_address) => SubMsg::new(WasmMsg::Execute {
+            contract_addr: _address.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }),
+    };
+    response.messages.push(msg);
+
+    let event = Event::new("transfer-reward")
+        .add_attribute("recipient", recipient.to_string())
+        .add_attribute("amount", amount.to_string());
+    response.events.push(event);
+
+    Ok(())
+}
@@ -2,12 +2,14 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use cosmwasm_std::{Addr, CustomQuery, Deps, StdError, StdResult, Storage, SubMsg};
+use cosmwasm_std::{Addr, CustomQuery, Deps, StdError, StdResult, Storage};
 use cw_storage_plus::Item;
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct HooksResponse {
     pub hooks: Vec<String>,
+    /// Hooks whose failure reverts the triggering action
+    pub required_hooks: Vec<String>,
 }
 
 #[derive(Error, Debug, PartialEq)]
@@ -23,20 +25,26 @@ pub enum HookError {
 }
 
 // store all hook addresses in one item. We cannot have many of them before the contract becomes unusable anyway.
-pub struct Hooks<'a>(Item<'a, Vec<Addr>>);
+// The second item holds the subset of hooks that are required.
+pub struct Hooks<'a>(Item<'a, Vec<Addr>>, Item<'a, Vec<Addr>>);
 
 impl<'a> Hooks<'a> {
-    pub const fn new(storage_key: &'a str) -> Self {
-        Hooks(Item::new(storage_key))
+    pub const fn new(storage_key: &'a str, required_storage_key: &'a str) -> Self {
+        Hooks(Item::new(storage_key), Item::new(required_storage_key))
     }
 
-    pub fn add_hook(&self, storage: &mut dyn Storage, addr: Addr) -> Result<(), HookError> {
+    pub fn add_hook(&self, storage: &mut dyn Storage, addr: Addr, required: bool) -> Result<(), HookError> {
         let mut hooks = self.0.may_load(storage)?.unwrap_or_default();
         if !hooks.iter().any(|h| h == &addr) {
-            hooks.push(addr);
+            hooks.push(addr.clone());
         } else {
             return Err(HookError::HookAlreadyRegistered {});
         }
+        if required {
+            let mut required_hooks = self.1.may_load(storage)?.unwrap_or_default();
+            required_hooks.push(addr);
+            self.1.save(storage, &required_hooks)?;
+        }
         Ok(self.0.save(storage, &hooks)?)
     }
 
@@ -47,25 +55,31 @@ impl<'a> Hooks<'a> {
         } else {
             return Err(HookError::HookNotRegistered {});
         }
+        let mut required_hooks = self.1.may_load(storage)?.unwrap_or_default();
+        required_hooks.retain(|x| x != &addr);
+        self.1.save(storage, &required_hooks)?;
         Ok(self.0.save(storage, &hooks)?)
     }
 
-    pub fn prepare_hooks<F: Fn(Addr) -> StdResult<SubMsg>>(
-        &self,
-        storage: &dyn Storage,
-        prep: F,
-    ) -> StdResult<Vec<SubMsg>> {
-        self.0
+    /// Returns each hook along with whether it is required
+    pub fn list_hooks(&self, storage: &dyn Storage) -> StdResult<Vec<(Addr, bool)>> {
+        let required_hooks = self.1.may_load(storage)?.unwrap_or_default();
+        Ok(self.0
             .may_load(storage)?
             .unwrap_or_default()
             .into_iter()
-            .map(prep)
-            .collect()
+            .map(|h| {
+                let required = required_hooks.contains(&h);
+                (h, required)
+            })
+            .collect())
     }
 
     pub fn query_hooks<Q: CustomQuery>(&self, deps: Deps<Q>) -> StdResult<HooksResponse> {
         let hooks = self.0.may_load(deps.storage)?.unwrap_or_default();
         let hooks = hooks.into_iter().map(String::from).collect();
-        Ok(HooksResponse { hooks })
+        let required_hooks = self.1.may_load(deps.storage)?.unwrap_or_default();
+        let required_hooks = required_hooks.into_iter().map(String::from).collect();
+        Ok(HooksResponse { hooks, required_hooks })
     }
 }
@@ -1,9 +1,12 @@
 use crate::error::ContractError;
+use crate::execute::INSTANTIATE_RECEIPT_REPLY_ID;
+use crate::helpers::map_validate;
 use crate::msg::{InstantiateMsg};
-use crate::state::{Config, CONFIG};
+use crate::state::{Config, CONFIG, RewardState, REWARD_STATE, TOTAL_STAKED};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{DepsMut, Env, MessageInfo, Response};
+use cosmwasm_std::{to_binary, DepsMut, Env, MessageInfo, Response, SubMsg, Uint128, WasmMsg};
+use pg721::msg::InstantiateMsg as Pg721InstantiateMsg;
 use cw2::set_contract_version;
 
 // version info for migration info
@@ -13,7 +16,7 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
@@ -21,17 +24,54 @@ pub fn instantiate(
 
     let config = Config {
         operators: vec![info.sender.clone()],
-        cw721_address: deps.api.addr_validate(&msg.cw721_address.clone())?,
+        collections: map_validate(deps.api, &msg.collections)?,
         label: msg.label,
         unstake_period: msg.unstake_period,
+        lock_tiers: vec![],
+        early_unstake_penalty: None,
+        force_timelock: msg.force_timelock,
     };
+    if config.collections.is_empty() {
+        return Err(ContractError::InvalidInput("collections must not be empty".to_string()));
+    }
     CONFIG.save(deps.storage, &config)?;
 
+    let reward_state = RewardState {
+        reward_index: Uint128::zero(),
+        last_update: env.block.time,
+        num_staked: 0,
+        staked_weight: 0,
+        unallocated: Uint128::zero(),
+    };
+    REWARD_STATE.save(deps.storage, &reward_state)?;
+    TOTAL_STAKED.save(deps.storage, &0, env.block.height)?;
+
+    // Submessage to instantiate the receipt collection, minted by the vault
+    let mut sub_msgs: Vec<SubMsg> = vec![];
+    if let Some(_receipt) = msg.receipt {
+        sub_msgs.push(SubMsg::reply_on_success(
+            WasmMsg::Instantiate {
+                code_id: _receipt.code_id,
+                msg: to_binary(&Pg721InstantiateMsg {
+                    name: _receipt.instantiate_msg.name,
+                    symbol: _receipt.instantiate_msg.symbol,
+                    minter: env.contract.address.to_string(),
+                    collection_info: _receipt.instantiate_msg.collection_info,
+                })?,
+                funds: info.funds,
+                admin: Some(info.sender.to_string()),
+                label: String::from("NFT vault receipts"),
+            },
+            INSTANTIATE_RECEIPT_REPLY_ID,
+        ));
+    }
+
     Ok(Response::new()
         .add_attribute("action", "instantiate")
         .add_attribute("contract_name", CONTRACT_NAME)
         .add_attribute("contract_version", CONTRACT_VERSION)
-        .add_attribute("cw721_address", config.cw721_address)
+        .add_attribute("collections", config.collections.iter().map(|addr| addr.to_string()).collect::<Vec<String>>().join(","))
         .add_attribute("label", config.label)
+        .add_submessages(sub_msgs)
     )
 }
\ No newline at end of file
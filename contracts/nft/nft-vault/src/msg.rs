@@ -1,13 +1,31 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use cosmwasm_std::{to_binary, Addr, Binary, StdResult, Timestamp};
-use crate::state::{VaultToken, VaultTokenStatus};
+use cosmwasm_std::{to_binary, Addr, Binary, StdResult, Timestamp, Uint128};
+use cw20::{Cw20ReceiveMsg, Denom};
+use cw721::Cw721ReceiveMsg;
+use pg721::msg::InstantiateMsg as Pg721InstantiateMsg;
+use crate::state::{
+    EarlyUnstakePenalty, HookDelivery, LockTier, RewardConfig, RewardState, VaultToken,
+    VaultTokenStatus, ScheduledForceAction
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
-    pub cw721_address: String,
+    pub collections: Vec<String>,
     pub label: String,
     pub unstake_period: u64,
+    /// Enables liquid staking, a receipt NFT is minted for each staked token
+    pub receipt: Option<ReceiptMsg>,
+    /// Force actions must be scheduled this many seconds before they are executed
+    pub force_timelock: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReceiptMsg {
+    /// The pg721 code the receipt collection is instantiated from
+    pub code_id: u64,
+    /// The minter is replaced with the vault
+    pub instantiate_msg: Pg721InstantiateMsg,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -15,28 +33,92 @@ pub struct InstantiateMsg {
 pub enum ExecuteMsg {
     /// Update the contract configuration
     UpdateConfig {
+        collections: Option<Vec<String>>,
         operators: Option<Vec<String>>,
         label: Option<String>,
         unstake_period: Option<u64>,
     },
-    /// Add a new hook to be informed of all staking events
-    AddStakeHook { hook: String },
+    /// Add a new hook to be informed of all staking events,
+    /// the failure of a required hook reverts the stake
+    AddStakeHook { hook: String, required: Option<bool> },
     /// Remove a stake hook
     RemoveStakeHook { hook: String },
     /// Add a new hook to be informed of all unstaking events
-    AddUnstakeHook { hook: String },
+    AddUnstakeHook { hook: String, required: Option<bool> },
     /// Remove an unstake hook
     RemoveUnstakeHook { hook: String },
     /// Add a new hook to be informed of all withdraw events
-    AddWithdrawHook { hook: String },
+    AddWithdrawHook { hook: String, required: Option<bool> },
     /// Remove a withdraw hook
     RemoveWithdrawHook { hook: String },
-    /// Stake an NFT
-    Stake { token_id: String, },
-    /// Unstake an NFT
-    Unstake { token_id: String, },
-    /// Withdraw an NFT
-    Withdraw { token_id: String, },
+    /// Update the lock tiers and the penalty for unstaking before a lock ends
+    UpdateLockConfig {
+        lock_tiers: Vec<LockTier>,
+        early_unstake_penalty: Option<EarlyUnstakePenalty>,
+    },
+    /// Stake an NFT, optionally locking it for the duration of a lock tier
+    Stake { collection: String, token_id: String, lock_duration: Option<u64>, },
+    /// Stake an NFT sent to the vault with `SendNft`, or act on the position of a receipt
+    /// sent back to the vault, the payload is a `ReceiveNftMsg`
+    ReceiveNft(Cw721ReceiveMsg),
+    /// Stake many NFTs of a collection the vault is approved to transfer
+    StakeMany { collection: String, token_ids: Vec<String>, lock_duration: Option<u64>, },
+    /// Unstake an NFT, a locked NFT is subject to the early unstake penalty.
+    /// When liquid staking is enabled, the burn penalty also burns the receipt,
+    /// so the holder unstakes by sending the receipt with `ReceiveNftMsg::Unstake`
+    Unstake { collection: String, token_id: String, },
+    /// Withdraw an NFT. When liquid staking is enabled the receipt is burned,
+    /// so the holder withdraws by sending the receipt with `ReceiveNftMsg::Withdraw`
+    Withdraw { collection: String, token_id: String, },
+    /// Unstake many unlocked NFTs of a collection, hooks receive a single batch message
+    UnstakeMany { collection: String, token_ids: Vec<String>, },
+    /// Withdraw many NFTs of a collection, hooks receive a single batch message.
+    /// Not available to liquid staking vaults
+    WithdrawMany { collection: String, token_ids: Vec<String>, },
+    /// Withdraw transferrable NFTs to their owners in order of unstake time,
+    /// not available to liquid staking vaults
+    WithdrawAllTransferrable { limit: Option<u32>, },
+    /// Set the reward token and the amount each staked token earns per second,
+    /// the reward token cannot be changed once set
+    ConfigureRewards { denom: Denom, emission_rate: Uint128, },
+    /// Fund the rewards with the native reward token
+    FundRewards {},
+    /// Fund the rewards with the cw20 reward token, the payload is a `ReceiveMsg`
+    Receive(Cw20ReceiveMsg),
+    /// Claim the rewards earned by NFTs of a collection
+    ClaimRewards { collection: String, token_ids: Vec<String>, },
+    /// Resend the oldest failed hook messages
+    RetryHooks { limit: Option<u32>, },
+    /// Unstake an NFT on behalf of its owner, ignoring its lock
+    ForceUnstake { collection: String, token_id: String, reason: String, },
+    /// Withdraw an NFT to a recipient, its unclaimed rewards are returned to the reward pool.
+    /// The receipt of a liquid staking position is voided rather than burned
+    ForceWithdraw { collection: String, token_id: String, recipient: String, reason: String, },
+    /// Cancel a force action awaiting the force timelock
+    CancelForceAction { collection: String, token_id: String, },
+}
+
+/// Payload of a `SendNft` to the vault
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveNftMsg {
+    Stake { lock_duration: Option<u64> },
+    /// Unstake the position of a sent receipt, the receipt is returned unless
+    /// the burn penalty burns the token. An early unstake fee must be paid with `Unstake`
+    Unstake {},
+    /// Withdraw the token of a sent receipt to its holder, the receipt is burned
+    Withdraw {},
+    /// Transfer the position of a sent receipt and the receipt to a recipient, with its
+    /// voting power. A receipt transferred directly on the receipt collection leaves the position
+    /// with its recorded owner until the holder sends it to the vault
+    TransferPosition { recipient: String },
+}
+
+/// Payload of a cw20 `Send` to the vault
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    FundRewards {},
 }
 
 /// Options when querying for VaultTokens
@@ -49,6 +131,7 @@ pub struct QueryOptions<T> {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct TokenTimestampOffset {
+    pub collection: Addr,
     pub token_id: String,
     pub timestamp: Timestamp,
 }
@@ -60,18 +143,41 @@ pub enum QueryMsg {
     StakeHooks {},
     UnstakeHooks {},
     WithdrawHooks {},
-    VaultToken { token_id: String },
+    VaultToken { collection: String, token_id: String },
+    /// The tokens of an owner, who is the recorded owner of each position when liquid staking is enabled
     VaultTokensByOwner { owner: String, query_options: QueryOptions<TokenTimestampOffset> },
+    VaultTokensByCollectionStakeTimestamp { collection: String, query_options: QueryOptions<TokenTimestampOffset> },
     VaultTokensByStakeTimestamp { query_options: QueryOptions<TokenTimestampOffset> },
     VaultTokensByUnstakeTimestamp { query_options: QueryOptions<TokenTimestampOffset> },
+    Rewards {},
+    /// The unclaimed rewards of an owner's tokens, the owner is the recorded owner
+    /// of each position when liquid staking is enabled
+    PendingRewards { owner: String },
+    FailedHooks { start_after: Option<u64>, limit: Option<u32> },
+    /// The number of tokens staked by an owner at the start of a block height,
+    /// the current number if no height is given
+    StakedBalanceAtHeight { owner: String, height: Option<u64> },
+    /// The total number of tokens staked at the start of a block height,
+    /// the current number if no height is given
+    TotalStakedAtHeight { height: Option<u64> },
+    ForceAction { collection: String, token_id: String },
+    /// The number of an owner's tokens in each status. When liquid staking is enabled the owner
+    /// is the recorded owner of each position, which receipts sent with `TransferPosition` update
+    OwnerSummary { owner: String },
+    /// The number of the vault's tokens in each status
+    VaultSummary {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ConfigResponse {
-    pub cw721_address: String,
+    pub collections: Vec<String>,
     pub operators: Vec<String>,
     pub label: String,
     pub unstake_period: u64,
+    pub lock_tiers: Vec<LockTier>,
+    pub early_unstake_penalty: Option<EarlyUnstakePenalty>,
+    pub receipt_collection: Option<String>,
+    pub force_timelock: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -84,6 +190,60 @@ pub struct VaultTokensResponse {
     pub vault_tokens: Vec<VaultToken>
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardsResponse {
+    pub reward_config: Option<RewardConfig>,
+    pub reward_state: RewardState,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingRewardsResponse {
+    pub denom: Option<Denom>,
+    pub pending: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OwnerSummaryResponse {
+    pub staked: u64,
+    pub unstaking: u64,
+    pub transferrable: u64,
+    pub total: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VaultSummaryResponse {
+    pub staked: u64,
+    pub unstaking: u64,
+    pub transferrable: u64,
+    pub total: u64,
+    /// The number of owners with a staked token
+    pub num_stakers: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ForceActionResponse {
+    pub force_action: Option<ScheduledForceAction>,
+}
+
+/// Shares the `weight` field of cw4 `MemberResponse`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakedBalanceAtHeightResponse {
+    pub weight: u64,
+    pub height: u64,
+}
+
+/// Shares the `weight` field of cw4 `TotalWeightResponse`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TotalStakedAtHeightResponse {
+    pub weight: u64,
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FailedHooksResponse {
+    pub failed_hooks: Vec<(u64, HookDelivery)>,
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum HookAction {
@@ -100,22 +260,23 @@ pub struct HookMsg {
     pub owner: Addr,
     pub stake_timestamp: Timestamp,
     pub unstake_timestamp: Option<Timestamp>,
+    pub lock_end_timestamp: Option<Timestamp>,
     pub status: VaultTokenStatus,
 }
 
 impl HookMsg {
     pub fn new(
-        cw721_address: &Addr,
         vault_token: &VaultToken,
         now: &Timestamp,
         unstake_period: u64,
     ) -> Self {
         HookMsg {
-            cw721_address: cw721_address.clone(),
+            cw721_address: vault_token.collection.clone(),
             token_id: vault_token.token_id.clone(),
             owner: vault_token.owner.clone(),
             stake_timestamp: vault_token.stake_timestamp.clone(),
             unstake_timestamp: vault_token.unstake_timestamp.clone(),
+            lock_end_timestamp: vault_token.lock_end_timestamp.clone(),
             status: vault_token.get_status(now, unstake_period),
         }
     }
@@ -131,6 +292,19 @@ impl HookMsg {
     }
 }
 
+/// Serializes the messages of an action, several messages are sent as a single batch
+pub fn hook_msgs_into_binary(mut msgs: Vec<HookMsg>, action: HookAction) -> StdResult<Binary> {
+    if msgs.len() == 1 {
+        return msgs.remove(0).into_binary(action);
+    }
+    let msg = match action {
+        HookAction::Stake => HookExecuteMsg::StakeManyHook(msgs),
+        HookAction::Unstake => HookExecuteMsg::UnstakeManyHook(msgs),
+        HookAction::Withdraw => HookExecuteMsg::WithdrawManyHook(msgs),
+    };
+    to_binary(&msg)
+}
+
 // This is just a helper to properly serialize the above message
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
@@ -138,4 +312,7 @@ pub enum HookExecuteMsg {
     StakeHook(HookMsg),
     UnstakeHook(HookMsg),
     WithdrawHook(HookMsg),
+    StakeManyHook(Vec<HookMsg>),
+    UnstakeManyHook(Vec<HookMsg>),
+    WithdrawManyHook(Vec<HookMsg>),
 }
\ No newline at end of file
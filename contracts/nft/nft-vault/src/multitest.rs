@@ -1,15 +1,24 @@
 #![cfg(test)]
-use cosmwasm_std::{Addr, Empty, coins, Coin, Decimal, Uint128, Timestamp};
+use cosmwasm_std::{
+    to_binary, Addr, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdError, StdResult,
+    coins, Coin, Decimal, Uint128, Timestamp
+};
+use cw_storage_plus::Item;
+use serde::{Deserialize, Serialize};
 use cw_multi_test::{App, AppBuilder, BankSudo, Contract, ContractWrapper, Executor, SudoMsg as CwSudoMsg};
 use cw721_base::msg::{MintMsg};
-use cw721::{Cw721QueryMsg, OwnerOfResponse};
+use cw721::{Cw721QueryMsg, Cw721ReceiveMsg, OwnerOfResponse};
 use pg721::msg::{InstantiateMsg as Pg721InstantiateMsg, ExecuteMsg as Pg721ExecuteMsg, RoyaltyInfoResponse};
 use pg721::state::CollectionInfo;
 use crate::error::ContractError;
 use crate::msg::{
     ExecuteMsg, QueryMsg, QueryOptions, InstantiateMsg as NftVaultInstantiateMsg,
-    VaultTokenResponse, VaultTokensResponse
+    VaultTokenResponse, VaultTokensResponse, ReceiveNftMsg, PendingRewardsResponse,
+    FailedHooksResponse, HookMsg, RewardsResponse, ConfigResponse, ReceiptMsg,
+    StakedBalanceAtHeightResponse, TotalStakedAtHeightResponse, ForceActionResponse,
+    OwnerSummaryResponse, VaultSummaryResponse
 };
+use crate::state::{LockTier, EarlyUnstakePenalty};
 
 const TOKEN_ID_A: &str = "1";
 const TOKEN_ID_B: &str = "2";
@@ -50,6 +59,62 @@ pub fn contract_nft_vault() -> Box<dyn Contract<Empty>> {
         crate::execute::execute,
         crate::instantiate::instantiate,
         crate::query::query,
+    )
+    .with_reply(crate::execute::reply);
+    Box::new(contract)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum MockHookExecuteMsg {
+    StakeHook(HookMsg),
+    UnstakeHook(HookMsg),
+    WithdrawHook(HookMsg),
+    UnstakeManyHook(Vec<HookMsg>),
+    WithdrawManyHook(Vec<HookMsg>),
+    SetFail { fail: bool },
+}
+
+const MOCK_HOOK_FAIL: Item<bool> = Item::new("fail");
+const MOCK_HOOK_RECEIVED: Item<u32> = Item::new("received");
+
+fn mock_hook_execute(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: MockHookExecuteMsg,
+) -> StdResult<Response> {
+    if let MockHookExecuteMsg::SetFail { fail } = msg {
+        MOCK_HOOK_FAIL.save(deps.storage, &fail)?;
+        return Ok(Response::new());
+    }
+    if MOCK_HOOK_FAIL.may_load(deps.storage)?.unwrap_or(false) {
+        return Err(StdError::generic_err("hook failed"));
+    }
+    let received = MOCK_HOOK_RECEIVED.may_load(deps.storage)?.unwrap_or(0);
+    MOCK_HOOK_RECEIVED.save(deps.storage, &(received + 1))?;
+    Ok(Response::new())
+}
+
+fn mock_hook_instantiate(
+    _deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: Empty,
+) -> StdResult<Response> {
+    Ok(Response::new())
+}
+
+fn mock_hook_query(deps: Deps, _env: Env, _msg: Empty) -> StdResult<Binary> {
+    to_binary(&MOCK_HOOK_RECEIVED.may_load(deps.storage)?.unwrap_or(0))
+}
+
+// A hook contract that counts the hook messages received, or fails them on demand
+pub fn contract_mock_hook() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        mock_hook_execute,
+        mock_hook_instantiate,
+        mock_hook_query,
     );
     Box::new(contract)
 }
@@ -95,9 +160,11 @@ fn setup_contracts(
     
     let nft_vault_id = router.store_code(contract_nft_vault());
     let msg = NftVaultInstantiateMsg {
-        cw721_address: collection.to_string(),
+        collections: vec![collection.to_string()],
         label: String::from("Test Vault"),
         unstake_period: UNSTAKE_PERIOD,
+        receipt: None,
+        force_timelock: None,
     };
     let nft_vault = router
         .instantiate_contract(
@@ -197,7 +264,9 @@ fn try_nft_staking_happy_path() {
 
     // Stake NFT of unowned NFT should fail
     let stake_msg = ExecuteMsg::Stake {
+        collection: collection.to_string(),
         token_id: TOKEN_ID_A.to_string(),
+        lock_duration: None,
     };
     let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
     assert!(res.is_err());
@@ -207,7 +276,9 @@ fn try_nft_staking_happy_path() {
 
     // Stake NFT should fail if not approved
     let stake_msg = ExecuteMsg::Stake {
+        collection: collection.to_string(),
         token_id: TOKEN_ID_B.to_string(),
+        lock_duration: None,
     };
     let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
     assert!(res.is_err());
@@ -215,7 +286,9 @@ fn try_nft_staking_happy_path() {
     // Stake NFT should succeed if approved
     approve(&mut router, &owner, &collection, &nft_vault, TOKEN_ID_B.to_string());
     let stake_msg = ExecuteMsg::Stake {
+        collection: collection.to_string(),
         token_id: TOKEN_ID_B.to_string(),
+        lock_duration: None,
     };
     let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
     assert!(res.is_ok());
@@ -233,6 +306,7 @@ fn try_nft_staking_happy_path() {
 
     // Query vault data for NFT
     let query_owner_msg = QueryMsg::VaultToken {
+        collection: collection.to_string(),
         token_id: TOKEN_ID_B.to_string(),
     };
     let res: VaultTokenResponse = router
@@ -246,6 +320,7 @@ fn try_nft_staking_happy_path() {
 
     // Creator cannot unstake owner's NFT
     let unstake_msg = ExecuteMsg::Unstake {
+        collection: collection.to_string(),
         token_id: TOKEN_ID_B.to_string(),
     };
     let res = router.execute_contract(creator.clone(), nft_vault.clone(), &unstake_msg, &[]);
@@ -253,6 +328,7 @@ fn try_nft_staking_happy_path() {
 
     // Owner can unstake their NFT
     let unstake_msg = ExecuteMsg::Unstake {
+        collection: collection.to_string(),
         token_id: TOKEN_ID_B.to_string(),
     };
     let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_msg, &[]);
@@ -260,6 +336,7 @@ fn try_nft_staking_happy_path() {
 
     // Query vault data for NFT
     let query_owner_msg = QueryMsg::VaultToken {
+        collection: collection.to_string(),
         token_id: TOKEN_ID_B.to_string(),
     };
     let res: VaultTokenResponse = router
@@ -271,13 +348,16 @@ fn try_nft_staking_happy_path() {
 
     // Owner can restake their NFT, which is in the unstaking phase
     let stake_msg = ExecuteMsg::Stake {
+        collection: collection.to_string(),
         token_id: TOKEN_ID_B.to_string(),
+        lock_duration: None,
     };
     let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
     assert!(res.is_ok());
 
     // Query vault data for NFT
     let query_owner_msg = QueryMsg::VaultToken {
+        collection: collection.to_string(),
         token_id: TOKEN_ID_B.to_string(),
     };
     let res: VaultTokenResponse = router
@@ -289,11 +369,13 @@ fn try_nft_staking_happy_path() {
 
     // Owner cannot withdraw NFT before unstaking period ends
     let unstake_msg = ExecuteMsg::Unstake {
+        collection: collection.to_string(),
         token_id: TOKEN_ID_B.to_string(),
     };
     let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_msg, &[]);
     assert!(res.is_ok());
     let withdraw_msg = ExecuteMsg::Withdraw {
+        collection: collection.to_string(),
         token_id: TOKEN_ID_B.to_string(),
     };
     let res = router.execute_contract(owner.clone(), nft_vault.clone(), &withdraw_msg, &[]);
@@ -302,6 +384,7 @@ fn try_nft_staking_happy_path() {
     // Creator cannot withdraw owner's NFT
     setup_block_time(&mut router, block_time.seconds() + UNSTAKE_PERIOD + 1u64);
     let withdraw_msg = ExecuteMsg::Withdraw {
+        collection: collection.to_string(),
         token_id: TOKEN_ID_B.to_string(),
     };
     let res = router.execute_contract(creator.clone(), nft_vault.clone(), &withdraw_msg, &[]);
@@ -309,12 +392,14 @@ fn try_nft_staking_happy_path() {
 
     // Owner can withdraw their NFT after unstaking period ends
     let withdraw_msg = ExecuteMsg::Withdraw {
+        collection: collection.to_string(),
         token_id: TOKEN_ID_B.to_string(),
     };
     let res = router.execute_contract(owner.clone(), nft_vault.clone(), &withdraw_msg, &[]);
     assert!(res.is_ok());
 
     let query_owner_msg = QueryMsg::VaultToken {
+        collection: collection.to_string(),
         token_id: TOKEN_ID_B.to_string(),
     };
     let res: VaultTokenResponse = router
@@ -341,7 +426,9 @@ fn try_nft_staking_queries() {
         let block_time = router.block_info().time;
         setup_block_time(&mut router, block_time.seconds() + n);
         let stake_msg = ExecuteMsg::Stake {
+            collection: collection.to_string(),
             token_id: n.to_string(),
+            lock_duration: None,
         };
         let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
         assert!(res.is_ok());
@@ -415,6 +502,7 @@ fn try_unauthorized_hook_messages() {
 
     let add_stake_hook_msg = ExecuteMsg::AddStakeHook {
         hook: dummy_addr.to_string(),
+        required: None,
     };
     let res = router.execute_contract(owner.clone(), nft_vault.clone(), &add_stake_hook_msg, &[]);
     assert_eq!(
@@ -433,6 +521,7 @@ fn try_unauthorized_hook_messages() {
 
     let add_unstake_hook_msg = ExecuteMsg::AddUnstakeHook {
         hook: dummy_addr.to_string(),
+        required: None,
     };
     let res = router.execute_contract(owner.clone(), nft_vault.clone(), &add_unstake_hook_msg, &[]);
     assert_eq!(
@@ -451,6 +540,7 @@ fn try_unauthorized_hook_messages() {
 
     let add_withdraw_hook_msg = ExecuteMsg::AddWithdrawHook {
         hook: dummy_addr.to_string(),
+        required: None,
     };
     let res = router.execute_contract(owner.clone(), nft_vault.clone(), &add_withdraw_hook_msg, &[]);
     assert_eq!(
@@ -466,4 +556,1291 @@ fn try_unauthorized_hook_messages() {
         res.unwrap_err().source().unwrap().to_string(),
         unauthorized_err,
     );
-}
\ No newline at end of file
+}
+#[test]
+fn try_receive_nft_and_stake_many() {
+    let mut router = custom_mock_app();
+
+    // Setup intial accounts
+    let (owner, creator) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (collection, nft_vault) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Sending an NFT to the vault stakes it in one step, without an approval
+    mint(&mut router, &creator, &owner, &collection, TOKEN_ID_A.to_string());
+    let send_nft_msg = Pg721ExecuteMsg::SendNft {
+        contract: nft_vault.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+        msg: to_binary(&ReceiveNftMsg::Stake { lock_duration: None }).unwrap(),
+    };
+    let res = router.execute_contract(owner.clone(), collection.clone(), &send_nft_msg, &[]);
+    assert!(res.is_ok());
+
+    let res: VaultTokenResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &QueryMsg::VaultToken { collection: collection.to_string(), token_id: TOKEN_ID_A.to_string() })
+        .unwrap();
+    let vault_token = res.vault_token.unwrap();
+    assert_eq!(vault_token.owner, owner);
+    assert_eq!(vault_token.unstake_timestamp, None);
+
+    // ReceiveNft can only be called by the vault collection
+    let receive_msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+        sender: owner.to_string(),
+        token_id: TOKEN_ID_B.to_string(),
+        msg: to_binary(&ReceiveNftMsg::Stake { lock_duration: None }).unwrap(),
+    });
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &receive_msg, &[]);
+    assert!(res.is_err());
+
+    // StakeMany stakes every token with an operator approval
+    let token_ids: Vec<String> = vec!["3".to_string(), "4".to_string(), "5".to_string()];
+    for token_id in &token_ids {
+        mint(&mut router, &creator, &owner, &collection, token_id.clone());
+    }
+    let approve_all_msg = Pg721ExecuteMsg::ApproveAll {
+        operator: nft_vault.to_string(),
+        expires: None,
+    };
+    let res = router.execute_contract(owner.clone(), collection.clone(), &approve_all_msg, &[]);
+    assert!(res.is_ok());
+
+    // A batch containing an unowned token fails as a whole
+    mint(&mut router, &creator, &creator, &collection, "6".to_string());
+    let stake_many_msg = ExecuteMsg::StakeMany {
+        collection: collection.to_string(),
+        token_ids: vec!["3".to_string(), "6".to_string()],
+        lock_duration: None,
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_many_msg, &[]);
+    assert!(res.is_err());
+
+    let stake_many_msg = ExecuteMsg::StakeMany { collection: collection.to_string(), token_ids: token_ids.clone(), lock_duration: None };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_many_msg, &[]);
+    assert!(res.is_ok());
+
+    for token_id in &token_ids {
+        let res: OwnerOfResponse = router
+            .wrap()
+            .query_wasm_smart(collection.clone(), &Cw721QueryMsg::OwnerOf {
+                token_id: token_id.clone(),
+                include_expired: None,
+            })
+            .unwrap();
+        assert_eq!(res.owner, nft_vault.to_string());
+    }
+
+    let res: VaultTokensResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &QueryMsg::VaultTokensByOwner {
+            owner: owner.to_string(),
+            query_options: QueryOptions {
+                descending: None,
+                start_after: None,
+                limit: None,
+            },
+        })
+        .unwrap();
+    assert_eq!(res.vault_tokens.len(), 4);
+}
+
+fn query_pending_rewards(router: &App, nft_vault: &Addr, owner: &Addr) -> Uint128 {
+    let res: PendingRewardsResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &QueryMsg::PendingRewards { owner: owner.to_string() })
+        .unwrap();
+    res.pending
+}
+
+#[test]
+fn try_staking_rewards() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+
+    // Setup intial accounts
+    let (owner, creator) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (collection, nft_vault) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Stake an NFT before rewards are configured
+    for token_id in [TOKEN_ID_A, TOKEN_ID_B] {
+        mint(&mut router, &creator, &owner, &collection, token_id.to_string());
+        approve(&mut router, &owner, &collection, &nft_vault, token_id.to_string());
+    }
+    let stake_msg = ExecuteMsg::Stake { collection: collection.to_string(), token_id: TOKEN_ID_A.to_string(), lock_duration: None };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+    assert!(res.is_ok());
+
+    // Only operators can configure rewards
+    let configure_rewards_msg = ExecuteMsg::ConfigureRewards {
+        denom: cw20::Denom::Native(NATIVE_DENOM.to_string()),
+        emission_rate: Uint128::from(1u128),
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &configure_rewards_msg, &[]);
+    assert!(res.is_err());
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &configure_rewards_msg, &[]);
+    assert!(res.is_ok());
+
+    // Fund 100 tokens of rewards
+    router
+        .sudo(CwSudoMsg::Bank({
+            BankSudo::Mint {
+                to_address: creator.to_string(),
+                amount: coins(100, NATIVE_DENOM),
+            }
+        }))
+        .unwrap();
+    let fund_rewards_msg = ExecuteMsg::FundRewards {};
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &fund_rewards_msg, &coins(100, NATIVE_DENOM));
+    assert!(res.is_ok());
+
+    // Each staked token earns 1 token per second
+    setup_block_time(&mut router, block_time.plus_seconds(10).seconds());
+    assert_eq!(query_pending_rewards(&router, &nft_vault, &owner), Uint128::from(10u128));
+
+    let stake_msg = ExecuteMsg::Stake { collection: collection.to_string(), token_id: TOKEN_ID_B.to_string(), lock_duration: None };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+    assert!(res.is_ok());
+    setup_block_time(&mut router, block_time.plus_seconds(20).seconds());
+    assert_eq!(query_pending_rewards(&router, &nft_vault, &owner), Uint128::from(30u128));
+
+    // Rewards stop accruing once a token is unstaked
+    let unstake_msg = ExecuteMsg::Unstake { collection: collection.to_string(), token_id: TOKEN_ID_A.to_string() };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_msg, &[]);
+    assert!(res.is_ok());
+    setup_block_time(&mut router, block_time.plus_seconds(30).seconds());
+    assert_eq!(query_pending_rewards(&router, &nft_vault, &owner), Uint128::from(40u128));
+
+    // Only the owner can claim rewards
+    let claim_rewards_msg = ExecuteMsg::ClaimRewards {
+        collection: collection.to_string(),
+        token_ids: vec![TOKEN_ID_A.to_string(), TOKEN_ID_B.to_string()],
+    };
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &claim_rewards_msg, &[]);
+    assert!(res.is_err());
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &claim_rewards_msg, &[]);
+    assert!(res.is_ok());
+    let owner_balance = router.wrap().query_balance(owner.clone(), NATIVE_DENOM).unwrap();
+    assert_eq!(owner_balance.amount, Uint128::from(INITIAL_BALANCE + 40));
+    assert_eq!(query_pending_rewards(&router, &nft_vault, &owner), Uint128::zero());
+
+    // Rewards stop accruing once the funded rewards are exhausted
+    setup_block_time(&mut router, block_time.plus_seconds(200).seconds());
+    assert_eq!(query_pending_rewards(&router, &nft_vault, &owner), Uint128::from(60u128));
+    let claim_rewards_msg = ExecuteMsg::ClaimRewards {
+        collection: collection.to_string(),
+        token_ids: vec![TOKEN_ID_B.to_string()],
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &claim_rewards_msg, &[]);
+    assert!(res.is_ok());
+    let vault_balance = router.wrap().query_balance(nft_vault.clone(), NATIVE_DENOM).unwrap();
+    assert_eq!(vault_balance.amount, Uint128::zero());
+}
+
+#[test]
+fn try_multi_collection_vault() {
+    let mut router = custom_mock_app();
+
+    // Setup intial accounts
+    let (owner, creator) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (collection, nft_vault) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Setup a second collection
+    router
+        .sudo(CwSudoMsg::Bank({
+            BankSudo::Mint {
+                to_address: creator.to_string(),
+                amount: coins(CREATION_FEE, NATIVE_DENOM),
+            }
+        }))
+        .unwrap();
+    let pg721_id = router.store_code(contract_pg721());
+    let msg = Pg721InstantiateMsg {
+        name: String::from("Test Coin 2"),
+        symbol: String::from("TEST2"),
+        minter: creator.to_string(),
+        collection_info: CollectionInfo {
+            creator: creator.to_string(),
+            description: String::from("Passage Monkeys 2"),
+            image: "ipfs://bafybeigi3bwpvyvsmnbj46ra4hyffcxdeaj6ntfk5jpic5mx27x6ih2qvq/images/1.png".to_string(),
+            external_link: None,
+            royalty_info: None,
+        },
+    };
+    let collection2 = router
+        .instantiate_contract(
+            pg721_id,
+            creator.clone(),
+            &msg,
+            &coins(CREATION_FEE, NATIVE_DENOM),
+            "NFT",
+            None,
+        )
+        .unwrap();
+
+    // The same token_id is minted in both collections
+    for _collection in [&collection, &collection2] {
+        mint(&mut router, &creator, &owner, _collection, TOKEN_ID_A.to_string());
+        approve(&mut router, &owner, _collection, &nft_vault, TOKEN_ID_A.to_string());
+    }
+
+    // Tokens of unapproved collections cannot be staked
+    let stake_msg = ExecuteMsg::Stake {
+        collection: collection2.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+        lock_duration: None,
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+    assert!(res.is_err());
+
+    let update_config_msg = ExecuteMsg::UpdateConfig {
+        collections: Some(vec![collection.to_string(), collection2.to_string()]),
+        operators: None,
+        label: None,
+        unstake_period: None,
+    };
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &update_config_msg, &[]);
+    assert!(res.is_ok());
+
+    for _collection in [&collection, &collection2] {
+        let stake_msg = ExecuteMsg::Stake {
+            collection: _collection.to_string(),
+            token_id: TOKEN_ID_A.to_string(),
+            lock_duration: None,
+        };
+        let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+        assert!(res.is_ok());
+    }
+
+    // Tokens are tracked per collection
+    let query_msg = QueryMsg::VaultTokensByCollectionStakeTimestamp {
+        collection: collection2.to_string(),
+        query_options: QueryOptions {
+            limit: None,
+            descending: None,
+            start_after: None,
+        },
+    };
+    let res: VaultTokensResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &query_msg)
+        .unwrap();
+    assert_eq!(res.vault_tokens.len(), 1);
+    assert_eq!(res.vault_tokens[0].collection, collection2);
+    assert_eq!(res.vault_tokens[0].token_id, TOKEN_ID_A.to_string());
+
+    // Unstaking a token leaves the token of the other collection staked
+    let unstake_msg = ExecuteMsg::Unstake {
+        collection: collection2.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_msg, &[]);
+    assert!(res.is_ok());
+
+    let query_msg = QueryMsg::VaultToken {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+    };
+    let res: VaultTokenResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &query_msg)
+        .unwrap();
+    assert_eq!(res.vault_token.unwrap().unstake_timestamp, None);
+}
+
+#[test]
+fn try_hook_retries() {
+    let mut router = custom_mock_app();
+
+    // Setup intial accounts
+    let (owner, creator) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (collection, nft_vault) = setup_contracts(&mut router, &creator).unwrap();
+
+    let mock_hook_id = router.store_code(contract_mock_hook());
+    let mut hooks = vec![];
+    for label in ["optional", "required"] {
+        let hook = router
+            .instantiate_contract(mock_hook_id, creator.clone(), &Empty {}, &[], label, None)
+            .unwrap();
+        hooks.push(hook);
+    }
+    let (optional_hook, required_hook) = (hooks[0].clone(), hooks[1].clone());
+
+    let add_stake_hook_msg = ExecuteMsg::AddStakeHook {
+        hook: optional_hook.to_string(),
+        required: None,
+    };
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &add_stake_hook_msg, &[]);
+    assert!(res.is_ok());
+
+    // A failing optional hook does not revert the stake, and is kept for retries
+    let set_fail_msg = MockHookExecuteMsg::SetFail { fail: true };
+    let res = router.execute_contract(creator.clone(), optional_hook.clone(), &set_fail_msg, &[]);
+    assert!(res.is_ok());
+
+    mint(&mut router, &creator, &owner, &collection, TOKEN_ID_A.to_string());
+    approve(&mut router, &owner, &collection, &nft_vault, TOKEN_ID_A.to_string());
+    let stake_msg = ExecuteMsg::Stake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+        lock_duration: None,
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+    assert!(res.is_ok());
+
+    let query_failed_hooks_msg = QueryMsg::FailedHooks { start_after: None, limit: None };
+    let res: FailedHooksResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &query_failed_hooks_msg)
+        .unwrap();
+    assert_eq!(res.failed_hooks.len(), 1);
+    let (_, delivery) = &res.failed_hooks[0];
+    assert_eq!(delivery.hook, optional_hook);
+    assert_eq!(delivery.msgs[0].token_id, TOKEN_ID_A.to_string());
+    assert_eq!(delivery.attempts, 1);
+    assert!(delivery.error.is_some());
+
+    // Only operators can retry hooks
+    let set_fail_msg = MockHookExecuteMsg::SetFail { fail: false };
+    let res = router.execute_contract(creator.clone(), optional_hook.clone(), &set_fail_msg, &[]);
+    assert!(res.is_ok());
+    let retry_hooks_msg = ExecuteMsg::RetryHooks { limit: None };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &retry_hooks_msg, &[]);
+    assert!(res.is_err());
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &retry_hooks_msg, &[]);
+    assert!(res.is_ok());
+
+    let res: FailedHooksResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &query_failed_hooks_msg)
+        .unwrap();
+    assert_eq!(res.failed_hooks.len(), 0);
+    let received: u32 = router.wrap().query_wasm_smart(optional_hook.clone(), &Empty {}).unwrap();
+    assert_eq!(received, 1);
+
+    // A failing required hook reverts the stake
+    let add_stake_hook_msg = ExecuteMsg::AddStakeHook {
+        hook: required_hook.to_string(),
+        required: Some(true),
+    };
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &add_stake_hook_msg, &[]);
+    assert!(res.is_ok());
+    let set_fail_msg = MockHookExecuteMsg::SetFail { fail: true };
+    let res = router.execute_contract(creator.clone(), required_hook.clone(), &set_fail_msg, &[]);
+    assert!(res.is_ok());
+
+    mint(&mut router, &creator, &owner, &collection, TOKEN_ID_B.to_string());
+    approve(&mut router, &owner, &collection, &nft_vault, TOKEN_ID_B.to_string());
+    let stake_msg = ExecuteMsg::Stake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_B.to_string(),
+        lock_duration: None,
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+    assert!(res.is_err());
+
+    let set_fail_msg = MockHookExecuteMsg::SetFail { fail: false };
+    let res = router.execute_contract(creator.clone(), required_hook.clone(), &set_fail_msg, &[]);
+    assert!(res.is_ok());
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+    assert!(res.is_ok());
+
+    for (hook, expected) in [(&optional_hook, 2u32), (&required_hook, 1u32)] {
+        let received: u32 = router.wrap().query_wasm_smart(hook.clone(), &Empty {}).unwrap();
+        assert_eq!(received, expected);
+    }
+}
+
+#[test]
+fn try_lock_tiers() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+
+    // Setup intial accounts
+    let (owner, creator) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (collection, nft_vault) = setup_contracts(&mut router, &creator).unwrap();
+
+    // Only operators can update the lock config
+    let update_lock_config_msg = ExecuteMsg::UpdateLockConfig {
+        lock_tiers: vec![LockTier { duration: 100, reward_weight: 2 }],
+        early_unstake_penalty: None,
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &update_lock_config_msg, &[]);
+    assert!(res.is_err());
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &update_lock_config_msg, &[]);
+    assert!(res.is_ok());
+
+    // Only configured lock durations are accepted
+    mint(&mut router, &creator, &owner, &collection, TOKEN_ID_A.to_string());
+    approve(&mut router, &owner, &collection, &nft_vault, TOKEN_ID_A.to_string());
+    let mut stake_msg = ExecuteMsg::Stake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+        lock_duration: Some(50),
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+    assert!(res.is_err());
+    if let ExecuteMsg::Stake { ref mut lock_duration, .. } = stake_msg {
+        *lock_duration = Some(100);
+    }
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+    assert!(res.is_ok());
+
+    let query_vault_token_msg = QueryMsg::VaultToken {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+    };
+    let res: VaultTokenResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &query_vault_token_msg)
+        .unwrap();
+    assert_eq!(res.vault_token.unwrap().lock_end_timestamp, Some(block_time.plus_seconds(100)));
+
+    let res: RewardsResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &QueryMsg::Rewards {})
+        .unwrap();
+    assert_eq!(res.reward_state.staked_weight, 2);
+
+    // Early unstaking is rejected without a penalty
+    let unstake_msg = ExecuteMsg::Unstake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_msg, &[]);
+    assert!(res.is_err());
+
+    // Early unstaking requires the exact fee once a fee penalty is set
+    let update_lock_config_msg = ExecuteMsg::UpdateLockConfig {
+        lock_tiers: vec![LockTier { duration: 100, reward_weight: 2 }],
+        early_unstake_penalty: Some(EarlyUnstakePenalty::Fee {
+            fee: Coin { denom: NATIVE_DENOM.to_string(), amount: Uint128::from(10u128) },
+            recipient: creator.clone(),
+        }),
+    };
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &update_lock_config_msg, &[]);
+    assert!(res.is_ok());
+
+    setup_block_time(&mut router, block_time.plus_seconds(50).seconds());
+    let creator_balance = router.wrap().query_balance(creator.clone(), NATIVE_DENOM).unwrap();
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_msg, &[]);
+    assert!(res.is_err());
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_msg, &coins(5, NATIVE_DENOM));
+    assert!(res.is_err());
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_msg, &coins(10, NATIVE_DENOM));
+    assert!(res.is_ok());
+
+    let res = router.wrap().query_balance(creator.clone(), NATIVE_DENOM).unwrap();
+    assert_eq!(res.amount, creator_balance.amount + Uint128::from(10u128));
+    let res: RewardsResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &QueryMsg::Rewards {})
+        .unwrap();
+    assert_eq!(res.reward_state.staked_weight, 0);
+
+    // No fee is charged once the lock has ended
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+    assert!(res.is_ok());
+    setup_block_time(&mut router, block_time.plus_seconds(150).seconds());
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_msg, &coins(10, NATIVE_DENOM));
+    assert!(res.is_err());
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_msg, &[]);
+    assert!(res.is_ok());
+}
+
+#[test]
+fn try_early_unstake_burn() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+
+    // Setup intial accounts
+    let (owner, creator) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate a vault with a force timelock that burns early unstaked tokens
+    let (collection, _) = setup_contracts(&mut router, &creator).unwrap();
+    let nft_vault_id = router.store_code(contract_nft_vault());
+    let msg = NftVaultInstantiateMsg {
+        collections: vec![collection.to_string()],
+        label: String::from("Burning Vault"),
+        unstake_period: UNSTAKE_PERIOD,
+        receipt: None,
+        force_timelock: Some(100),
+    };
+    let nft_vault = router
+        .instantiate_contract(nft_vault_id, creator.clone(), &msg, &[], "Burning Vault", None)
+        .unwrap();
+    let update_lock_config_msg = ExecuteMsg::UpdateLockConfig {
+        lock_tiers: vec![LockTier { duration: 100, reward_weight: 2 }],
+        early_unstake_penalty: Some(EarlyUnstakePenalty::Burn),
+    };
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &update_lock_config_msg, &[]);
+    assert!(res.is_ok());
+
+    let mock_hook_id = router.store_code(contract_mock_hook());
+    let hook = router
+        .instantiate_contract(mock_hook_id, creator.clone(), &Empty {}, &[], "hook", None)
+        .unwrap();
+    for add_hook_msg in [
+        ExecuteMsg::AddUnstakeHook { hook: hook.to_string(), required: None },
+        ExecuteMsg::AddWithdrawHook { hook: hook.to_string(), required: None },
+    ] {
+        let res = router.execute_contract(creator.clone(), nft_vault.clone(), &add_hook_msg, &[]);
+        assert!(res.is_ok());
+    }
+
+    mint(&mut router, &creator, &owner, &collection, TOKEN_ID_A.to_string());
+    approve(&mut router, &owner, &collection, &nft_vault, TOKEN_ID_A.to_string());
+    let stake_msg = ExecuteMsg::Stake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+        lock_duration: Some(100),
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+    assert!(res.is_ok());
+
+    // An operator schedules a force unstake
+    let force_unstake_msg = ExecuteMsg::ForceUnstake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+        reason: String::from("broke community rules"),
+    };
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &force_unstake_msg, &[]);
+    assert!(res.is_ok());
+
+    // Unstaking early burns the token, which is withdrawn from the vault
+    setup_block_time(&mut router, block_time.plus_seconds(50).seconds());
+    let unstake_msg = ExecuteMsg::Unstake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_msg, &[]);
+    assert!(res.is_ok());
+
+    let res: StdResult<OwnerOfResponse> = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &Cw721QueryMsg::OwnerOf {
+            token_id: TOKEN_ID_A.to_string(),
+            include_expired: None,
+        });
+    assert!(res.is_err());
+    let received: u32 = router.wrap().query_wasm_smart(hook.clone(), &Empty {}).unwrap();
+    assert_eq!(received, 2);
+    let res: ForceActionResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &QueryMsg::ForceAction {
+            collection: collection.to_string(),
+            token_id: TOKEN_ID_A.to_string(),
+        })
+        .unwrap();
+    assert_eq!(res.force_action, None);
+    let res: VaultSummaryResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &QueryMsg::VaultSummary {})
+        .unwrap();
+    assert_eq!(res, VaultSummaryResponse { staked: 0, unstaking: 0, transferrable: 0, total: 0, num_stakers: 0 });
+}
+
+// Instantiates a vault that mints staking receipts, returning the vault and receipt collection
+fn setup_liquid_vault(router: &mut App, creator: &Addr, collection: &Addr) -> (Addr, Addr) {
+    let pg721_id = router.store_code(contract_pg721());
+    let nft_vault_id = router.store_code(contract_nft_vault());
+    let msg = NftVaultInstantiateMsg {
+        collections: vec![collection.to_string()],
+        label: String::from("Liquid Vault"),
+        unstake_period: UNSTAKE_PERIOD,
+        receipt: Some(ReceiptMsg {
+            code_id: pg721_id,
+            instantiate_msg: Pg721InstantiateMsg {
+                name: String::from("Staked Test Coin"),
+                symbol: String::from("STEST"),
+                minter: creator.to_string(),
+                collection_info: CollectionInfo {
+                    creator: creator.to_string(),
+                    description: String::from("Staked Passage Monkeys"),
+                    image: "ipfs://bafybeigi3bwpvyvsmnbj46ra4hyffcxdeaj6ntfk5jpic5mx27x6ih2qvq/images/1.png".to_string(),
+                    external_link: None,
+                    royalty_info: None,
+                },
+            },
+        }),
+        force_timelock: None,
+    };
+    let nft_vault = router
+        .instantiate_contract(nft_vault_id, creator.clone(), &msg, &[], "Liquid Vault", None)
+        .unwrap();
+
+    let res: ConfigResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &QueryMsg::Config {})
+        .unwrap();
+    (nft_vault, Addr::unchecked(res.receipt_collection.unwrap()))
+}
+
+#[test]
+fn try_liquid_staking_receipts() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+
+    // Setup intial accounts
+    let (owner, creator) = setup_accounts(&mut router).unwrap();
+    let buyer = Addr::unchecked("buyer");
+
+    // Instantiate a vault that mints staking receipts
+    let (collection, _) = setup_contracts(&mut router, &creator).unwrap();
+    let (nft_vault, receipt_collection) = setup_liquid_vault(&mut router, &creator, &collection);
+
+    let configure_rewards_msg = ExecuteMsg::ConfigureRewards {
+        denom: cw20::Denom::Native(NATIVE_DENOM.to_string()),
+        emission_rate: Uint128::from(1u128),
+    };
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &configure_rewards_msg, &[]);
+    assert!(res.is_ok());
+    router
+        .sudo(CwSudoMsg::Bank({
+            BankSudo::Mint {
+                to_address: creator.to_string(),
+                amount: coins(100, NATIVE_DENOM),
+            }
+        }))
+        .unwrap();
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &ExecuteMsg::FundRewards {}, &coins(100, NATIVE_DENOM));
+    assert!(res.is_ok());
+
+    // Staking mints a receipt to the staker
+    mint(&mut router, &creator, &owner, &collection, TOKEN_ID_A.to_string());
+    approve(&mut router, &owner, &collection, &nft_vault, TOKEN_ID_A.to_string());
+    let stake_msg = ExecuteMsg::Stake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+        lock_duration: None,
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+    assert!(res.is_ok());
+
+    let receipt_token_id = format!("{}/{}", collection, TOKEN_ID_A);
+    let query_receipt_owner_msg = Cw721QueryMsg::OwnerOf {
+        token_id: receipt_token_id.clone(),
+        include_expired: None,
+    };
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(receipt_collection.clone(), &query_receipt_owner_msg)
+        .unwrap();
+    assert_eq!(res.owner, owner.to_string());
+
+    // Sending the receipt to the vault transfers the staking position
+    let send_receipt_msg = Pg721ExecuteMsg::SendNft {
+        contract: nft_vault.to_string(),
+        token_id: receipt_token_id.clone(),
+        msg: to_binary(&ReceiveNftMsg::TransferPosition { recipient: buyer.to_string() }).unwrap(),
+    };
+    let res = router.execute_contract(owner.clone(), receipt_collection.clone(), &send_receipt_msg, &[]);
+    assert!(res.is_ok());
+
+    // Owner queries follow the transferred position
+    setup_block_time(&mut router, block_time.plus_seconds(10).seconds());
+    for (holder, expected) in [(&owner, 0u64), (&buyer, 1)] {
+        let res: VaultTokensResponse = router
+            .wrap()
+            .query_wasm_smart(nft_vault.clone(), &QueryMsg::VaultTokensByOwner {
+                owner: holder.to_string(),
+                query_options: QueryOptions { descending: None, start_after: None, limit: None },
+            })
+            .unwrap();
+        assert_eq!(res.vault_tokens.len() as u64, expected);
+        let res: OwnerSummaryResponse = router
+            .wrap()
+            .query_wasm_smart(nft_vault.clone(), &QueryMsg::OwnerSummary { owner: holder.to_string() })
+            .unwrap();
+        assert_eq!(res, OwnerSummaryResponse { staked: expected, unstaking: 0, transferrable: 0, total: expected });
+        assert_eq!(query_pending_rewards(&router, &nft_vault, holder), Uint128::from(10 * expected as u128));
+    }
+
+    let unstake_msg = ExecuteMsg::Unstake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_msg, &[]);
+    assert!(res.is_err());
+    let res = router.execute_contract(buyer.clone(), nft_vault.clone(), &unstake_msg, &[]);
+    assert!(res.is_ok());
+
+    let query_vault_token_msg = QueryMsg::VaultToken {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+    };
+    let res: VaultTokenResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &query_vault_token_msg)
+        .unwrap();
+    assert_eq!(res.vault_token.unwrap().owner, buyer);
+
+    // The holder withdraws by sending the receipt to the vault, which burns it
+    setup_block_time(&mut router, block_time.plus_seconds(10 + UNSTAKE_PERIOD).seconds());
+    let withdraw_msg = ExecuteMsg::Withdraw {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+    };
+    let res = router.execute_contract(buyer.clone(), nft_vault.clone(), &withdraw_msg, &[]);
+    assert!(res.is_err());
+    let send_receipt_msg = Pg721ExecuteMsg::SendNft {
+        contract: nft_vault.to_string(),
+        token_id: receipt_token_id.clone(),
+        msg: to_binary(&ReceiveNftMsg::Withdraw {}).unwrap(),
+    };
+    let res = router.execute_contract(buyer.clone(), receipt_collection.clone(), &send_receipt_msg, &[]);
+    assert!(res.is_ok());
+
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &Cw721QueryMsg::OwnerOf {
+            token_id: TOKEN_ID_A.to_string(),
+            include_expired: None,
+        })
+        .unwrap();
+    assert_eq!(res.owner, buyer.to_string());
+    let res: StdResult<OwnerOfResponse> = router
+        .wrap()
+        .query_wasm_smart(receipt_collection.clone(), &query_receipt_owner_msg);
+    assert!(res.is_err());
+
+    // Unstaking early with the burn penalty also burns the receipt, which is sent to the vault
+    let update_lock_config_msg = ExecuteMsg::UpdateLockConfig {
+        lock_tiers: vec![LockTier { duration: 100, reward_weight: 2 }],
+        early_unstake_penalty: Some(EarlyUnstakePenalty::Burn),
+    };
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &update_lock_config_msg, &[]);
+    assert!(res.is_ok());
+    mint(&mut router, &creator, &owner, &collection, TOKEN_ID_B.to_string());
+    approve(&mut router, &owner, &collection, &nft_vault, TOKEN_ID_B.to_string());
+    let stake_msg = ExecuteMsg::Stake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_B.to_string(),
+        lock_duration: Some(100),
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+    assert!(res.is_ok());
+
+    let unstake_msg = ExecuteMsg::Unstake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_B.to_string(),
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_msg, &[]);
+    assert!(res.is_err());
+    let receipt_token_id = format!("{}/{}", collection, TOKEN_ID_B);
+    let send_receipt_msg = Pg721ExecuteMsg::SendNft {
+        contract: nft_vault.to_string(),
+        token_id: receipt_token_id.clone(),
+        msg: to_binary(&ReceiveNftMsg::Unstake {}).unwrap(),
+    };
+    let res = router.execute_contract(owner.clone(), receipt_collection.clone(), &send_receipt_msg, &[]);
+    assert!(res.is_ok());
+
+    let res: StdResult<OwnerOfResponse> = router
+        .wrap()
+        .query_wasm_smart(receipt_collection.clone(), &Cw721QueryMsg::OwnerOf {
+            token_id: receipt_token_id,
+            include_expired: None,
+        });
+    assert!(res.is_err());
+}
+
+#[test]
+fn try_force_withdraw_liquid_staking() {
+    let mut router = custom_mock_app();
+
+    // Setup intial accounts
+    let (owner, creator) = setup_accounts(&mut router).unwrap();
+    let buyer = Addr::unchecked("buyer");
+
+    // Instantiate a vault that mints staking receipts
+    let (collection, _) = setup_contracts(&mut router, &creator).unwrap();
+    let (nft_vault, receipt_collection) = setup_liquid_vault(&mut router, &creator, &collection);
+
+    for token_id in [TOKEN_ID_A, TOKEN_ID_B] {
+        mint(&mut router, &creator, &owner, &collection, token_id.to_string());
+        approve(&mut router, &owner, &collection, &nft_vault, token_id.to_string());
+        let stake_msg = ExecuteMsg::Stake {
+            collection: collection.to_string(),
+            token_id: token_id.to_string(),
+            lock_duration: None,
+        };
+        let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+        assert!(res.is_ok());
+
+        let transfer_msg = Pg721ExecuteMsg::TransferNft {
+            recipient: buyer.to_string(),
+            token_id: format!("{}/{}", collection, token_id),
+        };
+        let res = router.execute_contract(owner.clone(), receipt_collection.clone(), &transfer_msg, &[]);
+        assert!(res.is_ok());
+    }
+
+    // A force unstake records the receipt holder as the owner of the position
+    let force_unstake_msg = ExecuteMsg::ForceUnstake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+        reason: String::from("broke community rules"),
+    };
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &force_unstake_msg, &[]);
+    assert!(res.is_ok());
+    let res: VaultTokenResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &QueryMsg::VaultToken {
+            collection: collection.to_string(),
+            token_id: TOKEN_ID_A.to_string(),
+        })
+        .unwrap();
+    assert_eq!(res.vault_token.unwrap().owner, buyer);
+
+    // A force withdraw voids the receipt, which no longer represents a position
+    let force_withdraw_msg = ExecuteMsg::ForceWithdraw {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_B.to_string(),
+        recipient: owner.to_string(),
+        reason: String::from("compromised wallet"),
+    };
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &force_withdraw_msg, &[]);
+    assert!(res.is_ok());
+    let res: OwnerSummaryResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &QueryMsg::OwnerSummary { owner: buyer.to_string() })
+        .unwrap();
+    assert_eq!(res, OwnerSummaryResponse { staked: 0, unstaking: 1, transferrable: 0, total: 1 });
+
+    // Re-staking the token mints a receipt with a new id
+    approve(&mut router, &owner, &collection, &nft_vault, TOKEN_ID_B.to_string());
+    let stake_msg = ExecuteMsg::Stake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_B.to_string(),
+        lock_duration: None,
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+    assert!(res.is_ok());
+
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(receipt_collection.clone(), &Cw721QueryMsg::OwnerOf {
+            token_id: format!("1/{}/{}", collection, TOKEN_ID_B),
+            include_expired: None,
+        })
+        .unwrap();
+    assert_eq!(res.owner, owner.to_string());
+
+    // Only the holder of the new receipt owns the position
+    let unstake_msg = ExecuteMsg::Unstake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_B.to_string(),
+    };
+    let res = router.execute_contract(buyer.clone(), nft_vault.clone(), &unstake_msg, &[]);
+    assert!(res.is_err());
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_msg, &[]);
+    assert!(res.is_ok());
+}
+
+#[test]
+fn try_liquid_position_transfers() {
+    let mut router = custom_mock_app();
+    let start_height = router.block_info().height;
+
+    // Setup intial accounts
+    let (owner, creator) = setup_accounts(&mut router).unwrap();
+    let buyer = Addr::unchecked("buyer");
+
+    // Instantiate a vault that mints staking receipts
+    let (collection, _) = setup_contracts(&mut router, &creator).unwrap();
+    let (nft_vault, receipt_collection) = setup_liquid_vault(&mut router, &creator, &collection);
+
+    router.update_block(|block| block.height += 1);
+    mint(&mut router, &creator, &owner, &collection, TOKEN_ID_A.to_string());
+    approve(&mut router, &owner, &collection, &nft_vault, TOKEN_ID_A.to_string());
+    let stake_msg = ExecuteMsg::Stake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+        lock_duration: None,
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+    assert!(res.is_ok());
+
+    // Sending the receipt to the vault transfers the position with its voting power
+    router.update_block(|block| block.height += 1);
+    let receipt_token_id = format!("{}/{}", collection, TOKEN_ID_A);
+    let send_receipt_msg = Pg721ExecuteMsg::SendNft {
+        contract: nft_vault.to_string(),
+        token_id: receipt_token_id.clone(),
+        msg: to_binary(&ReceiveNftMsg::TransferPosition { recipient: buyer.to_string() }).unwrap(),
+    };
+    let res = router.execute_contract(buyer.clone(), receipt_collection.clone(), &send_receipt_msg, &[]);
+    assert!(res.is_err());
+    let res = router.execute_contract(owner.clone(), receipt_collection.clone(), &send_receipt_msg, &[]);
+    assert!(res.is_ok());
+    router.update_block(|block| block.height += 1);
+
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(receipt_collection.clone(), &Cw721QueryMsg::OwnerOf {
+            token_id: receipt_token_id,
+            include_expired: None,
+        })
+        .unwrap();
+    assert_eq!(res.owner, buyer.to_string());
+    let res: VaultTokenResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &QueryMsg::VaultToken {
+            collection: collection.to_string(),
+            token_id: TOKEN_ID_A.to_string(),
+        })
+        .unwrap();
+    assert_eq!(res.vault_token.unwrap().owner, buyer);
+
+    // Balances are reported as of the start of each height
+    for (offset, owner_weight, buyer_weight) in [(1u64, 0u64, 0u64), (2, 1, 0), (3, 0, 1)] {
+        for (addr, expected) in [(&owner, owner_weight), (&buyer, buyer_weight)] {
+            let res: StakedBalanceAtHeightResponse = router
+                .wrap()
+                .query_wasm_smart(nft_vault.clone(), &QueryMsg::StakedBalanceAtHeight {
+                    owner: addr.to_string(),
+                    height: Some(start_height + offset),
+                })
+                .unwrap();
+            assert_eq!(res.weight, expected);
+        }
+        let res: TotalStakedAtHeightResponse = router
+            .wrap()
+            .query_wasm_smart(nft_vault.clone(), &QueryMsg::TotalStakedAtHeight {
+                height: Some(start_height + offset),
+            })
+            .unwrap();
+        assert_eq!(res.weight, owner_weight + buyer_weight);
+    }
+}
+
+#[test]
+fn try_voting_power_snapshots() {
+    let mut router = custom_mock_app();
+    let start_height = router.block_info().height;
+
+    // Setup intial accounts
+    let (owner, creator) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (collection, nft_vault) = setup_contracts(&mut router, &creator).unwrap();
+
+    for token_id in [TOKEN_ID_A, TOKEN_ID_B] {
+        mint(&mut router, &creator, &owner, &collection, token_id.to_string());
+        approve(&mut router, &owner, &collection, &nft_vault, token_id.to_string());
+    }
+
+    // Stake a token in each of two blocks, then unstake one in a third
+    for token_id in [TOKEN_ID_A, TOKEN_ID_B] {
+        router.update_block(|block| block.height += 1);
+        let stake_msg = ExecuteMsg::Stake {
+            collection: collection.to_string(),
+            token_id: token_id.to_string(),
+            lock_duration: None,
+        };
+        let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+        assert!(res.is_ok());
+    }
+    router.update_block(|block| block.height += 1);
+    let unstake_msg = ExecuteMsg::Unstake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_msg, &[]);
+    assert!(res.is_ok());
+    router.update_block(|block| block.height += 1);
+
+    // Balances are reported as of the start of each height
+    for (offset, expected) in [(1u64, 0u64), (2, 1), (3, 2), (4, 1)] {
+        let res: StakedBalanceAtHeightResponse = router
+            .wrap()
+            .query_wasm_smart(nft_vault.clone(), &QueryMsg::StakedBalanceAtHeight {
+                owner: owner.to_string(),
+                height: Some(start_height + offset),
+            })
+            .unwrap();
+        assert_eq!(res.weight, expected);
+        let res: TotalStakedAtHeightResponse = router
+            .wrap()
+            .query_wasm_smart(nft_vault.clone(), &QueryMsg::TotalStakedAtHeight {
+                height: Some(start_height + offset),
+            })
+            .unwrap();
+        assert_eq!(res.weight, expected);
+    }
+
+    let res: StakedBalanceAtHeightResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &QueryMsg::StakedBalanceAtHeight {
+            owner: creator.to_string(),
+            height: None,
+        })
+        .unwrap();
+    assert_eq!(res, StakedBalanceAtHeightResponse { weight: 0, height: start_height + 4 });
+}
+
+#[test]
+fn try_force_actions() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+
+    // Setup intial accounts
+    let (owner, creator) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate a vault with a force timelock
+    let (collection, _) = setup_contracts(&mut router, &creator).unwrap();
+    let nft_vault_id = router.store_code(contract_nft_vault());
+    let msg = NftVaultInstantiateMsg {
+        collections: vec![collection.to_string()],
+        label: String::from("Timelocked Vault"),
+        unstake_period: UNSTAKE_PERIOD,
+        receipt: None,
+        force_timelock: Some(100),
+    };
+    let nft_vault = router
+        .instantiate_contract(nft_vault_id, creator.clone(), &msg, &[], "Timelocked Vault", None)
+        .unwrap();
+
+    for token_id in [TOKEN_ID_A, TOKEN_ID_B] {
+        mint(&mut router, &creator, &owner, &collection, token_id.to_string());
+        approve(&mut router, &owner, &collection, &nft_vault, token_id.to_string());
+        let stake_msg = ExecuteMsg::Stake {
+            collection: collection.to_string(),
+            token_id: token_id.to_string(),
+            lock_duration: None,
+        };
+        let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+        assert!(res.is_ok());
+    }
+
+    // Only operators can force an unstake, and a reason is required
+    let mut force_unstake_msg = ExecuteMsg::ForceUnstake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+        reason: String::from(""),
+    };
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &force_unstake_msg, &[]);
+    assert!(res.is_err());
+    if let ExecuteMsg::ForceUnstake { ref mut reason, .. } = force_unstake_msg {
+        *reason = String::from("broke community rules");
+    }
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &force_unstake_msg, &[]);
+    assert!(res.is_err());
+
+    // The first call schedules the unstake, which can be executed once the timelock has passed
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &force_unstake_msg, &[]);
+    assert!(res.is_ok());
+    let res: ForceActionResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &QueryMsg::ForceAction {
+            collection: collection.to_string(),
+            token_id: TOKEN_ID_A.to_string(),
+        })
+        .unwrap();
+    assert_eq!(res.force_action.unwrap().executable_at, block_time.plus_seconds(100));
+
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &force_unstake_msg, &[]);
+    assert!(res.is_err());
+    setup_block_time(&mut router, block_time.plus_seconds(100).seconds());
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &force_unstake_msg, &[]);
+    assert!(res.is_ok());
+
+    let query_vault_token_msg = QueryMsg::VaultToken {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+    };
+    let res: VaultTokenResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &query_vault_token_msg)
+        .unwrap();
+    assert_eq!(res.vault_token.unwrap().unstake_timestamp, Some(block_time.plus_seconds(100)));
+
+    // A staked token can be forced out to a recipient
+    let force_withdraw_msg = ExecuteMsg::ForceWithdraw {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_B.to_string(),
+        recipient: creator.to_string(),
+        reason: String::from("compromised wallet"),
+    };
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &force_withdraw_msg, &[]);
+    assert!(res.is_ok());
+    setup_block_time(&mut router, block_time.plus_seconds(200).seconds());
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &force_withdraw_msg, &[]);
+    assert!(res.is_ok());
+
+    let res: OwnerOfResponse = router
+        .wrap()
+        .query_wasm_smart(collection.clone(), &Cw721QueryMsg::OwnerOf {
+            token_id: TOKEN_ID_B.to_string(),
+            include_expired: None,
+        })
+        .unwrap();
+    assert_eq!(res.owner, creator.to_string());
+    let res: TotalStakedAtHeightResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &QueryMsg::TotalStakedAtHeight { height: None })
+        .unwrap();
+    assert_eq!(res.weight, 0);
+}
+
+#[test]
+fn try_summary_queries() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+
+    // Setup intial accounts
+    let (owner, creator) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (collection, nft_vault) = setup_contracts(&mut router, &creator).unwrap();
+
+    for token_id in [TOKEN_ID_A, TOKEN_ID_B] {
+        mint(&mut router, &creator, &owner, &collection, token_id.to_string());
+        approve(&mut router, &owner, &collection, &nft_vault, token_id.to_string());
+        let stake_msg = ExecuteMsg::Stake {
+            collection: collection.to_string(),
+            token_id: token_id.to_string(),
+            lock_duration: None,
+        };
+        let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_msg, &[]);
+        assert!(res.is_ok());
+    }
+    let unstake_msg = ExecuteMsg::Unstake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_msg, &[]);
+    assert!(res.is_ok());
+
+    let query_owner_summary_msg = QueryMsg::OwnerSummary { owner: owner.to_string() };
+    let res: OwnerSummaryResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &query_owner_summary_msg)
+        .unwrap();
+    assert_eq!(res, OwnerSummaryResponse { staked: 1, unstaking: 1, transferrable: 0, total: 2 });
+
+    // Unstaking tokens become transferrable once the unstake period has passed
+    setup_block_time(&mut router, block_time.plus_seconds(UNSTAKE_PERIOD).seconds());
+    let res: VaultSummaryResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &QueryMsg::VaultSummary {})
+        .unwrap();
+    assert_eq!(res, VaultSummaryResponse { staked: 1, unstaking: 0, transferrable: 1, total: 2, num_stakers: 1 });
+
+    let withdraw_msg = ExecuteMsg::Withdraw {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_A.to_string(),
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &withdraw_msg, &[]);
+    assert!(res.is_ok());
+    let unstake_msg = ExecuteMsg::Unstake {
+        collection: collection.to_string(),
+        token_id: TOKEN_ID_B.to_string(),
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_msg, &[]);
+    assert!(res.is_ok());
+
+    let res: OwnerSummaryResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &query_owner_summary_msg)
+        .unwrap();
+    assert_eq!(res, OwnerSummaryResponse { staked: 0, unstaking: 1, transferrable: 0, total: 1 });
+    let res: VaultSummaryResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &QueryMsg::VaultSummary {})
+        .unwrap();
+    assert_eq!(res, VaultSummaryResponse { staked: 0, unstaking: 1, transferrable: 0, total: 1, num_stakers: 0 });
+}
+
+#[test]
+fn try_batch_unstake_and_withdraw() {
+    let mut router = custom_mock_app();
+    let block_time = router.block_info().time;
+
+    // Setup intial accounts
+    let (owner, creator) = setup_accounts(&mut router).unwrap();
+
+    // Instantiate and configure contracts
+    let (collection, nft_vault) = setup_contracts(&mut router, &creator).unwrap();
+
+    let mock_hook_id = router.store_code(contract_mock_hook());
+    let hook = router
+        .instantiate_contract(mock_hook_id, creator.clone(), &Empty {}, &[], "hook", None)
+        .unwrap();
+    for add_hook_msg in [
+        ExecuteMsg::AddUnstakeHook { hook: hook.to_string(), required: None },
+        ExecuteMsg::AddWithdrawHook { hook: hook.to_string(), required: None },
+    ] {
+        let res = router.execute_contract(creator.clone(), nft_vault.clone(), &add_hook_msg, &[]);
+        assert!(res.is_ok());
+    }
+
+    let token_ids: Vec<String> = (1..=4).map(|n| n.to_string()).collect();
+    for token_id in &token_ids {
+        mint(&mut router, &creator, &owner, &collection, token_id.to_string());
+        approve(&mut router, &owner, &collection, &nft_vault, token_id.to_string());
+    }
+    let stake_many_msg = ExecuteMsg::StakeMany {
+        collection: collection.to_string(),
+        token_ids: token_ids.clone(),
+        lock_duration: None,
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &stake_many_msg, &[]);
+    assert!(res.is_ok());
+
+    // Only the owner can unstake, and hooks receive a single batch message
+    let unstake_many_msg = ExecuteMsg::UnstakeMany {
+        collection: collection.to_string(),
+        token_ids: token_ids[..3].to_vec(),
+    };
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &unstake_many_msg, &[]);
+    assert!(res.is_err());
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &unstake_many_msg, &[]);
+    assert!(res.is_ok());
+    let received: u32 = router.wrap().query_wasm_smart(hook.clone(), &Empty {}).unwrap();
+    assert_eq!(received, 1);
+
+    // Tokens can only be withdrawn once transferrable
+    let withdraw_many_msg = ExecuteMsg::WithdrawMany {
+        collection: collection.to_string(),
+        token_ids: token_ids[..1].to_vec(),
+    };
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &withdraw_many_msg, &[]);
+    assert!(res.is_err());
+    setup_block_time(&mut router, block_time.plus_seconds(UNSTAKE_PERIOD).seconds());
+    let res = router.execute_contract(owner.clone(), nft_vault.clone(), &withdraw_many_msg, &[]);
+    assert!(res.is_ok());
+
+    // Anyone can withdraw the remaining transferrable tokens to their owner
+    let withdraw_all_msg = ExecuteMsg::WithdrawAllTransferrable { limit: None };
+    let res = router.execute_contract(creator.clone(), nft_vault.clone(), &withdraw_all_msg, &[]);
+    assert!(res.is_ok());
+    let received: u32 = router.wrap().query_wasm_smart(hook.clone(), &Empty {}).unwrap();
+    assert_eq!(received, 3);
+
+    for (token_id, expected_owner) in token_ids.iter().zip([&owner, &owner, &owner, &nft_vault]) {
+        let res: OwnerOfResponse = router
+            .wrap()
+            .query_wasm_smart(collection.clone(), &Cw721QueryMsg::OwnerOf {
+                token_id: token_id.to_string(),
+                include_expired: None,
+            })
+            .unwrap();
+        assert_eq!(res.owner, expected_owner.to_string());
+    }
+
+    let res: FailedHooksResponse = router
+        .wrap()
+        .query_wasm_smart(nft_vault.clone(), &QueryMsg::FailedHooks { start_after: None, limit: None })
+        .unwrap();
+    assert!(res.failed_hooks.is_empty());
+}
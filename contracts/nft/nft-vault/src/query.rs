@@ -1,13 +1,20 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{to_binary, Binary, Deps, Env, StdResult, Addr};
+use cosmwasm_std::{to_binary, Binary, Deps, Env, Order, StdResult, Addr, Uint128};
 use cw_storage_plus::Bound;
 use crate::msg::{
     ConfigResponse, VaultTokenResponse, VaultTokensResponse, QueryMsg, QueryOptions,
-    TokenTimestampOffset
+    TokenTimestampOffset, RewardsResponse, PendingRewardsResponse, FailedHooksResponse,
+    StakedBalanceAtHeightResponse, TotalStakedAtHeightResponse, ForceActionResponse,
+    OwnerSummaryResponse, VaultSummaryResponse
 };
-use crate::state::{vault_tokens, CONFIG, STAKE_HOOKS, UNSTAKE_HOOKS, WITHDRAW_HOOKS};
-use crate::helpers::{option_bool_to_order};
+use crate::state::{
+    vault_tokens, CONFIG, STAKE_HOOKS, UNSTAKE_HOOKS, WITHDRAW_HOOKS, REWARD_CONFIG, REWARD_STATE,
+    VaultTokenStatus, vault_token_key, FAILED_HOOKS, RECEIPT_COLLECTION, STAKED_BALANCES,
+    TOTAL_STAKED, FORCE_ACTIONS, NUM_STAKERS, UNSTAKED_BALANCES, TOTAL_UNSTAKED, OWNER_UNSTAKES,
+    UNSTAKES
+};
+use crate::helpers::{option_bool_to_order, accrue_rewards, settle_token_reward};
 
 // Query limits
 const DEFAULT_QUERY_LIMIT: u32 = 10;
@@ -21,17 +28,38 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::StakeHooks {} => to_binary(&STAKE_HOOKS.query_hooks(deps)?),
         QueryMsg::UnstakeHooks {} => to_binary(&UNSTAKE_HOOKS.query_hooks(deps)?),
         QueryMsg::WithdrawHooks {} => to_binary(&WITHDRAW_HOOKS.query_hooks(deps)?),
-        QueryMsg::VaultToken { token_id } => to_binary(&query_vault_token(deps, env, token_id)?),
+        QueryMsg::VaultToken {
+            collection,
+            token_id
+        } => to_binary(&query_vault_token(deps, env, api.addr_validate(&collection)?, token_id)?),
         QueryMsg::VaultTokensByOwner {
             owner,
             query_options
         } => to_binary(&query_vault_tokens_by_owner(deps, api.addr_validate(&owner)?, &query_options)?),
+        QueryMsg::VaultTokensByCollectionStakeTimestamp {
+            collection,
+            query_options
+        } => to_binary(&query_vault_tokens_by_collection_stake_timestamp(deps, api.addr_validate(&collection)?, &query_options)?),
         QueryMsg::VaultTokensByStakeTimestamp {
             query_options
         } => to_binary(&query_vault_tokens_by_stake_timestamp(deps, &query_options)?),
         QueryMsg::VaultTokensByUnstakeTimestamp {
             query_options
         } => to_binary(&query_vault_tokens_by_unstake_timestamp(deps, &query_options)?),
+        QueryMsg::Rewards {} => to_binary(&query_rewards(deps, env)?),
+        QueryMsg::PendingRewards { owner } => to_binary(&query_pending_rewards(deps, env, api.addr_validate(&owner)?)?),
+        QueryMsg::FailedHooks { start_after, limit } => to_binary(&query_failed_hooks(deps, start_after, limit)?),
+        QueryMsg::StakedBalanceAtHeight {
+            owner,
+            height
+        } => to_binary(&query_staked_balance_at_height(deps, env, api.addr_validate(&owner)?, height)?),
+        QueryMsg::TotalStakedAtHeight { height } => to_binary(&query_total_staked_at_height(deps, env, height)?),
+        QueryMsg::ForceAction {
+            collection,
+            token_id
+        } => to_binary(&query_force_action(deps, api.addr_validate(&collection)?, token_id)?),
+        QueryMsg::OwnerSummary { owner } => to_binary(&query_owner_summary(deps, env, api.addr_validate(&owner)?)?),
+        QueryMsg::VaultSummary {} => to_binary(&query_vault_summary(deps, env)?),
     }
 }
 
@@ -39,14 +67,18 @@ fn query_config(deps: Deps, _env: Env) -> StdResult<ConfigResponse> {
     let config = CONFIG.load(deps.storage)?;
     Ok(ConfigResponse {
         operators: config.operators.iter().map(|addr| addr.to_string()).collect::<Vec<String>>(),
-        cw721_address: config.cw721_address.to_string(),
+        collections: config.collections.iter().map(|addr| addr.to_string()).collect::<Vec<String>>(),
         label: config.label,
         unstake_period: config.unstake_period,
+        lock_tiers: config.lock_tiers,
+        early_unstake_penalty: config.early_unstake_penalty,
+        receipt_collection: RECEIPT_COLLECTION.may_load(deps.storage)?.map(|addr| addr.to_string()),
+        force_timelock: config.force_timelock,
     })
 }
 
-fn query_vault_token(deps: Deps, _env: Env, token_id: String) -> StdResult<VaultTokenResponse> {
-    let vault_token = vault_tokens().may_load(deps.storage, token_id)?;
+fn query_vault_token(deps: Deps, _env: Env, collection: Addr, token_id: String) -> StdResult<VaultTokenResponse> {
+    let vault_token = vault_tokens().may_load(deps.storage, vault_token_key(&collection, &token_id))?;
     Ok(VaultTokenResponse { vault_token })
 }
 
@@ -57,7 +89,7 @@ pub fn query_vault_tokens_by_owner(
 ) -> StdResult<VaultTokensResponse> {
     let limit = query_options.limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
     let start = query_options.start_after.as_ref().map(|offset| {
-        Bound::exclusive((offset.timestamp.seconds(), offset.token_id.clone()))
+        Bound::exclusive((offset.timestamp.seconds(), vault_token_key(&offset.collection, &offset.token_id)))
     });
     let order = option_bool_to_order(query_options.descending);
 
@@ -73,13 +105,36 @@ pub fn query_vault_tokens_by_owner(
     Ok(VaultTokensResponse { vault_tokens })
 }
 
+pub fn query_vault_tokens_by_collection_stake_timestamp(
+    deps: Deps,
+    collection: Addr,
+    query_options: &QueryOptions<TokenTimestampOffset>
+) -> StdResult<VaultTokensResponse> {
+    let limit = query_options.limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
+    let start = query_options.start_after.as_ref().map(|offset| {
+        Bound::exclusive((offset.timestamp.seconds(), vault_token_key(&offset.collection, &offset.token_id)))
+    });
+    let order = option_bool_to_order(query_options.descending);
+
+    let vault_tokens = vault_tokens()
+        .idx
+        .collection_stake_timestamp
+        .sub_prefix(collection)
+        .range(deps.storage, start, None, order)
+        .take(limit)
+        .map(|res| res.map(|item| item.1))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(VaultTokensResponse { vault_tokens })
+}
+
 pub fn query_vault_tokens_by_stake_timestamp(
     deps: Deps,
     query_options: &QueryOptions<TokenTimestampOffset>
 ) -> StdResult<VaultTokensResponse> {
     let limit = query_options.limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
     let start = query_options.start_after.as_ref().map(|offset| {
-        Bound::exclusive((offset.timestamp.seconds(), offset.token_id.clone()))
+        Bound::exclusive((offset.timestamp.seconds(), vault_token_key(&offset.collection, &offset.token_id)))
     });
     let order = option_bool_to_order(query_options.descending);
 
@@ -100,7 +155,7 @@ pub fn query_vault_tokens_by_unstake_timestamp(
 ) -> StdResult<VaultTokensResponse> {
     let limit = query_options.limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
     let start = query_options.start_after.as_ref().map(|offset| {
-        Bound::exclusive((offset.timestamp.seconds(), offset.token_id.clone()))
+        Bound::exclusive((offset.timestamp.seconds(), vault_token_key(&offset.collection, &offset.token_id)))
     });
     let order = option_bool_to_order(query_options.descending);
 
@@ -114,3 +169,141 @@ pub fn query_vault_tokens_by_unstake_timestamp(
 
     Ok(VaultTokensResponse { vault_tokens })
 }
+
+pub fn query_rewards(deps: Deps, env: Env) -> StdResult<RewardsResponse> {
+    let reward_config = REWARD_CONFIG.may_load(deps.storage)?;
+    let mut reward_state = REWARD_STATE.load(deps.storage)?;
+    accrue_rewards(&mut reward_state, reward_config.as_ref(), &env.block.time)?;
+
+    Ok(RewardsResponse { reward_config, reward_state })
+}
+
+pub fn query_pending_rewards(
+    deps: Deps,
+    env: Env,
+    owner: Addr,
+) -> StdResult<PendingRewardsResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let reward_config = REWARD_CONFIG.may_load(deps.storage)?;
+    let mut reward_state = REWARD_STATE.load(deps.storage)?;
+    accrue_rewards(&mut reward_state, reward_config.as_ref(), &env.block.time)?;
+
+    let vault_tokens = vault_tokens()
+        .idx
+        .owner_stake_timestamp
+        .sub_prefix(owner)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|res| res.map(|item| item.1))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut pending = Uint128::zero();
+    for vault_token in vault_tokens {
+        let is_staked = vault_token.get_status(&env.block.time, config.unstake_period) == VaultTokenStatus::Staked;
+        let key = vault_token_key(&vault_token.collection, &vault_token.token_id);
+        let token_reward = settle_token_reward(deps.storage, &reward_state, key, is_staked)?;
+        pending += token_reward.accrued;
+    }
+
+    Ok(PendingRewardsResponse {
+        denom: reward_config.map(|c| c.denom),
+        pending,
+    })
+}
+
+pub fn query_failed_hooks(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<FailedHooksResponse> {
+    let limit = limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let failed_hooks = FAILED_HOOKS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(FailedHooksResponse { failed_hooks })
+}
+
+pub fn query_staked_balance_at_height(
+    deps: Deps,
+    env: Env,
+    owner: Addr,
+    height: Option<u64>,
+) -> StdResult<StakedBalanceAtHeightResponse> {
+    let weight = match height {
+        Some(_height) => STAKED_BALANCES.may_load_at_height(deps.storage, &owner, _height),
+        None => STAKED_BALANCES.may_load(deps.storage, &owner),
+    }?;
+    Ok(StakedBalanceAtHeightResponse {
+        weight: weight.unwrap_or_default(),
+        height: height.unwrap_or(env.block.height),
+    })
+}
+
+pub fn query_total_staked_at_height(
+    deps: Deps,
+    env: Env,
+    height: Option<u64>,
+) -> StdResult<TotalStakedAtHeightResponse> {
+    let weight = match height {
+        Some(_height) => TOTAL_STAKED.may_load_at_height(deps.storage, _height),
+        None => TOTAL_STAKED.may_load(deps.storage),
+    }?;
+    Ok(TotalStakedAtHeightResponse {
+        weight: weight.unwrap_or_default(),
+        height: height.unwrap_or(env.block.height),
+    })
+}
+
+pub fn query_force_action(deps: Deps, collection: Addr, token_id: String) -> StdResult<ForceActionResponse> {
+    let force_action = FORCE_ACTIONS.may_load(deps.storage, vault_token_key(&collection, &token_id))?;
+    Ok(ForceActionResponse { force_action })
+}
+
+/// Tokens unstaked after the cutoff are still unstaking
+fn unstake_cutoff(deps: Deps, env: &Env) -> StdResult<u64> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(env.block.time.seconds().saturating_sub(config.unstake_period))
+}
+
+/// The counters follow positions transferred through the vault, so liquid staking
+/// positions are summarized for their recorded owner
+pub fn query_owner_summary(deps: Deps, env: Env, owner: Addr) -> StdResult<OwnerSummaryResponse> {
+    let staked = STAKED_BALANCES.may_load(deps.storage, &owner)?.unwrap_or_default();
+    let unstaked = UNSTAKED_BALANCES.may_load(deps.storage, &owner)?.unwrap_or_default();
+
+    let start = Some(Bound::exclusive(unstake_cutoff(deps, &env)?));
+    let unstaking = OWNER_UNSTAKES
+        .prefix(&owner)
+        .range(deps.storage, start, None, Order::Ascending)
+        .map(|res| res.map(|item| item.1))
+        .sum::<StdResult<u64>>()?;
+
+    Ok(OwnerSummaryResponse {
+        staked,
+        unstaking,
+        transferrable: unstaked - unstaking,
+        total: staked + unstaked,
+    })
+}
+
+pub fn query_vault_summary(deps: Deps, env: Env) -> StdResult<VaultSummaryResponse> {
+    let staked = TOTAL_STAKED.may_load(deps.storage)?.unwrap_or_default();
+    let unstaked = TOTAL_UNSTAKED.may_load(deps.storage)?.unwrap_or_default();
+
+    let start = Some(Bound::exclusive(unstake_cutoff(deps, &env)?));
+    let unstaking = UNSTAKES
+        .range(deps.storage, start, None, Order::Ascending)
+        .map(|res| res.map(|item| item.1))
+        .sum::<StdResult<u64>>()?;
+
+    Ok(VaultSummaryResponse {
+        staked,
+        unstaking,
+        transferrable: unstaked - unstaking,
+        total: staked + unstaked,
+        num_stakers: NUM_STAKERS.may_load(deps.storage)?.unwrap_or_default(),
+    })
+}
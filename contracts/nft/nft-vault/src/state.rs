@@ -1,26 +1,58 @@
-use cosmwasm_std::{Addr, Timestamp};
-use cw_storage_plus::{Item, Index, IndexList, IndexedMap, MultiIndex};
+use cosmwasm_std::{Addr, Coin, Timestamp, Uint128};
+use cw20::Denom;
+use cw_storage_plus::{
+    Item, Index, IndexList, IndexedMap, Map, MultiIndex, SnapshotItem, SnapshotMap, Strategy
+};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter, Result};
 use crate::hooks::Hooks;
+use crate::msg::{HookAction, HookMsg};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
-    /// The NFT contract
-    pub cw721_address: Addr,
+    /// The NFT contracts accepted for staking
+    pub collections: Vec<Addr>,
     /// The addresses with admin permissions
     pub operators: Vec<Addr>,
     /// A human-readable string label for the vault
     pub label: String,
     /// The amount of time it takes to unstake a token
     pub unstake_period: u64,
+    /// The lock periods owners may opt into when staking
+    pub lock_tiers: Vec<LockTier>,
+    /// The penalty for unstaking before the lock period ends,
+    /// early unstaking is rejected if none is set
+    pub early_unstake_penalty: Option<EarlyUnstakePenalty>,
+    /// The amount of time a force action waits between being scheduled and executed
+    pub force_timelock: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LockTier {
+    /// The amount of time a token cannot be unstaked for
+    pub duration: u64,
+    /// The reward weight of a locked token, an unlocked token has a weight of 1
+    pub reward_weight: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EarlyUnstakePenalty {
+    /// The NFT is burned
+    Burn,
+    /// A fee is paid to the recipient
+    Fee { fee: Coin, recipient: Addr },
+    /// The unclaimed rewards of the token are returned to the reward pool
+    ForfeitRewards,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct VaultToken {
+    /// The NFT contract of the staked NFT
+    pub collection: Addr,
     /// The token_id of the staked NFT
     pub token_id: String,
     /// The owner of the NFT
@@ -29,6 +61,8 @@ pub struct VaultToken {
     pub stake_timestamp: Timestamp,
     /// The time at which the NFT unstaking began
     pub unstake_timestamp: Option<Timestamp>,
+    /// The time until which the NFT cannot be unstaked without a penalty
+    pub lock_end_timestamp: Option<Timestamp>,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
@@ -58,27 +92,46 @@ impl VaultToken {
     }
 }
 
+/// Primary key for VaultTokens: (collection, token_id)
+pub type VaultTokenKey = (Addr, String);
+
+/// Convenience vault token key constructor
+pub fn vault_token_key(collection: &Addr, token_id: &str) -> VaultTokenKey {
+    (collection.clone(), token_id.to_string())
+}
+
 /// Defines indices for accessing VaultTokens
 pub struct VaultTokenIndices<'a> {
-    pub owner_stake_timestamp: MultiIndex<'a, (Addr, u64), VaultToken, String>,
-    pub stake_timestamp: MultiIndex<'a, u64, VaultToken, String>,
-    pub unstake_timestamp: MultiIndex<'a, u64, VaultToken, String>,
+    pub owner_stake_timestamp: MultiIndex<'a, (Addr, u64), VaultToken, VaultTokenKey>,
+    pub collection_stake_timestamp: MultiIndex<'a, (Addr, u64), VaultToken, VaultTokenKey>,
+    pub stake_timestamp: MultiIndex<'a, u64, VaultToken, VaultTokenKey>,
+    pub unstake_timestamp: MultiIndex<'a, u64, VaultToken, VaultTokenKey>,
 }
 
 impl<'a> IndexList<VaultToken> for VaultTokenIndices<'a> {
     fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<VaultToken>> + '_> {
-        let v: Vec<&dyn Index<VaultToken>> = vec![&self.owner_stake_timestamp, &self.stake_timestamp, &self.unstake_timestamp];
+        let v: Vec<&dyn Index<VaultToken>> = vec![
+            &self.owner_stake_timestamp,
+            &self.collection_stake_timestamp,
+            &self.stake_timestamp,
+            &self.unstake_timestamp,
+        ];
         Box::new(v.into_iter())
     }
 }
 
-pub fn vault_tokens<'a>() -> IndexedMap<'a, String, VaultToken, VaultTokenIndices<'a>> {
+pub fn vault_tokens<'a>() -> IndexedMap<'a, VaultTokenKey, VaultToken, VaultTokenIndices<'a>> {
     let indexes = VaultTokenIndices {
         owner_stake_timestamp: MultiIndex::new(
             |d: &VaultToken| (d.owner.clone(), d.stake_timestamp.seconds()),
             "vault_tokens",
             "vault_tokens__owner_stake_timestamp"
         ),
+        collection_stake_timestamp: MultiIndex::new(
+            |d: &VaultToken| (d.collection.clone(), d.stake_timestamp.seconds()),
+            "vault_tokens",
+            "vault_tokens__collection_stake_timestamp"
+        ),
         stake_timestamp: MultiIndex::new(|d: &VaultToken|  d.stake_timestamp.seconds(), "vault_tokens", "vault_tokens__stake_timestamp"),
         unstake_timestamp: MultiIndex::new(
             |d: &VaultToken| d.unstake_timestamp.map_or(0u64, |ts| ts.seconds()),
@@ -89,6 +142,131 @@ pub fn vault_tokens<'a>() -> IndexedMap<'a, String, VaultToken, VaultTokenIndice
     IndexedMap::new("vault_tokens", indexes)
 }
 
-pub const STAKE_HOOKS: Hooks = Hooks::new("stake-hooks");
-pub const UNSTAKE_HOOKS: Hooks = Hooks::new("unstake-hooks");
-pub const WITHDRAW_HOOKS: Hooks = Hooks::new("withdraw-hooks");
\ No newline at end of file
+pub const STAKE_HOOKS: Hooks = Hooks::new("stake-hooks", "stake-hooks-required");
+pub const UNSTAKE_HOOKS: Hooks = Hooks::new("unstake-hooks", "unstake-hooks-required");
+pub const WITHDRAW_HOOKS: Hooks = Hooks::new("withdraw-hooks", "withdraw-hooks-required");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HookDelivery {
+    /// The hook contract receiving the message
+    pub hook: Addr,
+    /// The action that triggered the hook
+    pub action: HookAction,
+    /// The messages sent to the hook, several messages are sent as a single batch
+    pub msgs: Vec<HookMsg>,
+    /// The number of times delivery was attempted
+    pub attempts: u32,
+    /// The error of the last failed attempt
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ForceAction {
+    Unstake,
+    Withdraw { recipient: Addr },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ScheduledForceAction {
+    pub action: ForceAction,
+    /// Why the operator is forcing the token out
+    pub reason: String,
+    /// The earliest time the action can be executed
+    pub executable_at: Timestamp,
+}
+
+/// Force actions awaiting the force timelock
+pub const FORCE_ACTIONS: Map<VaultTokenKey, ScheduledForceAction> = Map::new("force_actions");
+
+/// The number of tokens each staker has staked, snapshotted for voting power queries.
+/// A liquid staking position counts for its recorded owner, which changes when its receipt
+/// is sent to the vault with `ReceiveNftMsg::TransferPosition`
+pub const STAKED_BALANCES: SnapshotMap<&Addr, u64> = SnapshotMap::new(
+    "staked_balances",
+    "staked_balances__checkpoints",
+    "staked_balances__changelog",
+    Strategy::EveryBlock,
+);
+
+/// The total number of staked tokens, snapshotted for voting power queries
+pub const TOTAL_STAKED: SnapshotItem<u64> = SnapshotItem::new(
+    "total_staked",
+    "total_staked__checkpoints",
+    "total_staked__changelog",
+    Strategy::EveryBlock,
+);
+
+/// The number of owners with a staked token
+pub const NUM_STAKERS: Item<u64> = Item::new("num_stakers");
+
+/// The number of unstaked tokens of each owner awaiting withdrawal
+pub const UNSTAKED_BALANCES: Map<&Addr, u64> = Map::new("unstaked_balances");
+
+/// The total number of unstaked tokens awaiting withdrawal
+pub const TOTAL_UNSTAKED: Item<u64> = Item::new("total_unstaked");
+
+/// The number of tokens each owner unstaked at a time in seconds, those unstaked within
+/// the unstake period are still unstaking
+pub const OWNER_UNSTAKES: Map<(&Addr, u64), u64> = Map::new("owner_unstakes");
+
+/// The number of tokens unstaked at a time in seconds
+pub const UNSTAKES: Map<u64, u64> = Map::new("unstakes");
+
+/// The receipt cw721 minted for each staked token when liquid staking is enabled,
+/// the holder of a receipt is the owner of the staking position
+pub const RECEIPT_COLLECTION: Item<Addr> = Item::new("receipt_collection");
+
+/// The staked token of each receipt, used to look up the position of a receipt sent to the vault
+pub const RECEIPT_TOKENS: Map<&str, VaultTokenKey> = Map::new("receipt_tokens");
+
+/// The number of receipts of each token voided by a force withdraw, the vault cannot burn
+/// a receipt without its holder's approval so a re-staked token is minted a receipt with a new id
+pub const VOIDED_RECEIPTS: Map<VaultTokenKey, u32> = Map::new("voided_receipts");
+
+/// The next id used to track a hook delivery, which is also the reply id of its SubMsg
+pub const HOOK_DELIVERY_ID: Item<u64> = Item::new("hook_delivery_id");
+
+/// Hook deliveries awaiting a reply
+pub const PENDING_HOOKS: Map<u64, HookDelivery> = Map::new("pending_hooks");
+
+/// Hook deliveries that failed, kept until they are retried
+pub const FAILED_HOOKS: Map<u64, HookDelivery> = Map::new("failed_hooks");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardConfig {
+    /// The native or cw20 token paid out as staking rewards
+    pub denom: Denom,
+    /// The amount of rewards each staked token earns per second
+    pub emission_rate: Uint128,
+}
+
+pub const REWARD_CONFIG: Item<RewardConfig> = Item::new("reward_config");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardState {
+    /// The cumulative rewards earned per reward weight of a staked token
+    pub reward_index: Uint128,
+    /// The time at which the reward index was last updated
+    pub last_update: Timestamp,
+    /// The number of tokens currently Staked
+    pub num_staked: u64,
+    /// The sum of the reward weights of tokens currently Staked
+    pub staked_weight: u64,
+    /// Funded rewards not yet earned by any token
+    pub unallocated: Uint128,
+}
+
+pub const REWARD_STATE: Item<RewardState> = Item::new("reward_state");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct TokenReward {
+    /// The reward index at which the token's rewards were last settled
+    pub reward_index: Uint128,
+    /// Rewards earned by the token but not yet claimed
+    pub accrued: Uint128,
+    /// The reward weight of the token while Staked
+    pub weight: u64,
+}
+
+pub const TOKEN_REWARDS: Map<VaultTokenKey, TokenReward> = Map::new("token_rewards");
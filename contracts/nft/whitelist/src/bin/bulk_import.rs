@@ -0,0 +1,214 @@
+//! Prepares `AddMembers` payloads from a CSV or JSON member list.
+//!
+//! cargo run --features=bulk-import --bin bulk_import -- <members.csv|members.json> <bech32 prefix>
+//!     [--member-limit <n>] [--chunk-size <n>] [--stage-id <n>]
+//!
+//! CSV rows are `address[,limit]`, JSON is a list of addresses or of
+//! `{"address": ..., "limit": ...}` objects. Each line written to stdout is an
+//! `add_members` execute msg, the validation report is written to stderr.
+//!
+//! `--member-limit` is the room left in the stage, the whitelist member limit
+//! less its current members. Members past it are reported and left out, and
+//! chunks never hold more members than it.
+
+use std::collections::HashSet;
+use std::env::args;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::process::exit;
+
+use bech32::{FromBase32, Variant};
+use serde::Deserialize;
+
+use whitelist::msg::{AddMembersMsg, ExecuteMsg};
+
+// each member costs a few storage reads and writes, keep batches well under the block gas limit
+const DEFAULT_CHUNK_SIZE: usize = 250;
+
+const USAGE: &str = "usage: bulk_import <members.csv|members.json> <bech32 prefix> \
+    [--member-limit <n>] [--chunk-size <n>] [--stage-id <n>]";
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonMember {
+    Address(String),
+    Allocation { address: String, limit: Option<u32> },
+}
+
+struct Member {
+    address: String,
+    limit: Option<u32>,
+}
+
+fn parse_csv(contents: &str) -> Result<Vec<Member>, String> {
+    let mut members = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut columns = line
+            .split(',')
+            .map(|column| column.trim().trim_matches('"'));
+        let address = columns.next().unwrap_or_default().to_string();
+        // skip a header row
+        if i == 0 && address.eq_ignore_ascii_case("address") {
+            continue;
+        }
+        let limit = match columns.next() {
+            Some(limit) if !limit.is_empty() => Some(
+                limit
+                    .parse::<u32>()
+                    .map_err(|e| format!("line {}: invalid limit {}: {}", i + 1, limit, e))?,
+            ),
+            _ => None,
+        };
+        members.push(Member { address, limit });
+    }
+    Ok(members)
+}
+
+fn parse_json(contents: &str) -> Result<Vec<Member>, String> {
+    let members: Vec<JsonMember> = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+    Ok(members
+        .into_iter()
+        .map(|member| match member {
+            JsonMember::Address(address) => Member {
+                address,
+                limit: None,
+            },
+            JsonMember::Allocation { address, limit } => Member { address, limit },
+        })
+        .collect())
+}
+
+fn validate_address(address: &str, prefix: &str) -> Result<(), String> {
+    if address != address.to_lowercase() {
+        return Err("not lowercase".to_string());
+    }
+    let (hrp, data, variant) = bech32::decode(address).map_err(|e| e.to_string())?;
+    if hrp != prefix {
+        return Err(format!("expected prefix {}", prefix));
+    }
+    if variant != Variant::Bech32 {
+        return Err("not bech32".to_string());
+    }
+    let bytes = Vec::<u8>::from_base32(&data).map_err(|e| e.to_string())?;
+    if bytes.len() != 20 && bytes.len() != 32 {
+        return Err(format!("invalid length {}", bytes.len()));
+    }
+    Ok(())
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    exit(1);
+}
+
+fn main() {
+    let args: Vec<String> = args().collect();
+    if args.len() < 3 {
+        usage();
+    }
+    let path = Path::new(&args[1]);
+    let prefix = &args[2];
+
+    let mut member_limit: Option<usize> = None;
+    let mut chunk_size = DEFAULT_CHUNK_SIZE;
+    let mut stage_id: Option<u32> = None;
+    let mut options = args[3..].iter();
+    while let Some(option) = options.next() {
+        let value = options.next().unwrap_or_else(|| usage());
+        match option.as_str() {
+            "--member-limit" => {
+                member_limit = Some(value.parse().expect("invalid member limit"));
+            }
+            "--chunk-size" => chunk_size = value.parse().expect("invalid chunk size"),
+            "--stage-id" => stage_id = Some(value.parse().expect("invalid stage id")),
+            _ => usage(),
+        }
+    }
+    if let Some(member_limit) = member_limit {
+        chunk_size = chunk_size.min(member_limit);
+    }
+    let chunk_size = chunk_size.max(1);
+
+    let contents = read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("failed to read {}: {}", path.display(), e);
+        exit(1);
+    });
+    let members = if path.extension().map_or(false, |ext| ext == "json") {
+        parse_json(&contents)
+    } else {
+        parse_csv(&contents)
+    }
+    .unwrap_or_else(|e| {
+        eprintln!("failed to parse {}: {}", path.display(), e);
+        exit(1);
+    });
+
+    let mut seen = HashSet::new();
+    let mut valid = vec![];
+    let mut duplicates = vec![];
+    let mut invalid = vec![];
+    for member in members.into_iter() {
+        if let Err(reason) = validate_address(&member.address, prefix) {
+            invalid.push(format!("{} ({})", member.address, reason));
+            continue;
+        }
+        if member.limit == Some(0) {
+            invalid.push(format!("{} (limit must be > 0)", member.address));
+            continue;
+        }
+        if !seen.insert(member.address.clone()) {
+            duplicates.push(member.address);
+            continue;
+        }
+        valid.push(member);
+    }
+
+    let over_limit = match member_limit {
+        Some(member_limit) if valid.len() > member_limit => valid.split_off(member_limit),
+        _ => vec![],
+    };
+
+    let chunks = valid.chunks(chunk_size);
+    let num_chunks = chunks.len();
+    for chunk in chunks {
+        let allocations: Vec<(String, u32)> = chunk
+            .iter()
+            .filter_map(|member| member.limit.map(|limit| (member.address.clone(), limit)))
+            .collect();
+        let msg = ExecuteMsg::AddMembers(AddMembersMsg {
+            to_add: chunk
+                .iter()
+                .filter(|member| member.limit.is_none())
+                .map(|member| member.address.clone())
+                .collect(),
+            allocations: if allocations.is_empty() {
+                None
+            } else {
+                Some(allocations)
+            },
+            stage_id,
+            // members added since the list was exported are skipped on chain
+            skip_invalid: Some(true),
+        });
+        println!("{}", serde_json::to_string(&msg).unwrap());
+    }
+
+    eprintln!("valid members: {}", valid.len());
+    eprintln!("payloads: {} of up to {} members", num_chunks, chunk_size);
+    eprintln!("duplicates: {}", duplicates.len());
+    for duplicate in duplicates.iter() {
+        eprintln!("  {}", duplicate);
+    }
+    eprintln!("invalid: {}", invalid.len());
+    for invalid in invalid.iter() {
+        eprintln!("  {}", invalid);
+    }
+    eprintln!("over member limit: {}", over_limit.len());
+    for member in over_limit.iter() {
+        eprintln!("  {}", member.address);
+    }
+}
@@ -1,17 +1,25 @@
 use crate::error::ContractError;
 use crate::msg::{
-    AddMembersMsg, ConfigResponse, ExecuteMsg, HasEndedResponse, HasMemberResponse,
-    HasStartedResponse, InstantiateMsg, IsActiveResponse, MembersResponse, QueryMsg,
-    RemoveMembersMsg,
+    ActiveStageResponse, AddMembersMsg, AddStageMsg, AdminsResponse, ConfigResponse, ExecuteMsg,
+    GateRulesResponse, HasEndedResponse, HasMemberResponse, HasStartedResponse, InstantiateMsg,
+    IsActiveResponse, MemberAllocationResponse, MembersResponse, QueryMsg,
+    RemainingAllocationResponse, RemoveMembersMsg, StageResponse, StagesResponse,
+};
+use crate::state::{
+    Config, GateRule, Stage, CONFIG, GATE_RULES, MEMBER_ALLOCATIONS, MEMBER_MINTS, PENDING_ADMINS,
+    STAGES, STAGE_MEMBERS, WHITELIST,
 };
-use crate::state::{Config, CONFIG, WHITELIST};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, StdResult, Response};
-use cosmwasm_std::{Order, Timestamp};
+use cosmwasm_std::{Addr, Api, Order, StdError, Storage, Timestamp};
 use cw2::set_contract_version;
+use cw20::{BalanceResponse, Cw20QueryMsg};
+use cw721::{Cw721QueryMsg, TokensResponse};
 use cw_storage_plus::Bound;
 use cw_utils::{maybe_addr};
+use nft_vault::msg::{QueryMsg as VaultQueryMsg, StakedBalanceAtHeightResponse};
+use sha2::{Digest, Sha256};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:passage-whitelist";
@@ -21,6 +29,9 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 const PAGINATION_DEFAULT_LIMIT: u32 = 25;
 const PAGINATION_MAX_LIMIT: u32 = 100;
 
+// each gate rule costs a query when checking membership
+const MAX_GATE_RULES: usize = 5;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -50,18 +61,25 @@ pub fn instantiate(
         });
     }
 
+    if let Some(merkle_root) = &msg.merkle_root {
+        validate_merkle_root(merkle_root)?;
+    }
+
     // remove duplicate members
     msg.members.sort_unstable();
     msg.members.dedup();
 
     let config = Config {
-        admin: info.sender.clone(),
+        admins: vec![info.sender.clone()],
+        operators: vec![],
         start_time: msg.start_time,
         end_time: msg.end_time,
         num_members: msg.members.len() as u32,
         unit_price: msg.unit_price,
         per_address_limit: msg.per_address_limit,
         member_limit: msg.member_limit,
+        merkle_root: msg.merkle_root,
+        minters: vec![],
     };
     CONFIG.save(deps.storage, &config)?;
 
@@ -117,6 +135,20 @@ pub fn execute(
         ExecuteMsg::IncreaseMemberLimit(member_limit) => {
             execute_increase_member_limit(deps, info, member_limit)
         }
+        ExecuteMsg::UpdateMerkleRoot(merkle_root) => {
+            execute_update_merkle_root(deps, info, merkle_root)
+        }
+        ExecuteMsg::AddStage(msg) => execute_add_stage(deps, env, info, msg),
+        ExecuteMsg::UpdateMinters(minters) => execute_update_minters(deps, info, minters),
+        ExecuteMsg::UpdateAdmins { admins } => execute_update_admins(deps, info, admins),
+        ExecuteMsg::AcceptAdmins {} => execute_accept_admins(deps, info),
+        ExecuteMsg::UpdateOperators(operators) => execute_update_operators(deps, info, operators),
+        ExecuteMsg::UpdateGateRules { stage_id, rules } => {
+            execute_update_gate_rules(deps, info, stage_id, rules)
+        }
+        ExecuteMsg::RecordMint { member, count } => {
+            execute_record_mint(deps, env, info, member, count)
+        }
     }
 }
 
@@ -127,7 +159,7 @@ pub fn execute_update_start_time(
     start_time: Timestamp,
 ) -> Result<Response, ContractError> {
     let mut config = CONFIG.load(deps.storage)?;
-    if info.sender != config.admin {
+    if !config.is_admin(&info.sender) {
         return Err(ContractError::Unauthorized {});
     }
 
@@ -155,7 +187,7 @@ pub fn execute_update_end_time(
     end_time: Timestamp,
 ) -> Result<Response, ContractError> {
     let mut config = CONFIG.load(deps.storage)?;
-    if info.sender != config.admin {
+    if !config.is_admin(&info.sender) {
         return Err(ContractError::Unauthorized {});
     }
 
@@ -168,6 +200,16 @@ pub fn execute_update_end_time(
         return Err(ContractError::InvalidEndTime(end_time, config.start_time));
     }
 
+    // the base stage can't overlap the stage that follows it
+    if let Some(next_stage) = STAGES.may_load(deps.storage, 1)? {
+        if end_time > next_stage.start_time {
+            return Err(ContractError::InvalidEndTime(
+                end_time,
+                next_stage.start_time,
+            ));
+        }
+    }
+
     config.end_time = end_time;
     CONFIG.save(deps.storage, &config)?;
     Ok(Response::new()
@@ -183,34 +225,87 @@ pub fn execute_add_members(
     mut msg: AddMembersMsg,
 ) -> Result<Response, ContractError> {
     let mut config = CONFIG.load(deps.storage)?;
-    if info.sender != config.admin {
+    if !config.can_manage_members(&info.sender) {
         return Err(ContractError::Unauthorized {});
     }
 
+    let stage_id = msg.stage_id.unwrap_or(0);
+    let mut stage = load_stage(deps.storage, &config, stage_id)?;
+
     // remove duplicate members
     msg.to_add.sort_unstable();
     msg.to_add.dedup();
 
-    for add in msg.to_add.into_iter() {
-        if config.num_members >= config.member_limit {
+    let to_add = msg.to_add.into_iter().map(|member| (member, None));
+    let allocations = msg
+        .allocations
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(member, allocation)| (member, Some(allocation)));
+
+    let skip_invalid = msg.skip_invalid.unwrap_or(false);
+    let mut skipped_duplicates: Vec<String> = vec![];
+    let mut skipped_invalid: Vec<String> = vec![];
+    let mut skipped_over_limit: Vec<String> = vec![];
+    let mut num_added: u32 = 0;
+
+    for (add, allocation) in to_add.chain(allocations) {
+        if stage.num_members >= config.member_limit {
+            if skip_invalid {
+                skipped_over_limit.push(add);
+                continue;
+            }
             return Err(ContractError::MembersExceeded {
                 expected: config.member_limit,
-                actual: config.num_members,
+                actual: stage.num_members,
             });
         }
-        let addr = deps.api.addr_validate(&add)?;
-        if WHITELIST.has(deps.storage, addr.clone()) {
+        let addr = match deps.api.addr_validate(&add) {
+            Ok(addr) => addr,
+            Err(_) if skip_invalid => {
+                skipped_invalid.push(add);
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if is_stage_member(deps.storage, stage_id, &addr) {
+            if skip_invalid {
+                skipped_duplicates.push(add);
+                continue;
+            }
             return Err(ContractError::DuplicateMember(addr.to_string()));
         }
-        WHITELIST.save(deps.storage, addr, &true)?;
-        config.num_members += 1;
+        if let Some(allocation) = allocation {
+            if allocation == 0 {
+                if skip_invalid {
+                    skipped_invalid.push(add);
+                    continue;
+                }
+                return Err(ContractError::InvalidPerAddressLimit {
+                    max: "must be > 0".to_string(),
+                    got: allocation.to_string(),
+                });
+            }
+            MEMBER_ALLOCATIONS.save(deps.storage, (stage_id, addr.clone()), &allocation)?;
+        }
+        save_stage_member(deps.storage, stage_id, addr)?;
+        stage.num_members += 1;
+        num_added += 1;
     }
 
-    CONFIG.save(deps.storage, &config)?;
+    save_stage(deps.storage, &mut config, stage_id, &stage)?;
 
-    Ok(Response::new()
+    let mut res = Response::new()
         .add_attribute("action", "add_members")
-        .add_attribute("sender", info.sender))
+        .add_attribute("sender", info.sender);
+    if skip_invalid {
+        res = res
+            .add_attribute("num_added", num_added.to_string())
+            .add_attribute("skipped_duplicates", skipped_duplicates.join(","))
+            .add_attribute("skipped_invalid", skipped_invalid.join(","))
+            .add_attribute("skipped_over_limit", skipped_over_limit.join(","));
+    }
+    Ok(res)
 }
 
 pub fn execute_remove_members(
@@ -220,24 +315,33 @@ pub fn execute_remove_members(
     msg: RemoveMembersMsg,
 ) -> Result<Response, ContractError> {
     let mut config = CONFIG.load(deps.storage)?;
-    if info.sender != config.admin {
+    if !config.can_manage_members(&info.sender) {
         return Err(ContractError::Unauthorized {});
     }
 
-    if env.block.time >= config.start_time {
+    let stage_id = msg.stage_id.unwrap_or(0);
+    let mut stage = load_stage(deps.storage, &config, stage_id)?;
+
+    if env.block.time >= stage.start_time {
         return Err(ContractError::AlreadyStarted {});
     }
 
     for remove in msg.to_remove.into_iter() {
         let addr = deps.api.addr_validate(&remove)?;
-        if !WHITELIST.has(deps.storage, addr.clone()) {
+        if !is_stage_member(deps.storage, stage_id, &addr) {
             return Err(ContractError::NoMemberFound(addr.to_string()));
         }
-        WHITELIST.remove(deps.storage, addr);
-        config.num_members -= 1;
+        MEMBER_ALLOCATIONS.remove(deps.storage, (stage_id, addr.clone()));
+        MEMBER_MINTS.remove(deps.storage, (stage_id, addr.clone()));
+        if stage_id == 0 {
+            WHITELIST.remove(deps.storage, addr);
+        } else {
+            STAGE_MEMBERS.remove(deps.storage, (stage_id, addr));
+        }
+        stage.num_members -= 1;
     }
 
-    CONFIG.save(deps.storage, &config)?;
+    save_stage(deps.storage, &mut config, stage_id, &stage)?;
 
     Ok(Response::new()
         .add_attribute("action", "remove_members")
@@ -250,7 +354,7 @@ pub fn execute_update_per_address_limit(
     per_address_limit: u32,
 ) -> Result<Response, ContractError> {
     let mut config = CONFIG.load(deps.storage)?;
-    if info.sender != config.admin {
+    if !config.is_admin(&info.sender) {
         return Err(ContractError::Unauthorized {});
     }
 
@@ -264,10 +368,14 @@ pub fn execute_update_per_address_limit(
 /// Increase member limit. Must include a fee if crossing 1000, 2000, etc member limit.
 pub fn execute_increase_member_limit(
     deps: DepsMut,
-    _info: MessageInfo,
+    info: MessageInfo,
     member_limit: u32,
 ) -> Result<Response, ContractError> {
     let mut config = CONFIG.load(deps.storage)?;
+    if !config.is_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
     if config.member_limit >= member_limit {
         return Err(ContractError::InvalidMemberLimit {
             min: config.member_limit,
@@ -283,18 +391,492 @@ pub fn execute_increase_member_limit(
     )
 }
 
+/// Replace the Merkle root of off chain members, or remove it by passing None.
+pub fn execute_update_merkle_root(
+    deps: DepsMut,
+    info: MessageInfo,
+    merkle_root: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if !config.is_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(merkle_root) = &merkle_root {
+        validate_merkle_root(merkle_root)?;
+    }
+
+    config.merkle_root = merkle_root;
+    CONFIG.save(deps.storage, &config)?;
+    Ok(Response::new()
+        .add_attribute("action", "update_merkle_root")
+        .add_attribute("merkle_root", config.merkle_root.unwrap_or_default())
+        .add_attribute("sender", info.sender))
+}
+
+/// Append a stage that opens once the previous stage has ended.
+pub fn execute_add_stage(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    mut msg: AddStageMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let (last_stage_id, last_stage) = last_stage(deps.storage, &config)?;
+    if msg.start_time < last_stage.end_time {
+        return Err(ContractError::InvalidStartTime(
+            last_stage.end_time,
+            msg.start_time,
+        ));
+    }
+
+    if msg.start_time >= msg.end_time {
+        return Err(ContractError::InvalidStartTime(
+            msg.start_time,
+            msg.end_time,
+        ));
+    }
+
+    if env.block.time >= msg.start_time {
+        return Err(ContractError::InvalidStartTime(
+            env.block.time,
+            msg.start_time,
+        ));
+    }
+
+    if msg.unit_price.amount.u128() == 0 {
+        return Err(ContractError::InvalidUnitPrice(
+            msg.unit_price.amount.u128(),
+        ));
+    }
+
+    if msg.per_address_limit == 0 {
+        return Err(ContractError::InvalidPerAddressLimit {
+            max: "must be > 0".to_string(),
+            got: msg.per_address_limit.to_string(),
+        });
+    }
+
+    // remove duplicate members
+    msg.members.sort_unstable();
+    msg.members.dedup();
+
+    let num_members = msg.members.len() as u32;
+    if config.member_limit < num_members {
+        return Err(ContractError::MembersExceeded {
+            expected: config.member_limit,
+            actual: num_members,
+        });
+    }
+
+    let stage_id = last_stage_id + 1;
+    for member in msg.members.into_iter() {
+        let addr = deps.api.addr_validate(&member)?;
+        STAGE_MEMBERS.save(deps.storage, (stage_id, addr), &true)?;
+    }
+
+    let stage = Stage {
+        start_time: msg.start_time,
+        end_time: msg.end_time,
+        unit_price: msg.unit_price,
+        per_address_limit: msg.per_address_limit,
+        num_members,
+    };
+    STAGES.save(deps.storage, stage_id, &stage)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "add_stage")
+        .add_attribute("stage_id", stage_id.to_string())
+        .add_attribute("start_time", stage.start_time.to_string())
+        .add_attribute("end_time", stage.end_time.to_string())
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_update_minters(
+    deps: DepsMut,
+    info: MessageInfo,
+    minters: Vec<String>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if !config.is_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.minters = minters
+        .iter()
+        .map(|minter| deps.api.addr_validate(minter))
+        .collect::<StdResult<Vec<Addr>>>()?;
+    CONFIG.save(deps.storage, &config)?;
+    Ok(Response::new()
+        .add_attribute("action", "update_minters")
+        .add_attribute("minters", minters.join(","))
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_update_admins(
+    deps: DepsMut,
+    info: MessageInfo,
+    admins: Vec<String>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if admins.is_empty() {
+        return Err(ContractError::InvalidAdmins(
+            "must not be empty".to_string(),
+        ));
+    }
+
+    let mut pending_admins = admins
+        .iter()
+        .map(|admin| deps.api.addr_validate(admin))
+        .collect::<StdResult<Vec<Addr>>>()?;
+    pending_admins.sort_unstable();
+    pending_admins.dedup();
+    PENDING_ADMINS.save(deps.storage, &pending_admins)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_admins")
+        .add_attribute("pending_admins", admins.join(","))
+        .add_attribute("sender", info.sender))
+}
+
+/// Any of the proposed admins can accept, replacing the current admins.
+pub fn execute_accept_admins(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    let pending_admins = PENDING_ADMINS.may_load(deps.storage)?.unwrap_or_default();
+    if !pending_admins.contains(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.admins = pending_admins;
+    CONFIG.save(deps.storage, &config)?;
+    PENDING_ADMINS.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_attribute("action", "accept_admins")
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_update_operators(
+    deps: DepsMut,
+    info: MessageInfo,
+    operators: Vec<String>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if !config.is_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.operators = operators
+        .iter()
+        .map(|operator| deps.api.addr_validate(operator))
+        .collect::<StdResult<Vec<Addr>>>()?;
+    CONFIG.save(deps.storage, &config)?;
+    Ok(Response::new()
+        .add_attribute("action", "update_operators")
+        .add_attribute("operators", operators.join(","))
+        .add_attribute("sender", info.sender))
+}
+
+/// Replace the gate rules of a stage, an empty list removes them.
+pub fn execute_update_gate_rules(
+    deps: DepsMut,
+    info: MessageInfo,
+    stage_id: Option<u32>,
+    rules: Vec<GateRule>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_admin(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let stage_id = stage_id.unwrap_or(0);
+    load_stage(deps.storage, &config, stage_id)?;
+
+    if rules.len() > MAX_GATE_RULES {
+        return Err(ContractError::InvalidGateRules(format!(
+            "at most {} rules per stage",
+            MAX_GATE_RULES
+        )));
+    }
+
+    let rules = rules
+        .into_iter()
+        .map(|rule| validate_gate_rule(deps.api, rule))
+        .collect::<Result<Vec<GateRule>, ContractError>>()?;
+    if rules.is_empty() {
+        GATE_RULES.remove(deps.storage, stage_id);
+    } else {
+        GATE_RULES.save(deps.storage, stage_id, &rules)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_gate_rules")
+        .add_attribute("stage_id", stage_id.to_string())
+        .add_attribute("num_rules", rules.len().to_string())
+        .add_attribute("sender", info.sender))
+}
+
+fn validate_gate_rule(api: &dyn Api, rule: GateRule) -> Result<GateRule, ContractError> {
+    match rule {
+        GateRule::Cw721(collection) => Ok(GateRule::Cw721(api.addr_validate(collection.as_str())?)),
+        GateRule::Cw20 { token, min_balance } => {
+            if min_balance.is_zero() {
+                return Err(ContractError::InvalidGateRules(
+                    "cw20 min_balance must be > 0".to_string(),
+                ));
+            }
+            Ok(GateRule::Cw20 {
+                token: api.addr_validate(token.as_str())?,
+                min_balance,
+            })
+        }
+        GateRule::NftVault { vault, min_staked } => {
+            if min_staked == 0 {
+                return Err(ContractError::InvalidGateRules(
+                    "nft-vault min_staked must be > 0".to_string(),
+                ));
+            }
+            Ok(GateRule::NftVault {
+                vault: api.addr_validate(vault.as_str())?,
+                min_staked,
+            })
+        }
+    }
+}
+
+fn passes_gate_rule(deps: Deps, rule: &GateRule, addr: &Addr) -> StdResult<bool> {
+    match rule {
+        GateRule::Cw721(collection) => {
+            let res: TokensResponse = deps.querier.query_wasm_smart(
+                collection,
+                &Cw721QueryMsg::Tokens {
+                    owner: addr.to_string(),
+                    start_after: None,
+                    limit: Some(1),
+                },
+            )?;
+            Ok(!res.tokens.is_empty())
+        }
+        GateRule::Cw20 { token, min_balance } => {
+            let res: BalanceResponse = deps.querier.query_wasm_smart(
+                token,
+                &Cw20QueryMsg::Balance {
+                    address: addr.to_string(),
+                },
+            )?;
+            Ok(res.balance >= *min_balance)
+        }
+        GateRule::NftVault { vault, min_staked } => {
+            let res: StakedBalanceAtHeightResponse = deps.querier.query_wasm_smart(
+                vault,
+                &VaultQueryMsg::StakedBalanceAtHeight {
+                    owner: addr.to_string(),
+                    height: None,
+                },
+            )?;
+            Ok(res.weight >= *min_staked)
+        }
+    }
+}
+
+/// Use up part of a member allocation in the active stage. Membership is
+/// verified by the minter before minting.
+pub fn execute_record_mint(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    member: String,
+    count: u32,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.minters.contains(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let addr = deps.api.addr_validate(&member)?;
+    let stage = active_stage(deps.storage, &config, env.block.time)?
+        .ok_or(ContractError::NoActiveStage {})?;
+    let allocation = member_allocation(deps.storage, &addr, &stage)?;
+
+    let used = MEMBER_MINTS
+        .may_load(deps.storage, (stage.stage_id, addr.clone()))?
+        .unwrap_or(0)
+        .checked_add(count)
+        .ok_or(ContractError::MaxPerAddressLimitExceeded {})?;
+    if used > allocation {
+        return Err(ContractError::MaxPerAddressLimitExceeded {});
+    }
+    MEMBER_MINTS.save(deps.storage, (stage.stage_id, addr.clone()), &used)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "record_mint")
+        .add_attribute("member", addr)
+        .add_attribute("stage_id", stage.stage_id.to_string())
+        .add_attribute("count", count.to_string())
+        .add_attribute("sender", info.sender))
+}
+
+fn base_stage(config: &Config) -> Stage {
+    Stage {
+        start_time: config.start_time,
+        end_time: config.end_time,
+        unit_price: config.unit_price.clone(),
+        per_address_limit: config.per_address_limit,
+        num_members: config.num_members,
+    }
+}
+
+fn load_stage(storage: &dyn Storage, config: &Config, stage_id: u32) -> StdResult<Stage> {
+    if stage_id == 0 {
+        Ok(base_stage(config))
+    } else {
+        STAGES.load(storage, stage_id)
+    }
+}
+
+fn save_stage(
+    storage: &mut dyn Storage,
+    config: &mut Config,
+    stage_id: u32,
+    stage: &Stage,
+) -> StdResult<()> {
+    if stage_id == 0 {
+        config.num_members = stage.num_members;
+        CONFIG.save(storage, config)
+    } else {
+        STAGES.save(storage, stage_id, stage)
+    }
+}
+
+fn last_stage(storage: &dyn Storage, config: &Config) -> StdResult<(u32, Stage)> {
+    match STAGES.range(storage, None, None, Order::Descending).next() {
+        Some(item) => item,
+        None => Ok((0, base_stage(config))),
+    }
+}
+
+fn all_stages(storage: &dyn Storage, config: &Config) -> StdResult<Vec<StageResponse>> {
+    std::iter::once(Ok((0, base_stage(config))))
+        .chain(STAGES.range(storage, None, None, Order::Ascending))
+        .map(|item| item.map(|(stage_id, stage)| stage_response(stage_id, stage)))
+        .collect()
+}
+
+fn active_stage(
+    storage: &dyn Storage,
+    config: &Config,
+    time: Timestamp,
+) -> StdResult<Option<StageResponse>> {
+    Ok(all_stages(storage, config)?
+        .into_iter()
+        .find(|stage| (time >= stage.start_time) && (time < stage.end_time)))
+}
+
+fn member_allocation(storage: &dyn Storage, addr: &Addr, stage: &StageResponse) -> StdResult<u32> {
+    Ok(MEMBER_ALLOCATIONS
+        .may_load(storage, (stage.stage_id, addr.clone()))?
+        .unwrap_or(stage.per_address_limit))
+}
+
+fn stage_response(stage_id: u32, stage: Stage) -> StageResponse {
+    StageResponse {
+        stage_id,
+        start_time: stage.start_time,
+        end_time: stage.end_time,
+        unit_price: stage.unit_price,
+        per_address_limit: stage.per_address_limit,
+        num_members: stage.num_members,
+    }
+}
+
+fn is_stage_member(storage: &dyn Storage, stage_id: u32, addr: &Addr) -> bool {
+    if stage_id == 0 {
+        WHITELIST.has(storage, addr.clone())
+    } else {
+        STAGE_MEMBERS.has(storage, (stage_id, addr.clone()))
+    }
+}
+
+fn save_stage_member(storage: &mut dyn Storage, stage_id: u32, addr: Addr) -> StdResult<()> {
+    if stage_id == 0 {
+        WHITELIST.save(storage, addr, &true)
+    } else {
+        STAGE_MEMBERS.save(storage, (stage_id, addr), &true)
+    }
+}
+
+fn validate_merkle_root(merkle_root: &str) -> Result<(), ContractError> {
+    match hex::decode(merkle_root) {
+        Ok(bytes) if bytes.len() == 32 => Ok(()),
+        _ => Err(ContractError::InvalidMerkleRoot(merkle_root.to_string())),
+    }
+}
+
+/// Leaves are sha256(address) and each level hashes the sorted pair of nodes,
+/// so proofs don't need to carry left / right positions.
+fn verify_merkle_proof(merkle_root: &str, member: &Addr, proof: Vec<String>) -> StdResult<bool> {
+    let mut hash = Sha256::digest(member.as_bytes()).to_vec();
+    for sibling in proof.into_iter() {
+        let sibling = match hex::decode(&sibling) {
+            Ok(bytes) if bytes.len() == 32 => bytes,
+            _ => {
+                return Err(StdError::generic_err(format!(
+                    "Invalid proof hash: {}",
+                    sibling
+                )))
+            }
+        };
+        let mut hasher = Sha256::new();
+        if hash <= sibling {
+            hasher.update(&hash);
+            hasher.update(&sibling);
+        } else {
+            hasher.update(&sibling);
+            hasher.update(&hash);
+        }
+        hash = hasher.finalize().to_vec();
+    }
+
+    let root = hex::decode(merkle_root).map_err(|e| StdError::generic_err(e.to_string()))?;
+    Ok(hash == root)
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
-        QueryMsg::Members { start_after, limit } => {
-            to_binary(&query_members(deps, start_after, limit)?)
-        }
+        QueryMsg::Members {
+            start_after,
+            limit,
+            stage_id,
+        } => to_binary(&query_members(deps, start_after, limit, stage_id)?),
 
         QueryMsg::HasStarted {} => to_binary(&query_has_started(deps, env)?),
         QueryMsg::HasEnded {} => to_binary(&query_has_ended(deps, env)?),
         QueryMsg::IsActive {} => to_binary(&query_is_active(deps, env)?),
-        QueryMsg::HasMember { member } => to_binary(&query_has_member(deps, member)?),
+        QueryMsg::HasMember {
+            member,
+            proof,
+            stage_id,
+        } => to_binary(&query_has_member(deps, member, proof, stage_id)?),
+        QueryMsg::MemberAllocation { member, stage_id } => {
+            to_binary(&query_member_allocation(deps, env, member, stage_id)?)
+        }
+        QueryMsg::RemainingAllocation { member, stage_id } => {
+            to_binary(&query_remaining_allocation(deps, env, member, stage_id)?)
+        }
+        QueryMsg::GateRules { stage_id } => to_binary(&query_gate_rules(deps, stage_id)?),
         QueryMsg::Config {} => to_binary(&query_config(deps, env)?),
+        QueryMsg::Admins {} => to_binary(&query_admins(deps)?),
+        QueryMsg::ActiveStage {} => to_binary(&query_active_stage(deps, env)?),
+        QueryMsg::Stages {} => to_binary(&query_stages(deps)?),
     }
 }
 
@@ -307,15 +889,16 @@ fn query_has_started(deps: Deps, env: Env) -> StdResult<HasStartedResponse> {
 
 fn query_has_ended(deps: Deps, env: Env) -> StdResult<HasEndedResponse> {
     let config = CONFIG.load(deps.storage)?;
+    let (_, last_stage) = last_stage(deps.storage, &config)?;
     Ok(HasEndedResponse {
-        has_ended: (env.block.time >= config.end_time),
+        has_ended: (env.block.time >= last_stage.end_time),
     })
 }
 
 fn query_is_active(deps: Deps, env: Env) -> StdResult<IsActiveResponse> {
     let config = CONFIG.load(deps.storage)?;
     Ok(IsActiveResponse {
-        is_active: (env.block.time >= config.start_time) && (env.block.time < config.end_time),
+        is_active: active_stage(deps.storage, &config, env.block.time)?.is_some(),
     })
 }
 
@@ -323,31 +906,73 @@ fn query_members(
     deps: Deps,
     start_after: Option<String>,
     limit: Option<u32>,
+    stage_id: Option<u32>,
 ) -> StdResult<MembersResponse> {
     let limit = limit
         .unwrap_or(PAGINATION_DEFAULT_LIMIT)
         .min(PAGINATION_MAX_LIMIT) as usize;
     let start_addr = maybe_addr(deps.api, start_after)?;
     let start = start_addr.map(Bound::exclusive);
-    let members = WHITELIST
-        .range(deps.storage, start, None, Order::Ascending)
-        .take(limit)
-        .map(|addr| addr.unwrap().0.to_string())
-        .collect::<Vec<String>>();
+    let members = match stage_id.unwrap_or(0) {
+        0 => WHITELIST
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|addr| addr.unwrap().0.to_string())
+            .collect::<Vec<String>>(),
+        stage_id => STAGE_MEMBERS
+            .prefix(stage_id)
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|addr| addr.unwrap().0.to_string())
+            .collect::<Vec<String>>(),
+    };
 
     Ok(MembersResponse { members })
 }
 
-fn query_has_member(deps: Deps, member: String) -> StdResult<HasMemberResponse> {
+fn query_has_member(
+    deps: Deps,
+    member: String,
+    proof: Option<Vec<String>>,
+    stage_id: Option<u32>,
+) -> StdResult<HasMemberResponse> {
     let addr = deps.api.addr_validate(&member)?;
+    let stage_id = stage_id.unwrap_or(0);
+    if is_stage_member(deps.storage, stage_id, &addr) {
+        return Ok(HasMemberResponse { has_member: true });
+    }
 
-    Ok(HasMemberResponse {
-        has_member: WHITELIST.has(deps.storage, addr),
-    })
+    // fall back to the Merkle root for base stage members that are not stored on chain
+    let config = CONFIG.load(deps.storage)?;
+    if let (0, Some(merkle_root), Some(proof)) = (stage_id, config.merkle_root, proof) {
+        if verify_merkle_proof(&merkle_root, &addr, proof)? {
+            return Ok(HasMemberResponse { has_member: true });
+        }
+    }
+
+    // then to the gate rules, resolved from what the address currently holds
+    let rules = GATE_RULES
+        .may_load(deps.storage, stage_id)?
+        .unwrap_or_default();
+    for rule in rules.iter() {
+        if passes_gate_rule(deps, rule, &addr)? {
+            return Ok(HasMemberResponse { has_member: true });
+        }
+    }
+
+    Ok(HasMemberResponse { has_member: false })
+}
+
+fn query_gate_rules(deps: Deps, stage_id: Option<u32>) -> StdResult<GateRulesResponse> {
+    let rules = GATE_RULES
+        .may_load(deps.storage, stage_id.unwrap_or(0))?
+        .unwrap_or_default();
+    Ok(GateRulesResponse { rules })
 }
 
 fn query_config(deps: Deps, env: Env) -> StdResult<ConfigResponse> {
     let config = CONFIG.load(deps.storage)?;
+    let is_active = active_stage(deps.storage, &config, env.block.time)?.is_some();
     Ok(ConfigResponse {
         num_members: config.num_members,
         per_address_limit: config.per_address_limit,
@@ -355,7 +980,105 @@ fn query_config(deps: Deps, env: Env) -> StdResult<ConfigResponse> {
         start_time: config.start_time,
         end_time: config.end_time,
         unit_price: config.unit_price,
-        is_active: (env.block.time >= config.start_time) && (env.block.time < config.end_time),
+        is_active,
+        merkle_root: config.merkle_root,
+        minters: config
+            .minters
+            .iter()
+            .map(|minter| minter.to_string())
+            .collect(),
+    })
+}
+
+/// The given stage, or the active stage like `RecordMint`, falling back to the base stage
+/// when no stage is open.
+fn query_stage(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    stage_id: Option<u32>,
+) -> StdResult<StageResponse> {
+    match stage_id {
+        Some(stage_id) => Ok(stage_response(
+            stage_id,
+            load_stage(deps.storage, config, stage_id)?,
+        )),
+        None => Ok(active_stage(deps.storage, config, env.block.time)?
+            .unwrap_or_else(|| stage_response(0, base_stage(config)))),
+    }
+}
+
+/// Member limit for a stage, falling back to the stage `per_address_limit`.
+/// Membership itself is checked with `HasMember`.
+fn query_member_allocation(
+    deps: Deps,
+    env: Env,
+    member: String,
+    stage_id: Option<u32>,
+) -> StdResult<MemberAllocationResponse> {
+    let addr = deps.api.addr_validate(&member)?;
+    let config = CONFIG.load(deps.storage)?;
+    let stage = query_stage(deps, &env, &config, stage_id)?;
+
+    Ok(MemberAllocationResponse {
+        allocation: member_allocation(deps.storage, &addr, &stage)?,
+        member: addr.to_string(),
+    })
+}
+
+fn query_remaining_allocation(
+    deps: Deps,
+    env: Env,
+    member: String,
+    stage_id: Option<u32>,
+) -> StdResult<RemainingAllocationResponse> {
+    let addr = deps.api.addr_validate(&member)?;
+    let config = CONFIG.load(deps.storage)?;
+    let stage = query_stage(deps, &env, &config, stage_id)?;
+    let allocation = member_allocation(deps.storage, &addr, &stage)?;
+    let used = MEMBER_MINTS
+        .may_load(deps.storage, (stage.stage_id, addr.clone()))?
+        .unwrap_or(0);
+
+    Ok(RemainingAllocationResponse {
+        member: addr.to_string(),
+        used,
+        remaining: allocation.saturating_sub(used),
+    })
+}
+
+fn query_admins(deps: Deps) -> StdResult<AdminsResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let pending_admins = PENDING_ADMINS.may_load(deps.storage)?.unwrap_or_default();
+    Ok(AdminsResponse {
+        admins: config
+            .admins
+            .iter()
+            .map(|admin| admin.to_string())
+            .collect(),
+        pending_admins: pending_admins
+            .iter()
+            .map(|admin| admin.to_string())
+            .collect(),
+        operators: config
+            .operators
+            .iter()
+            .map(|operator| operator.to_string())
+            .collect(),
+    })
+}
+
+fn query_active_stage(deps: Deps, env: Env) -> StdResult<ActiveStageResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(ActiveStageResponse {
+        stage: active_stage(deps.storage, &config, env.block.time)?,
+    })
+}
+
+fn query_stages(deps: Deps) -> StdResult<StagesResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(StagesResponse {
+        stages: all_stages(deps.storage, &config)?,
     })
 }
 
@@ -363,9 +1086,9 @@ fn query_config(deps: Deps, env: Env) -> StdResult<ConfigResponse> {
 mod tests {
     use super::*;
     use cosmwasm_std::{
-        coin,
+        coin, from_binary,
         testing::{mock_dependencies, mock_env, mock_info},
-        Attribute
+        Attribute, ContractResult, SystemError, SystemResult, Uint128, WasmQuery,
     };
 
     const ADMIN: &str = "admin";
@@ -383,6 +1106,7 @@ mod tests {
             unit_price: coin(UNIT_AMOUNT, NATIVE_DENOM),
             per_address_limit: 1,
             member_limit: 1000,
+            merkle_root: None,
         };
         let info = mock_info(ADMIN, &[coin(100_000_000, "ujuno")]);
         let res = instantiate(deps, mock_env(), info.clone(), msg).unwrap();
@@ -408,6 +1132,7 @@ mod tests {
             unit_price: coin(1, NATIVE_DENOM),
             per_address_limit: 1,
             member_limit: 1000,
+            merkle_root: None,
         };
         let info = mock_info(ADMIN, &[coin(100_000_000, "ujuno")]);
         instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
@@ -427,6 +1152,7 @@ mod tests {
             unit_price: coin(UNIT_AMOUNT, NATIVE_DENOM),
             per_address_limit: 1,
             member_limit: 1000,
+            merkle_root: None,
         };
         let info = mock_info(ADMIN, &[coin(100_000_000, "ujuno")]);
         let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -443,6 +1169,7 @@ mod tests {
             unit_price: coin(UNIT_AMOUNT, NATIVE_DENOM),
             per_address_limit: 1,
             member_limit: 1000,
+            merkle_root: None,
         };
         let info = mock_info(ADMIN, &[coin(100_000_000, "ujuno")]);
         let mut deps = mock_dependencies();
@@ -485,23 +1212,27 @@ mod tests {
         // dedupe addrs
         let add_msg = AddMembersMsg {
             to_add: vec!["adsfsa1".to_string(), "adsfsa1".to_string()],
+            allocations: None,
+            stage_id: None,
+            skip_invalid: None,
         };
         let msg = ExecuteMsg::AddMembers(add_msg);
         let info = mock_info(ADMIN, &[]);
         let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
         assert_eq!(res.attributes.len(), 2);
-        let res = query_members(deps.as_ref(), None, None).unwrap();
+        let res = query_members(deps.as_ref(), None, None, None).unwrap();
         assert_eq!(res.members.len(), 2);
 
         execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
 
         let remove_msg = RemoveMembersMsg {
             to_remove: vec!["adsfsa1".to_string()],
+            stage_id: None,
         };
         let msg = ExecuteMsg::RemoveMembers(remove_msg);
         let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
         assert_eq!(res.attributes.len(), 2);
-        let res = query_members(deps.as_ref(), None, None).unwrap();
+        let res = query_members(deps.as_ref(), None, None, None).unwrap();
         assert_eq!(res.members.len(), 1);
     }
 
@@ -533,6 +1264,7 @@ mod tests {
             unit_price: coin(UNIT_AMOUNT, NATIVE_DENOM),
             per_address_limit: 1,
             member_limit: 1000,
+            merkle_root: None,
         };
         let info = mock_info(ADMIN, &[coin(100_000_000, "ujuno")]);
         let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
@@ -540,15 +1272,15 @@ mod tests {
         let mut all_elements: Vec<String> = vec![];
 
         // enforcing a min
-        let res = query_members(deps.as_ref(), None, None).unwrap();
+        let res = query_members(deps.as_ref(), None, None, None).unwrap();
         assert_eq!(res.members.len(), 25);
 
         // enforcing a max
-        let res = query_members(deps.as_ref(), None, Some(125)).unwrap();
+        let res = query_members(deps.as_ref(), None, Some(125), None).unwrap();
         assert_eq!(res.members.len(), 100);
 
         // first fetch
-        let res = query_members(deps.as_ref(), None, Some(50)).unwrap();
+        let res = query_members(deps.as_ref(), None, Some(50), None).unwrap();
         assert_eq!(res.members.len(), 50);
         all_elements.append(&mut res.members.clone());
 
@@ -557,6 +1289,7 @@ mod tests {
             deps.as_ref(),
             Some(res.members[res.members.len() - 1].clone()),
             Some(50),
+            None,
         )
         .unwrap();
         assert_eq!(res.members.len(), 50);
@@ -567,6 +1300,7 @@ mod tests {
             deps.as_ref(),
             Some(res.members[res.members.len() - 1].clone()),
             Some(50),
+            None,
         )
         .unwrap();
         all_elements.append(&mut res.members.clone());
@@ -595,5 +1329,526 @@ mod tests {
         let info = mock_info(ADMIN, &[]);
         let res = execute(deps.as_mut(), mock_env(), info, msg);
         assert!(res.is_ok());
+
+        // only admin can raise the limit
+        let msg = ExecuteMsg::IncreaseMemberLimit(1003);
+        let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap_err();
+        assert_eq!(err.to_string(), ContractError::Unauthorized {}.to_string());
+        let res = query_config(deps.as_ref(), mock_env()).unwrap();
+        assert_eq!(1002, res.member_limit);
+    }
+
+    fn hash_pair(a: &[u8], b: &[u8]) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        if a <= b {
+            hasher.update(a);
+            hasher.update(b);
+        } else {
+            hasher.update(b);
+            hasher.update(a);
+        }
+        hasher.finalize().to_vec()
+    }
+
+    #[test]
+    fn merkle_root_members() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let leaves: Vec<Vec<u8>> = ["juno1a", "juno1b", "juno1c", "juno1d"]
+            .iter()
+            .map(|addr| Sha256::digest(addr.as_bytes()).to_vec())
+            .collect();
+        let left = hash_pair(&leaves[0], &leaves[1]);
+        let right = hash_pair(&leaves[2], &leaves[3]);
+        let root = hex::encode(hash_pair(&left, &right));
+
+        // only admin can set a valid root
+        let msg = ExecuteMsg::UpdateMerkleRoot(Some(root.clone()));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        let bad_msg = ExecuteMsg::UpdateMerkleRoot(Some("abcd".to_string()));
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), bad_msg).unwrap_err();
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+        let res = query_config(deps.as_ref(), mock_env()).unwrap();
+        assert_eq!(res.merkle_root, Some(root));
+
+        let proof = vec![hex::encode(&leaves[3]), hex::encode(&left)];
+        let res = query_has_member(
+            deps.as_ref(),
+            "juno1c".to_string(),
+            Some(proof.clone()),
+            None,
+        )
+        .unwrap();
+        assert!(res.has_member);
+
+        // proof must match the member and is required for off chain members
+        let res = query_has_member(deps.as_ref(), "juno1d".to_string(), Some(proof), None).unwrap();
+        assert!(!res.has_member);
+        let res = query_has_member(deps.as_ref(), "juno1c".to_string(), None, None).unwrap();
+        assert!(!res.has_member);
+
+        // stored members don't need a proof
+        let res = query_has_member(deps.as_ref(), "adsfsa".to_string(), None, None).unwrap();
+        assert!(res.has_member);
+
+        // removing the root disables proofs
+        let msg = ExecuteMsg::UpdateMerkleRoot(None);
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+        let proof = vec![hex::encode(&leaves[3]), hex::encode(&left)];
+        let res = query_has_member(deps.as_ref(), "juno1c".to_string(), Some(proof), None).unwrap();
+        assert!(!res.has_member);
+    }
+
+    #[test]
+    fn ordered_stages() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        // stages can't overlap the previous stage
+        let mut stage_msg = AddStageMsg {
+            members: vec!["stage1".to_string()],
+            start_time: START_TIME,
+            end_time: END_TIME.plus_seconds(100),
+            unit_price: coin(UNIT_AMOUNT * 2, NATIVE_DENOM),
+            per_address_limit: 2,
+        };
+        let info = mock_info(ADMIN, &[]);
+        let msg = ExecuteMsg::AddStage(stage_msg.clone());
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+
+        stage_msg.start_time = END_TIME;
+        let msg = ExecuteMsg::AddStage(stage_msg);
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let res = query_stages(deps.as_ref()).unwrap();
+        assert_eq!(res.stages.len(), 2);
+        assert_eq!(res.stages[1].stage_id, 1);
+        assert_eq!(res.stages[1].num_members, 1);
+
+        // base stage can't be extended into the next stage
+        let msg = ExecuteMsg::UpdateEndTime(END_TIME.plus_seconds(1));
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+
+        // members are tracked per stage
+        let msg = ExecuteMsg::AddMembers(AddMembersMsg {
+            to_add: vec!["stage1b".to_string()],
+            allocations: None,
+            stage_id: Some(1),
+            skip_invalid: None,
+        });
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let res = query_members(deps.as_ref(), None, None, Some(1)).unwrap();
+        assert_eq!(
+            res.members,
+            vec!["stage1".to_string(), "stage1b".to_string()]
+        );
+        let res = query_has_member(deps.as_ref(), "stage1".to_string(), None, Some(1)).unwrap();
+        assert!(res.has_member);
+        let res = query_has_member(deps.as_ref(), "stage1".to_string(), None, None).unwrap();
+        assert!(!res.has_member);
+
+        // active stage follows block time
+        let res = query_active_stage(deps.as_ref(), mock_env()).unwrap();
+        assert_eq!(res.stage, None);
+
+        let mut env = mock_env();
+        env.block.time = START_TIME;
+        let res = query_active_stage(deps.as_ref(), env.clone()).unwrap();
+        assert_eq!(res.stage.unwrap().stage_id, 0);
+
+        env.block.time = END_TIME.plus_seconds(1);
+        let stage = query_active_stage(deps.as_ref(), env.clone())
+            .unwrap()
+            .stage
+            .unwrap();
+        assert_eq!(stage.stage_id, 1);
+        assert_eq!(stage.unit_price, coin(UNIT_AMOUNT * 2, NATIVE_DENOM));
+        assert_eq!(stage.per_address_limit, 2);
+
+        // the whitelist stays active through every stage
+        let res = query_is_active(deps.as_ref(), env.clone()).unwrap();
+        assert!(res.is_active);
+        let res = query_config(deps.as_ref(), env.clone()).unwrap();
+        assert!(res.is_active);
+        let res = query_has_ended(deps.as_ref(), env.clone()).unwrap();
+        assert!(!res.has_ended);
+
+        env.block.time = END_TIME.plus_seconds(100);
+        let res = query_active_stage(deps.as_ref(), env.clone()).unwrap();
+        assert_eq!(res.stage, None);
+        let res = query_is_active(deps.as_ref(), env.clone()).unwrap();
+        assert!(!res.is_active);
+        let res = query_has_ended(deps.as_ref(), env).unwrap();
+        assert!(res.has_ended);
+    }
+
+    #[test]
+    fn member_allocations() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let info = mock_info(ADMIN, &[]);
+        let msg = ExecuteMsg::AddMembers(AddMembersMsg {
+            to_add: vec!["member1".to_string()],
+            allocations: Some(vec![("partner1".to_string(), 0)]),
+            stage_id: None,
+            skip_invalid: None,
+        });
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+
+        let msg = ExecuteMsg::AddMembers(AddMembersMsg {
+            to_add: vec!["member1".to_string()],
+            allocations: Some(vec![("partner1".to_string(), 5)]),
+            stage_id: None,
+            skip_invalid: None,
+        });
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let res = query_config(deps.as_ref(), mock_env()).unwrap();
+        assert_eq!(res.num_members, 3);
+
+        let res = query_has_member(deps.as_ref(), "partner1".to_string(), None, None).unwrap();
+        assert!(res.has_member);
+        let res = query_member_allocation(deps.as_ref(), mock_env(), "partner1".to_string(), None)
+            .unwrap();
+        assert_eq!(res.allocation, 5);
+
+        // members without an allocation use the stage limit
+        let res = query_member_allocation(deps.as_ref(), mock_env(), "member1".to_string(), None)
+            .unwrap();
+        assert_eq!(res.allocation, 1);
+
+        // removing a member drops the allocation
+        let msg = ExecuteMsg::RemoveMembers(RemoveMembersMsg {
+            to_remove: vec!["partner1".to_string()],
+            stage_id: None,
+        });
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let res = query_member_allocation(deps.as_ref(), mock_env(), "partner1".to_string(), None)
+            .unwrap();
+        assert_eq!(res.allocation, 1);
+    }
+
+    #[test]
+    fn record_mints() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let info = mock_info(ADMIN, &[]);
+        let msg = ExecuteMsg::AddMembers(AddMembersMsg {
+            to_add: vec![],
+            allocations: Some(vec![("partner1".to_string(), 2)]),
+            stage_id: None,
+            skip_invalid: None,
+        });
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let minter = mock_info("minter", &[]);
+        let msg = ExecuteMsg::UpdateMinters(vec!["minter".to_string()]);
+        execute(deps.as_mut(), mock_env(), minter.clone(), msg.clone()).unwrap_err();
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let record = ExecuteMsg::RecordMint {
+            member: "partner1".to_string(),
+            count: 1,
+        };
+        let mut env = mock_env();
+        env.block.time = START_TIME;
+
+        // only minters can record, and only while a stage is active
+        execute(deps.as_mut(), env.clone(), info, record.clone()).unwrap_err();
+        execute(deps.as_mut(), mock_env(), minter.clone(), record.clone()).unwrap_err();
+
+        execute(deps.as_mut(), env.clone(), minter.clone(), record.clone()).unwrap();
+        let res =
+            query_remaining_allocation(deps.as_ref(), env.clone(), "partner1".to_string(), None)
+                .unwrap();
+        assert_eq!(res.used, 1);
+        assert_eq!(res.remaining, 1);
+
+        let msg = ExecuteMsg::RecordMint {
+            member: "partner1".to_string(),
+            count: 2,
+        };
+        execute(deps.as_mut(), env.clone(), minter.clone(), msg).unwrap_err();
+        execute(deps.as_mut(), env.clone(), minter.clone(), record).unwrap();
+        let res =
+            query_remaining_allocation(deps.as_ref(), env.clone(), "partner1".to_string(), None)
+                .unwrap();
+        assert_eq!(res.used, 2);
+        assert_eq!(res.remaining, 0);
+
+        // counts that would overflow the used allocation are rejected
+        let msg = ExecuteMsg::RecordMint {
+            member: "partner1".to_string(),
+            count: u32::MAX,
+        };
+        let err = execute(deps.as_mut(), env, minter, msg).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ContractError::MaxPerAddressLimitExceeded {}.to_string()
+        );
+    }
+
+    #[test]
+    fn allocations_default_to_active_stage() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let info = mock_info(ADMIN, &[]);
+        let msg = ExecuteMsg::AddStage(AddStageMsg {
+            members: vec![],
+            start_time: END_TIME,
+            end_time: END_TIME.plus_seconds(100),
+            unit_price: coin(UNIT_AMOUNT * 2, NATIVE_DENOM),
+            per_address_limit: 2,
+        });
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::AddMembers(AddMembersMsg {
+            to_add: vec![],
+            allocations: Some(vec![("partner1".to_string(), 3)]),
+            stage_id: Some(1),
+            skip_invalid: None,
+        });
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::UpdateMinters(vec!["minter".to_string()]);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = END_TIME.plus_seconds(1);
+        let msg = ExecuteMsg::RecordMint {
+            member: "partner1".to_string(),
+            count: 1,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("minter", &[]), msg).unwrap();
+
+        // queries without a stage follow the active stage
+        let res = query_member_allocation(deps.as_ref(), env.clone(), "partner1".to_string(), None)
+            .unwrap();
+        assert_eq!(res.allocation, 3);
+        let res =
+            query_remaining_allocation(deps.as_ref(), env, "partner1".to_string(), None).unwrap();
+        assert_eq!(res.used, 1);
+        assert_eq!(res.remaining, 2);
+
+        // and the base stage when no stage is open
+        let res = query_member_allocation(deps.as_ref(), mock_env(), "partner1".to_string(), None)
+            .unwrap();
+        assert_eq!(res.allocation, 1);
+        let res =
+            query_remaining_allocation(deps.as_ref(), mock_env(), "partner1".to_string(), None)
+                .unwrap();
+        assert_eq!(res.used, 0);
+    }
+
+    #[test]
+    fn gate_rules() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { contract_addr, msg } if contract_addr == "collection" => {
+                let tokens = match from_binary(msg).unwrap() {
+                    Cw721QueryMsg::Tokens { owner, .. } if owner == "holder" => {
+                        vec!["1".to_string()]
+                    }
+                    _ => vec![],
+                };
+                let res = to_binary(&TokensResponse { tokens }).unwrap();
+                SystemResult::Ok(ContractResult::Ok(res))
+            }
+            WasmQuery::Smart { contract_addr, msg } if contract_addr == "token" => {
+                let balance = match from_binary(msg).unwrap() {
+                    Cw20QueryMsg::Balance { address } if address == "whale" => Uint128::new(1000),
+                    _ => Uint128::new(10),
+                };
+                let res = to_binary(&BalanceResponse { balance }).unwrap();
+                SystemResult::Ok(ContractResult::Ok(res))
+            }
+            _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: "wasm".to_string(),
+            }),
+        });
+
+        let info = mock_info(ADMIN, &[]);
+        let msg = ExecuteMsg::UpdateGateRules {
+            stage_id: None,
+            rules: vec![GateRule::Cw20 {
+                token: Addr::unchecked("token"),
+                min_balance: Uint128::zero(),
+            }],
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+
+        let rules = vec![
+            GateRule::Cw721(Addr::unchecked("collection")),
+            GateRule::Cw20 {
+                token: Addr::unchecked("token"),
+                min_balance: Uint128::new(100),
+            },
+        ];
+        let msg = ExecuteMsg::UpdateGateRules {
+            stage_id: None,
+            rules: rules.clone(),
+        };
+        let anyone = mock_info("anyone", &[]);
+        execute(deps.as_mut(), mock_env(), anyone, msg.clone()).unwrap_err();
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let res = query_gate_rules(deps.as_ref(), None).unwrap();
+        assert_eq!(res.rules, rules);
+
+        // static members and gated holders are both members
+        let res = query_has_member(deps.as_ref(), "adsfsa".to_string(), None, None).unwrap();
+        assert!(res.has_member);
+        let res = query_has_member(deps.as_ref(), "holder".to_string(), None, None).unwrap();
+        assert!(res.has_member);
+        let res = query_has_member(deps.as_ref(), "whale".to_string(), None, None).unwrap();
+        assert!(res.has_member);
+        let res = query_has_member(deps.as_ref(), "nobody".to_string(), None, None).unwrap();
+        assert!(!res.has_member);
+    }
+
+    #[test]
+    fn admin_handover_and_operators() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let admin = mock_info(ADMIN, &[]);
+        let new_admin = mock_info("new_admin", &[]);
+        let operator = mock_info("operator", &[]);
+
+        let msg = ExecuteMsg::UpdateAdmins { admins: vec![] };
+        execute(deps.as_mut(), mock_env(), admin.clone(), msg).unwrap_err();
+        let msg = ExecuteMsg::UpdateAdmins {
+            admins: vec!["new_admin".to_string()],
+        };
+        execute(deps.as_mut(), mock_env(), new_admin.clone(), msg.clone()).unwrap_err();
+        execute(deps.as_mut(), mock_env(), admin.clone(), msg).unwrap();
+
+        // admins only change once a proposed admin accepts
+        let res = query_admins(deps.as_ref()).unwrap();
+        assert_eq!(res.admins, vec![ADMIN.to_string()]);
+        assert_eq!(res.pending_admins, vec!["new_admin".to_string()]);
+
+        let msg = ExecuteMsg::AcceptAdmins {};
+        execute(deps.as_mut(), mock_env(), operator.clone(), msg.clone()).unwrap_err();
+        execute(deps.as_mut(), mock_env(), new_admin.clone(), msg).unwrap();
+        let res = query_admins(deps.as_ref()).unwrap();
+        assert_eq!(res.admins, vec!["new_admin".to_string()]);
+        assert!(res.pending_admins.is_empty());
+
+        let msg = ExecuteMsg::UpdatePerAddressLimit(2);
+        execute(deps.as_mut(), mock_env(), admin, msg).unwrap_err();
+
+        // operators manage members but not settings
+        let msg = ExecuteMsg::UpdateOperators(vec!["operator".to_string()]);
+        execute(deps.as_mut(), mock_env(), new_admin, msg).unwrap();
+
+        let msg = ExecuteMsg::AddMembers(AddMembersMsg {
+            to_add: vec!["member1".to_string()],
+            allocations: None,
+            stage_id: None,
+            skip_invalid: None,
+        });
+        execute(deps.as_mut(), mock_env(), operator.clone(), msg).unwrap();
+        let res = query_has_member(deps.as_ref(), "member1".to_string(), None, None).unwrap();
+        assert!(res.has_member);
+
+        let msg = ExecuteMsg::UpdatePerAddressLimit(2);
+        execute(deps.as_mut(), mock_env(), operator, msg).unwrap_err();
+    }
+
+    #[test]
+    fn add_members_skip_invalid() {
+        let mut deps = mock_dependencies();
+        setup_contract(deps.as_mut());
+
+        let add_msg = AddMembersMsg {
+            to_add: vec![
+                "adsfsa".to_string(),
+                "ab".to_string(),
+                "member1".to_string(),
+            ],
+            allocations: Some(vec![("partner1".to_string(), 0)]),
+            stage_id: None,
+            skip_invalid: None,
+        };
+        let info = mock_info(ADMIN, &[]);
+        let msg = ExecuteMsg::AddMembers(add_msg.clone());
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+
+        // duplicate and invalid members are reported instead of failing the batch
+        let msg = ExecuteMsg::AddMembers(AddMembersMsg {
+            skip_invalid: Some(true),
+            ..add_msg
+        });
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert!(res.attributes.contains(&Attribute::new("num_added", "1")));
+        assert!(res
+            .attributes
+            .contains(&Attribute::new("skipped_duplicates", "adsfsa")));
+        assert!(res
+            .attributes
+            .contains(&Attribute::new("skipped_invalid", "ab,partner1")));
+
+        let res = query_config(deps.as_ref(), mock_env()).unwrap();
+        assert_eq!(res.num_members, 2);
+    }
+
+    #[test]
+    fn add_members_skip_over_limit() {
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg {
+            members: vec!["adsfsa".to_string()],
+            start_time: START_TIME,
+            end_time: END_TIME,
+            unit_price: coin(UNIT_AMOUNT, NATIVE_DENOM),
+            per_address_limit: 1,
+            member_limit: 3,
+            merkle_root: None,
+        };
+        let info = mock_info(ADMIN, &[coin(100_000_000, "ujuno")]);
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+
+        let add_msg = AddMembersMsg {
+            to_add: vec![
+                "member1".to_string(),
+                "member2".to_string(),
+                "member3".to_string(),
+            ],
+            allocations: Some(vec![("partner1".to_string(), 2)]),
+            stage_id: None,
+            skip_invalid: None,
+        };
+        let add = ExecuteMsg::AddMembers(add_msg.clone());
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), add).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ContractError::MembersExceeded {
+                expected: 3,
+                actual: 3
+            }
+            .to_string()
+        );
+
+        // members past the limit are reported instead of failing the batch
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::AddMembers(AddMembersMsg {
+            skip_invalid: Some(true),
+            ..add_msg
+        });
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert!(res.attributes.contains(&Attribute::new("num_added", "2")));
+        assert!(res
+            .attributes
+            .contains(&Attribute::new("skipped_over_limit", "member3,partner1")));
+
+        let res = query_config(deps.as_ref(), mock_env()).unwrap();
+        assert_eq!(res.num_members, 3);
     }
 }
@@ -10,12 +10,18 @@ pub enum ContractError {
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("InvalidAdmins: {0}")]
+    InvalidAdmins(String),
+
     #[error("AlreadyStarted")]
     AlreadyStarted {},
 
     #[error("DuplicateMember: {0}")]
     DuplicateMember(String),
 
+    #[error("NoActiveStage")]
+    NoActiveStage {},
+
     #[error("NoMemberFound: {0}")]
     NoMemberFound(String),
 
@@ -37,6 +43,12 @@ pub enum ContractError {
     #[error("Max minting limit per address exceeded")]
     MaxPerAddressLimitExceeded {},
 
+    #[error("InvalidGateRules: {0}")]
+    InvalidGateRules(String),
+
+    #[error("InvalidMerkleRoot: {0}")]
+    InvalidMerkleRoot(String),
+
     #[error("InvalidUnitPrice {0}")]
     InvalidUnitPrice(u128),
 
@@ -1,3 +1,4 @@
+use crate::state::GateRule;
 use cosmwasm_std::{Coin, Timestamp};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -10,6 +11,7 @@ pub struct InstantiateMsg {
     pub unit_price: Coin,
     pub per_address_limit: u32,
     pub member_limit: u32,
+    pub merkle_root: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -21,32 +23,91 @@ pub enum ExecuteMsg {
     RemoveMembers(RemoveMembersMsg),
     UpdatePerAddressLimit(u32),
     IncreaseMemberLimit(u32),
+    UpdateMerkleRoot(Option<String>),
+    AddStage(AddStageMsg),
+    UpdateMinters(Vec<String>),
+    /// Propose a new set of admins, which takes effect once one of them accepts
+    UpdateAdmins {
+        admins: Vec<String>,
+    },
+    AcceptAdmins {},
+    UpdateOperators(Vec<String>),
+    UpdateGateRules {
+        stage_id: Option<u32>,
+        rules: Vec<GateRule>,
+    },
+    /// Called by minters to use up a member allocation in the active stage
+    RecordMint {
+        member: String,
+        count: u32,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AddMembersMsg {
     pub to_add: Vec<String>,
+    /// Members added with their own (address, limit) allocation
+    pub allocations: Option<Vec<(String, u32)>>,
+    /// Defaults to the base stage
+    pub stage_id: Option<u32>,
+    /// Skip duplicate and invalid members instead of failing, listing them in the response.
+    /// Members past the member limit are listed as skipped over the limit.
+    pub skip_invalid: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct RemoveMembersMsg {
     pub to_remove: Vec<String>,
+    /// Defaults to the base stage
+    pub stage_id: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AddStageMsg {
+    pub members: Vec<String>,
+    pub start_time: Timestamp,
+    pub end_time: Timestamp,
+    pub unit_price: Coin,
+    pub per_address_limit: u32,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
+    /// Whether the base stage has started
     HasStarted {},
+    /// Whether the last stage has ended
     HasEnded {},
+    /// Whether any stage is active
     IsActive {},
     Members {
         start_after: Option<String>,
         limit: Option<u32>,
+        stage_id: Option<u32>,
     },
     HasMember {
         member: String,
+        /// Hex encoded sibling hashes proving membership against the Merkle root
+        proof: Option<Vec<String>>,
+        stage_id: Option<u32>,
+    },
+    /// Defaults to the active stage, or the base stage when no stage is open
+    MemberAllocation {
+        member: String,
+        stage_id: Option<u32>,
+    },
+    /// Defaults to the active stage, or the base stage when no stage is open
+    RemainingAllocation {
+        member: String,
+        stage_id: Option<u32>,
+    },
+    GateRules {
+        stage_id: Option<u32>,
     },
     Config {},
+    Admins {},
+    ActiveStage {},
+    Stages {},
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
@@ -59,6 +120,31 @@ pub struct HasMemberResponse {
     pub has_member: bool,
 }
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct MemberAllocationResponse {
+    pub member: String,
+    pub allocation: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct RemainingAllocationResponse {
+    pub member: String,
+    pub used: u32,
+    pub remaining: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct GateRulesResponse {
+    pub rules: Vec<GateRule>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AdminsResponse {
+    pub admins: Vec<String>,
+    pub pending_admins: Vec<String>,
+    pub operators: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct HasEndedResponse {
     pub has_ended: bool,
@@ -87,5 +173,28 @@ pub struct ConfigResponse {
     pub start_time: Timestamp,
     pub end_time: Timestamp,
     pub unit_price: Coin,
+    /// Whether any stage is active, the other fields describe the base stage
     pub is_active: bool,
+    pub merkle_root: Option<String>,
+    pub minters: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StageResponse {
+    pub stage_id: u32,
+    pub start_time: Timestamp,
+    pub end_time: Timestamp,
+    pub unit_price: Coin,
+    pub per_address_limit: u32,
+    pub num_members: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ActiveStageResponse {
+    pub stage: Option<StageResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StagesResponse {
+    pub stages: Vec<StageResponse>,
 }
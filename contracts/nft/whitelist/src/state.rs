@@ -1,18 +1,72 @@
-use cosmwasm_std::{Addr, Coin, Timestamp};
+use cosmwasm_std::{Addr, Coin, Timestamp, Uint128};
 use cw_storage_plus::{Item, Map};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
-    pub admin: Addr,
+    pub admins: Vec<Addr>,
+    /// Addresses that can add and remove members but not change times or prices
+    pub operators: Vec<Addr>,
     pub start_time: Timestamp,
     pub end_time: Timestamp,
     pub num_members: u32,
     pub unit_price: Coin,
     pub per_address_limit: u32,
     pub member_limit: u32,
+    /// Hex encoded sha256 Merkle root of additional base stage members that are not stored on chain
+    pub merkle_root: Option<String>,
+    /// Minter contracts allowed to record whitelist mints
+    pub minters: Vec<Addr>,
+}
+
+impl Config {
+    pub fn is_admin(&self, addr: &Addr) -> bool {
+        self.admins.contains(addr)
+    }
+
+    pub fn can_manage_members(&self, addr: &Addr) -> bool {
+        self.is_admin(addr) || self.operators.contains(addr)
+    }
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
+/// Admins proposed by `UpdateAdmins`, waiting for one of them to accept
+pub const PENDING_ADMINS: Item<Vec<Addr>> = Item::new("pending_admins");
 pub const WHITELIST: Map<Addr, bool> = Map::new("wl");
+
+/// A mint window with its own members, price and limit. The window in `Config`
+/// is stage 0 and its members are stored in `WHITELIST`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Stage {
+    pub start_time: Timestamp,
+    pub end_time: Timestamp,
+    pub unit_price: Coin,
+    pub per_address_limit: u32,
+    pub num_members: u32,
+}
+
+/// Stages following the base stage, ordered by stage id starting at 1
+pub const STAGES: Map<u32, Stage> = Map::new("stages");
+pub const STAGE_MEMBERS: Map<(u32, Addr), bool> = Map::new("stage_members");
+
+/// Per member limits overriding the stage `per_address_limit`, keyed by (stage id, member)
+pub const MEMBER_ALLOCATIONS: Map<(u32, Addr), u32> = Map::new("member_allocations");
+
+/// Membership resolved at query time from what an address holds
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GateRule {
+    /// Holders of a token in the cw721 collection
+    Cw721(Addr),
+    /// Holders of at least `min_balance` of the cw20 token
+    Cw20 { token: Addr, min_balance: Uint128 },
+    /// Owners with at least `min_staked` tokens staked in the nft-vault
+    NftVault { vault: Addr, min_staked: u64 },
+}
+
+/// Gate rules of each stage, OR'd with the stage members
+pub const GATE_RULES: Map<u32, Vec<GateRule>> = Map::new("gate_rules");
+
+/// Mints recorded by minters against a member allocation, keyed by (stage id, member)
+pub const MEMBER_MINTS: Map<(u32, Addr), u32> = Map::new("member_mints");